@@ -0,0 +1,37 @@
+use oxrdf::NamedNode;
+use oxrdf::Variable;
+use zarrdf::bgp::GraphPattern;
+use zarrdf::bgp::TriplePattern;
+use zarrdf::storage::Storage;
+use zarrs::filesystem::FilesystemStore;
+
+const RDF: &str = "resources/input.ttl";
+const OUTPUT: &str = "output.zarr";
+const ENROLLED_IN: &str = "http://example.org/enrolledIn";
+const NAME: &str = "http://example.org/name";
+
+fn main() -> Result<(), zarrdf::error::ZarrDfError> {
+    let store = FilesystemStore::new(OUTPUT)?;
+    let storage = Storage::from_rdf_and_store(RDF, store)?;
+
+    // ?s :enrolledIn ?c . ?c :name ?n
+    let patterns = [
+        TriplePattern::new(
+            Variable::new_unchecked("s"),
+            NamedNode::new_unchecked(ENROLLED_IN),
+            Variable::new_unchecked("c"),
+        ),
+        TriplePattern::new(
+            Variable::new_unchecked("c"),
+            NamedNode::new_unchecked(NAME),
+            Variable::new_unchecked("n"),
+        ),
+    ];
+    for solution in storage.solutions(&patterns) {
+        for (variable, value) in solution?.iter() {
+            print!("{variable} = {value}\t");
+        }
+        println!();
+    }
+    Ok(())
+}
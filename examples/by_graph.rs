@@ -0,0 +1,16 @@
+use zarrdf::query::BasicGraphPattern;
+use zarrdf::storage::Storage;
+use zarrs::filesystem::FilesystemStore;
+
+const RDF: &str = "resources/input.trig";
+const OUTPUT: &str = "output.zarr";
+const GRAPH: &str = "<http://example.org/provenance1>";
+
+fn main() -> Result<(), zarrdf::error::ZarrDfError> {
+    let store = FilesystemStore::new(OUTPUT)?;
+    let storage = Storage::from_rdf_and_store(RDF, store)?;
+    let graph = storage.dictionary.get_graph_idx(GRAPH);
+    let quads = storage.quads_matching(None, None, None, graph)?;
+    println!("{:?}", quads);
+    Ok(())
+}
@@ -0,0 +1,11 @@
+use zarrdf::storage::Storage;
+use zarrs::filesystem::FilesystemStore;
+
+fn main() -> Result<(), zarrdf::error::ZarrDfError> {
+    let args: Vec<_> = std::env::args().collect();
+    let zarr = args.get(1).expect("missing zarr file");
+    let rdf = args.get(2).expect("missing rdf file");
+    let store = FilesystemStore::new(zarr)?;
+    Storage::from_zarr(store)?.export_to_path(rdf)?;
+    Ok(())
+}
@@ -0,0 +1,31 @@
+use zarrdf::sparql::QueryResults;
+use zarrdf::storage::Storage;
+use zarrs::filesystem::FilesystemStore;
+
+const RDF: &str = "resources/input.ttl";
+const OUTPUT: &str = "output.zarr";
+const QUERY: &str = "
+    PREFIX : <http://example.org/>
+    SELECT ?s (COUNT(?name) AS ?names) ?course
+    WHERE {
+        ?s :name ?name
+        OPTIONAL { ?s :enrolledIn ?c . ?c :name ?course }
+    }
+    GROUP BY ?s ?course
+    ORDER BY DESC(?names)
+";
+
+fn main() -> Result<(), zarrdf::error::ZarrDfError> {
+    let store = FilesystemStore::new(OUTPUT)?;
+    let storage = Storage::from_rdf_and_store(RDF, store)?;
+
+    if let QueryResults::Solutions(_, solutions) = storage.sparql(QUERY)? {
+        for solution in solutions {
+            for (variable, value) in solution.iter() {
+                print!("{variable} = {value}\t");
+            }
+            println!();
+        }
+    }
+    Ok(())
+}
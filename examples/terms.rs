@@ -0,0 +1,18 @@
+use oxrdf::Subject;
+use zarrdf::query::TermPattern;
+use zarrdf::storage::Storage;
+use zarrs::filesystem::FilesystemStore;
+
+const RDF: &str = "resources/input.ttl";
+const OUTPUT: &str = "output.zarr";
+const SUBJECT: &str = "http://example.org/a";
+
+fn main() -> Result<(), zarrdf::error::ZarrDfError> {
+    let store = FilesystemStore::new(OUTPUT)?;
+    let storage = Storage::from_rdf_and_store(RDF, store)?;
+    let subject = Subject::NamedNode(oxrdf::NamedNode::new_unchecked(SUBJECT));
+    for triple in storage.triples_matching_terms(Some(&subject), None, None) {
+        println!("{}", triple?);
+    }
+    Ok(())
+}
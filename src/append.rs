@@ -0,0 +1,344 @@
+use std::mem;
+
+use zarrs::array::Array;
+use zarrs::group::GroupBuilder;
+use zarrs::storage::ReadableWritableStorageTraits;
+use zarrs::storage::StorePrefix;
+
+use crate::config::Config;
+use crate::dictionary::Delta;
+use crate::error::ZarrDfError;
+use crate::index::Index;
+use crate::index::COLUMNS;
+use crate::query::BasicGraphPattern;
+use crate::sort::ExternalSorter;
+use crate::storage::base;
+use crate::storage::DeltaIndexes;
+use crate::storage::Storage;
+use crate::storage::COMMITTED_ATTRIBUTE;
+use crate::storage::DELTAS;
+use crate::storage::DICTIONARY;
+use crate::storage::GENERATION_ATTRIBUTE;
+use crate::utils::serialize_graph_name;
+use crate::utils::serialize_term;
+use crate::Quad;
+
+impl<S: ReadableWritableStorageTraits + 'static> Storage<S> {
+    /// Adds the quads of an RDF file, whose delta is encoded like the arrays
+    /// of the store, see [`append_with_config`](Storage::append_with_config).
+    pub fn append(&mut self, rdf_path: &str) -> Result<(), ZarrDfError> {
+        self.append_with_config(rdf_path, &self.config()?)
+    }
+
+    /// Explicit configuration version of [`append`](Storage::append).
+    ///
+    /// Appending writes a delta: the sections of the terms the dictionary
+    /// does not hold yet, identified after all the others, and the three
+    /// indexes of the quads the store does not hold yet, under the
+    /// [`DELTAS`] group. Queries merge the chunks of the deltas with the base
+    /// ones until [`compact`](Storage::compact) folds them back.
+    ///
+    /// The input is streamed twice, like by
+    /// [`from_rdf_and_store_with_config`](Storage::from_rdf_and_store_with_config),
+    /// and the configuration only applies to the arrays of the delta.
+    pub fn append_with_config(
+        &mut self,
+        rdf_path: &str,
+        config: &Config,
+    ) -> Result<(), ZarrDfError> {
+        let quads = || Ok::<_, ZarrDfError>(Self::read_rdf_path(rdf_path, config)?.map(Ok));
+        self.transaction(|storage| storage.append_quads(quads, config))
+    }
+
+    /// Appends the quads returned by `quads`, which is called once per pass,
+    /// within a [`transaction`](Storage::transaction).
+    pub(crate) fn append_quads<I>(
+        &mut self,
+        quads: impl Fn() -> Result<I, ZarrDfError>,
+        config: &Config,
+    ) -> Result<(), ZarrDfError>
+    where
+        I: Iterator<Item = Result<[String; 4], ZarrDfError>>,
+    {
+        let delta = self.build_delta(quads()?, config)?;
+        self.dictionary.push_delta(delta);
+        match self.write_delta_indexes(quads, config)? {
+            Some(added) => self.deltas.push(DeltaIndexes {
+                added: Some(added),
+                removed: None,
+            }),
+            // Quads the store already holds add nothing, not even terms
+            None => {
+                self.dictionary.pop_delta();
+            }
+        }
+        Ok(())
+    }
+
+    /// Sorts the terms of `quads` that the dictionary does not hold, position
+    /// by position.
+    fn build_delta(
+        &self,
+        quads: impl Iterator<Item = Result<[String; 4], ZarrDfError>>,
+        config: &Config,
+    ) -> Result<Delta, ZarrDfError> {
+        let memory_budget = config.memory_budget / 4;
+        let mut subjects = ExternalSorter::new(memory_budget, config);
+        let mut predicates = ExternalSorter::new(memory_budget, config);
+        let mut objects = ExternalSorter::new(memory_budget, config);
+        let mut graphs = ExternalSorter::new(memory_budget, config);
+
+        let dictionary = &self.dictionary;
+        for quad in quads {
+            let [subject, predicate, object, graph] = quad?;
+            if dictionary.get_subject_idx(&subject).is_none() {
+                subjects.push(subject)?;
+            }
+            if dictionary.get_predicate_idx(&predicate).is_none() {
+                predicates.push(predicate)?;
+            }
+            if dictionary.get_object_idx(&object).is_none() {
+                objects.push(object)?;
+            }
+            // The default graph is always known
+            if dictionary.get_graph_idx(&graph).is_none() {
+                graphs.push(graph)?;
+            }
+        }
+
+        let subjects = subjects.finish()?;
+        let predicates = predicates.finish()?;
+        let objects = objects.finish()?;
+        let graphs = graphs.finish()?;
+        let delta = Delta::new(
+            subjects.iter()?,
+            predicates.iter()?,
+            objects.iter()?,
+            graphs.iter()?,
+        )?;
+
+        Ok(delta)
+    }
+
+    /// Writes the last delta of the dictionary along with the indexes of the
+    /// quads the store does not hold yet, or nothing if there are none.
+    fn write_delta_indexes<I>(
+        &self,
+        quads: impl Fn() -> Result<I, ZarrDfError>,
+        config: &Config,
+    ) -> Result<Option<[Array<S>; 3]>, ZarrDfError>
+    where
+        I: Iterator<Item = Result<[String; 4], ZarrDfError>>,
+    {
+        let mut sorter = ExternalSorter::new(config.memory_budget, config);
+        for quad in Self::encode(quads()?, &self.dictionary) {
+            sorter.push(quad?)?;
+        }
+        let sorted = sorter.finish()?;
+        let mut unseen = self.filter_stored(sorted.iter()?, false)?.peekable();
+        if unseen.peek().is_none() {
+            return Ok(None);
+        }
+        let sorted = Self::sort_quads(unseen, config)?;
+
+        let delta = self.dictionary.deltas().last();
+        let delta = delta.expect("the delta is pushed before its quads are encoded");
+        let prefix = self.create_delta(delta, config)?;
+        let arrays = Self::write_indexes(
+            &sorted,
+            &prefix,
+            self.store.clone(),
+            &self.dictionary,
+            config,
+            true,
+        )?;
+        Ok(Some(arrays))
+    }
+
+    /// Creates the group of the next delta, writing the sections of its new
+    /// terms, and returns its path.
+    pub(crate) fn create_delta(
+        &self,
+        delta: &Delta,
+        config: &Config,
+    ) -> Result<String, ZarrDfError> {
+        let store = self.store.clone();
+        let prefix = format!("{}/{DELTAS}/{}", base(self.generation), self.deltas.len());
+        // A change that failed may have left arrays there
+        store.erase_prefix(&group_prefix(&prefix))?;
+        GroupBuilder::new()
+            .build(store.clone(), &prefix)?
+            .store_metadata()?;
+        Self::write_delta(delta, &prefix, store, config)?;
+        Ok(prefix)
+    }
+
+    /// Runs `change`, which may write several deltas, and commits them all at
+    /// once by storing their number in the [`DELTAS`] group. If it fails,
+    /// none of them is committed and the storage is left as it was.
+    pub(crate) fn transaction<T>(
+        &mut self,
+        change: impl FnOnce(&mut Self) -> Result<T, ZarrDfError>,
+    ) -> Result<T, ZarrDfError> {
+        let committed = self.deltas.len();
+        let result = change(self).and_then(|value| {
+            if self.deltas.len() > committed {
+                let attributes = [(COMMITTED_ATTRIBUTE.to_string(), self.deltas.len().into())];
+                GroupBuilder::new()
+                    .attributes(attributes.into_iter().collect())
+                    .build(
+                        self.store.clone(),
+                        &format!("{}/{DELTAS}", base(self.generation)),
+                    )?
+                    .store_metadata()?;
+            }
+            Ok(value)
+        });
+        if result.is_err() {
+            self.discard_deltas(committed);
+        }
+        result
+    }
+
+    /// Forgets every delta but the first `len` ones, leaving their arrays to
+    /// be overwritten by the next ones.
+    pub(crate) fn discard_deltas(&mut self, len: usize) {
+        self.deltas.truncate(len);
+        self.dictionary.truncate_deltas(len);
+    }
+
+    /// Keeps from `quads`, sorted by subject, the ones the store holds if
+    /// `stored` is true and the others otherwise, fetching the chunk of
+    /// every subject once.
+    pub(crate) fn filter_stored<'a>(
+        &'a self,
+        quads: impl Iterator<Item = Result<Quad, ZarrDfError>> + 'a,
+        stored: bool,
+    ) -> Result<impl Iterator<Item = Result<Quad, ZarrDfError>> + 'a, ZarrDfError> {
+        let num_chunks = self.num_chunks(Index::Subject)?;
+        let sorted = self.sorted(Index::Subject);
+        let mut chunk: Option<(u64, Vec<u64>)> = None;
+        Ok(quads.filter_map(move |quad| {
+            let quad = match quad {
+                Ok(quad) => quad,
+                Err(error) => return Some(Err(error)),
+            };
+            // Rows of the subject index hold the rest of the quad
+            let [subject, rest @ ..] = quad;
+            if chunk.as_ref().is_none_or(|(key, _)| *key != subject) {
+                // Subjects added by the delta have no chunk yet
+                let rows = if subject < num_chunks {
+                    self.chunk_rows(Index::Subject, subject)
+                } else {
+                    Ok(Vec::new())
+                };
+                match rows {
+                    Ok(rows) => chunk = Some((subject, rows)),
+                    Err(error) => return Some(Err(error)),
+                }
+            }
+            let rows = chunk
+                .as_ref()
+                .map_or(&[][..], |(_, rows)| rows.as_chunks::<COLUMNS>().0);
+            let found = if sorted {
+                rows.binary_search(&rest).is_ok()
+            } else {
+                rows.contains(&rest)
+            };
+            (found == stored).then_some(Ok(quad))
+        }))
+    }
+
+    /// Folds the deltas of every [`append`](Storage::append) and
+    /// [`delete`](Storage::delete) back into the base dictionary and indexes,
+    /// with the dictionary layout of the store and the compression, filters
+    /// and sharding read from the metadata of its subject index.
+    ///
+    /// Removed quads are dropped along with the terms that no quad references
+    /// anymore, which renumbers the others, so every chunk is rewritten.
+    pub fn compact(&mut self) -> Result<(), ZarrDfError> {
+        self.compact_with_config(&self.config()?)
+    }
+
+    /// Explicit configuration version of [`compact`](Storage::compact).
+    ///
+    /// The quads are streamed twice from the store, like from an RDF file
+    /// by [`from_rdf_and_store_with_config`](Storage::from_rdf_and_store_with_config),
+    /// which renumbers every term. The new dictionary and indexes are written
+    /// next to the old ones, under the [`COMPACTED`](crate::storage::COMPACTED) group, and the root group
+    /// only points to them once they are complete, so a compaction that
+    /// fails leaves the store as it was. The arrays of the previous
+    /// generation are kept for the storages opened before, until they
+    /// [`refresh`](Storage::refresh), and the ones of the generation before
+    /// it are erased.
+    pub fn compact_with_config(&mut self, config: &Config) -> Result<(), ZarrDfError> {
+        if self.deltas.is_empty() {
+            return Ok(());
+        }
+        let quads = || {
+            let quads = self.quads_matching_iter(None, None, None, None);
+            Ok::<_, ZarrDfError>(quads.map(|quad| {
+                let quad = self.dictionary.quad(quad?)?;
+                Ok([
+                    serialize_term(quad.subject),
+                    serialize_term(quad.predicate),
+                    serialize_term(quad.object),
+                    serialize_graph_name(quad.graph_name),
+                ])
+            }))
+        };
+        let dictionary = Self::build_dictionary(quads()?, config)?;
+        let sorted = Self::sort_quads(Self::encode(quads()?, &dictionary), config)?;
+
+        // A compaction that failed may have left arrays there
+        let store = self.store.clone();
+        let generation = self.generation + 1;
+        let prefix = base(generation);
+        store.erase_prefix(&group_prefix(&prefix))?;
+        GroupBuilder::new()
+            .build(store.clone(), &prefix)?
+            .store_metadata()?;
+        Self::write_dictionary(&dictionary, &prefix, store.clone(), config)?;
+        let [subject, predicate, object] =
+            Self::write_indexes(&sorted, &prefix, store.clone(), &dictionary, config, false)?;
+
+        let attributes = [(GENERATION_ATTRIBUTE.to_string(), generation.into())];
+        GroupBuilder::new()
+            .attributes(attributes.into_iter().collect())
+            .build(store.clone(), "/")?
+            .store_metadata()?;
+        *self = Self {
+            dictionary,
+            subject: Some(subject),
+            predicate: Some(predicate),
+            object: Some(object),
+            deltas: Vec::new(),
+            generation,
+            store: store.clone(),
+            pools: mem::take(&mut self.pools),
+        };
+
+        // Storages still reading the generation before the previous one
+        // have had a whole compaction to refresh
+        match generation.checked_sub(2) {
+            None => {}
+            Some(0) => {
+                let groups = [Index::Subject, Index::Predicate, Index::Object]
+                    .map(|index| index.to_string())
+                    .into_iter()
+                    .chain([DICTIONARY.to_string(), DELTAS.to_string()]);
+                for group in groups {
+                    store.erase_prefix(&group_prefix(&group))?;
+                }
+            }
+            Some(generation) => store.erase_prefix(&group_prefix(&base(generation)))?,
+        }
+        Ok(())
+    }
+}
+
+/// Returns the prefix of the keys of the group at `path`.
+fn group_prefix(path: &str) -> StorePrefix {
+    let path = path.trim_start_matches('/');
+    StorePrefix::new(format!("{path}/")).expect("prefix is valid")
+}
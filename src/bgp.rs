@@ -0,0 +1,727 @@
+use std::cmp::Ordering;
+use std::iter;
+use std::iter::Peekable;
+use std::sync::Arc;
+
+use oxrdf::BlankNode;
+use oxrdf::GraphName;
+use oxrdf::Literal;
+use oxrdf::NamedNode;
+use oxrdf::Subject;
+use oxrdf::Term;
+use oxrdf::Variable;
+use zarrs::storage::ReadableWritableStorageTraits;
+
+use crate::dictionary::Dictionary;
+use crate::dictionary::TermId;
+use crate::dictionary::DEFAULT_GRAPH;
+use crate::error::ZarrDfError;
+use crate::index::Index;
+use crate::query::BasicGraphPattern;
+use crate::storage::Storage;
+use crate::utils::serialize_term;
+
+/// Subject, predicate or object of a [`TriplePattern`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PatternTerm {
+    Term(Term),
+    Variable(Variable),
+}
+
+impl From<Term> for PatternTerm {
+    fn from(term: Term) -> Self {
+        PatternTerm::Term(term)
+    }
+}
+
+impl From<Subject> for PatternTerm {
+    fn from(subject: Subject) -> Self {
+        PatternTerm::Term(subject.into())
+    }
+}
+
+impl From<NamedNode> for PatternTerm {
+    fn from(iri: NamedNode) -> Self {
+        PatternTerm::Term(iri.into())
+    }
+}
+
+impl From<BlankNode> for PatternTerm {
+    fn from(id: BlankNode) -> Self {
+        PatternTerm::Term(id.into())
+    }
+}
+
+impl From<Literal> for PatternTerm {
+    fn from(literal: Literal) -> Self {
+        PatternTerm::Term(literal.into())
+    }
+}
+
+impl From<Variable> for PatternTerm {
+    fn from(variable: Variable) -> Self {
+        PatternTerm::Variable(variable)
+    }
+}
+
+impl std::fmt::Display for PatternTerm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PatternTerm::Term(term) => write!(f, "{term}"),
+            PatternTerm::Variable(variable) => write!(f, "{variable}"),
+        }
+    }
+}
+
+/// A triple whose components may be variables.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TriplePattern {
+    pub subject: PatternTerm,
+    pub predicate: PatternTerm,
+    pub object: PatternTerm,
+}
+
+impl TriplePattern {
+    pub fn new(
+        subject: impl Into<PatternTerm>,
+        predicate: impl Into<PatternTerm>,
+        object: impl Into<PatternTerm>,
+    ) -> Self {
+        TriplePattern {
+            subject: subject.into(),
+            predicate: predicate.into(),
+            object: object.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for TriplePattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {}", self.subject, self.predicate, self.object)
+    }
+}
+
+/// Triple pattern in a named graph or, when `graph` is `None`, in the default
+/// graph.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct QuadPattern {
+    pub triple: TriplePattern,
+    pub graph: Option<PatternTerm>,
+}
+
+impl From<TriplePattern> for QuadPattern {
+    fn from(triple: TriplePattern) -> Self {
+        QuadPattern {
+            triple,
+            graph: None,
+        }
+    }
+}
+
+/// Graphs patterns are matched in, as given by the `FROM` and `FROM NAMED`
+/// clauses of a SPARQL query.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct QueryDataset {
+    /// Graphs whose merge is the default graph.
+    pub default: Vec<NamedNode>,
+    /// Graphs a pattern in a named graph may match.
+    pub named: Vec<NamedNode>,
+}
+
+/// Values of the variables of a pattern for one of its matches.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Solution {
+    variables: Arc<[Variable]>,
+    values: Vec<Option<Term>>,
+}
+
+impl Solution {
+    pub(crate) fn new(variables: Arc<[Variable]>, values: Vec<Option<Term>>) -> Self {
+        Solution { variables, values }
+    }
+
+    /// Variables of the pattern, in order of first appearance.
+    pub fn variables(&self) -> &[Variable] {
+        &self.variables
+    }
+
+    /// Returns the value of the variable named `variable`, without the
+    /// leading `?`.
+    pub fn get(&self, variable: &str) -> Option<&Term> {
+        let position = self.variables.iter().position(|v| v.as_str() == variable)?;
+        self.values[position].as_ref()
+    }
+
+    /// Returns the bound variables along with their values.
+    pub fn iter(&self) -> impl Iterator<Item = (&Variable, &Term)> {
+        self.variables
+            .iter()
+            .zip(&self.values)
+            .filter_map(|(variable, value)| Some((variable, value.as_ref()?)))
+    }
+}
+
+/// Evaluation of basic graph patterns, which are sets of triple patterns
+/// sharing variables.
+pub trait GraphPattern {
+    /// Returns the solutions of the patterns over the union of all the graphs
+    /// of the dataset, with each variable bound to the same term in every
+    /// pattern it occurs in.
+    fn solutions(
+        &self,
+        patterns: &[TriplePattern],
+    ) -> impl Iterator<Item = Result<Solution, ZarrDfError>> + '_;
+
+    /// Same as [`solutions`](GraphPattern::solutions), but each pattern is
+    /// matched in its graph, whose variable ranges over the named graphs of
+    /// `dataset`, or else in the default graph of `dataset`.
+    ///
+    /// Without a dataset, the default graph is the union of all the graphs
+    /// and every graph but the default one is named.
+    fn quad_solutions(
+        &self,
+        patterns: &[QuadPattern],
+        dataset: Option<&QueryDataset>,
+    ) -> impl Iterator<Item = Result<Solution, ZarrDfError>> + '_;
+}
+
+impl<S: ReadableWritableStorageTraits + 'static> GraphPattern for Storage<S> {
+    fn solutions(
+        &self,
+        patterns: &[TriplePattern],
+    ) -> impl Iterator<Item = Result<Solution, ZarrDfError>> + '_ {
+        let patterns: Vec<_> = patterns.iter().cloned().map(QuadPattern::from).collect();
+        self.evaluate_bgp(&patterns, &Arc::new(self.graphs(None)))
+    }
+
+    fn quad_solutions(
+        &self,
+        patterns: &[QuadPattern],
+        dataset: Option<&QueryDataset>,
+    ) -> impl Iterator<Item = Result<Solution, ZarrDfError>> + '_ {
+        self.evaluate_bgp(patterns, &Arc::new(self.graphs(dataset)))
+    }
+}
+
+/// Identifiers of the graphs of a [`QueryDataset`], where `None` stands for
+/// every graph.
+pub(crate) struct Graphs {
+    default: Option<Vec<TermId>>,
+    named: Option<Vec<TermId>>,
+}
+
+impl Graphs {
+    fn is_default(&self, graph: TermId) -> bool {
+        self.default.as_ref().is_none_or(|ids| ids.contains(&graph))
+    }
+
+    fn is_named(&self, graph: TermId) -> bool {
+        graph != DEFAULT_GRAPH && self.named.as_ref().is_none_or(|ids| ids.contains(&graph))
+    }
+}
+
+/// Component of a triple pattern whose constants have been looked up in the
+/// [`Dictionary`] and whose variables have been numbered.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Slot {
+    Constant(TermId),
+    Variable(usize),
+}
+
+/// Encoded [`QuadPattern`], whose graph is `None` in the default graph.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Encoded {
+    triple: [Slot; 3],
+    graph: Option<Slot>,
+}
+
+/// Component of a quad, as subjects, predicates, objects and graphs are
+/// numbered separately.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Position {
+    Triple(Index),
+    Graph,
+}
+
+impl Position {
+    fn locate(self, dictionary: &Dictionary, term: &str) -> Option<TermId> {
+        match self {
+            Position::Triple(index) => dictionary.locate(index, term),
+            // The empty string would stand for the default graph
+            Position::Graph if term.is_empty() => None,
+            Position::Graph => dictionary.get_graph_idx(term),
+        }
+    }
+
+    fn term(self, dictionary: &Dictionary, id: TermId) -> Result<Term, ZarrDfError> {
+        match self {
+            Position::Triple(index) => dictionary.term(index, id),
+            Position::Graph => match dictionary.graph_term(id)? {
+                GraphName::NamedNode(iri) => Ok(iri.into()),
+                GraphName::BlankNode(id) => Ok(id.into()),
+                GraphName::DefaultGraph => Err(ZarrDfError::TermNotFound(id.get())),
+            },
+        }
+    }
+
+    /// Returns the identifier in the position `to` of the term whose
+    /// identifier in this position is `id`, if the term occurs there.
+    fn convert(self, dictionary: &Dictionary, id: TermId, to: Position) -> Option<TermId> {
+        match (self, to) {
+            (Position::Triple(from), Position::Triple(to)) => dictionary.convert(id, from, to),
+            _ if self == to => Some(id),
+            _ => {
+                let term = self.term(dictionary, id).ok()?;
+                to.locate(dictionary, &serialize_term(term))
+            }
+        }
+    }
+}
+
+/// Identifier bound to a variable, along with the position it was read from.
+#[derive(Clone, Copy)]
+struct Binding {
+    position: Position,
+    id: TermId,
+}
+
+impl Binding {
+    fn same_term(&self, dictionary: &Dictionary, other: &Binding) -> bool {
+        other.position.convert(dictionary, other.id, self.position) == Some(self.id)
+    }
+}
+
+/// Bindings of a partial solution, indexed by variable number.
+type Row = Vec<Option<Binding>>;
+
+type Rows<'a> = Box<dyn Iterator<Item = Result<Row, ZarrDfError>> + 'a>;
+
+/// Positions of the components of a triple pattern.
+const POSITIONS: [Index; 3] = [Index::Subject, Index::Predicate, Index::Object];
+
+/// Encodes the patterns, numbering their variables in `variables`, or returns
+/// `None` if one of their constants is not in the dictionary.
+fn encode(
+    dictionary: &Dictionary,
+    patterns: &[QuadPattern],
+    variables: &mut Vec<Variable>,
+) -> Option<Vec<Encoded>> {
+    let mut encoded = Some(Vec::with_capacity(patterns.len()));
+    let mut slot = |term: &PatternTerm, position: Position| match term {
+        PatternTerm::Variable(variable) => match variables.iter().position(|v| v == variable) {
+            Some(position) => Some(Slot::Variable(position)),
+            None => {
+                variables.push(variable.clone());
+                Some(Slot::Variable(variables.len() - 1))
+            }
+        },
+        PatternTerm::Term(term) => position
+            .locate(dictionary, &serialize_term(term.clone()))
+            .map(Slot::Constant),
+    };
+    for pattern in patterns {
+        let triple = &pattern.triple;
+        let terms = [&triple.subject, &triple.predicate, &triple.object];
+        // Keep numbering the variables, as solutions list them all
+        let slots = [0, 1, 2].map(|i| slot(terms[i], Position::Triple(POSITIONS[i])));
+        let graph = pattern
+            .graph
+            .as_ref()
+            .map(|graph| slot(graph, Position::Graph));
+        let (Some(subject), Some(predicate), Some(object), None | Some(Some(_))) =
+            (slots[0], slots[1], slots[2], graph)
+        else {
+            encoded = None;
+            continue;
+        };
+        if let Some(encoded) = &mut encoded {
+            encoded.push(Encoded {
+                triple: [subject, predicate, object],
+                graph: graph.flatten(),
+            });
+        }
+    }
+    encoded
+}
+
+/// Returns the variable, and its position, by which the matches of a pattern
+/// are sorted when it is scanned with only its constants bound.
+///
+/// Rows are sorted within chunks and chunks are read in order, so matches are
+/// sorted by the first unbound component of the index answering the pattern.
+/// With two components bound, every index keyed by one of them yields the
+/// same order, whichever [`Storage::access_path`] picks. Matches are only
+/// filtered by their graph, which keeps that order.
+fn sort_order(pattern: &Encoded) -> Option<(usize, Index)> {
+    let bound = pattern.triple.map(|slot| matches!(slot, Slot::Constant(_)));
+    let index = Index::for_pattern(bound);
+    let position = *index.positions()[..3]
+        .iter()
+        .find(|&&position| !bound[position])?;
+    let Slot::Variable(variable) = pattern.triple[position] else {
+        return None;
+    };
+    // A repeated variable may be bound from another position
+    let occurrences = pattern
+        .triple
+        .iter()
+        .chain(&pattern.graph)
+        .filter(|&&slot| slot == Slot::Variable(variable))
+        .count();
+    (occurrences == 1).then_some((variable, POSITIONS[position]))
+}
+
+/// Replaces the variables bound by `row` with their identifiers in the
+/// position they occur in, or returns `None` if a term cannot occur there.
+///
+/// The graph is only bound in a named graph.
+fn substitute(
+    dictionary: &Dictionary,
+    row: &Row,
+    pattern: &Encoded,
+) -> Option<[Option<TermId>; 4]> {
+    let positions = POSITIONS.map(Position::Triple);
+    let slots = pattern.triple.iter().zip(positions);
+    let mut ids = [None; 4];
+    for (id, (slot, position)) in ids
+        .iter_mut()
+        .zip(slots.chain(pattern.graph.iter().zip([Position::Graph])))
+    {
+        *id = match *slot {
+            Slot::Constant(id) => Some(id),
+            Slot::Variable(variable) => match row[variable] {
+                Some(binding) => Some(binding.position.convert(dictionary, binding.id, position)?),
+                None => None,
+            },
+        };
+    }
+    Some(ids)
+}
+
+/// Binds the variables of a pattern to the components of a matching quad,
+/// or returns `None` if they conflict with the bindings of `row`.
+fn extend(dictionary: &Dictionary, row: &Row, pattern: &Encoded, quad: [TermId; 4]) -> Option<Row> {
+    let mut row = row.clone();
+    let positions = POSITIONS.map(Position::Triple);
+    let slots = pattern.triple.iter().zip(positions);
+    for ((slot, position), id) in slots
+        .chain(pattern.graph.iter().zip([Position::Graph]))
+        .zip(quad)
+    {
+        if let Slot::Variable(variable) = *slot {
+            let binding = Binding { position, id };
+            match row[variable] {
+                Some(bound) if !bound.same_term(dictionary, &binding) => return None,
+                Some(_) => {}
+                None => row[variable] = Some(binding),
+            }
+        }
+    }
+    Some(row)
+}
+
+/// Combines the bindings of two rows, or returns `None` if they bind a
+/// variable to different terms.
+fn merge(dictionary: &Dictionary, left: &Row, right: &Row) -> Option<Row> {
+    left.iter()
+        .zip(right)
+        .map(|(left, right)| match (left, right) {
+            (Some(left), Some(right)) => left.same_term(dictionary, right).then_some(Some(*left)),
+            (Some(binding), None) | (None, Some(binding)) => Some(Some(*binding)),
+            (None, None) => Some(None),
+        })
+        .collect()
+}
+
+impl<S: ReadableWritableStorageTraits + 'static> Storage<S> {
+    /// Same as [`GraphPattern::solutions`], but the solutions do not borrow
+    /// the patterns, which are encoded upfront.
+    pub(crate) fn evaluate_bgp(
+        &self,
+        patterns: &[QuadPattern],
+        graphs: &Arc<Graphs>,
+    ) -> impl Iterator<Item = Result<Solution, ZarrDfError>> + '_ {
+        let mut variables = Vec::new();
+        let encoded = encode(&self.dictionary, patterns, &mut variables);
+        let width = variables.len();
+        let variables: Arc<[Variable]> = variables.into();
+        let rows: Rows<'_> = match encoded {
+            Some(patterns) => self.join(&patterns, width, graphs),
+            // A constant missing from the dictionary cannot match anything
+            None => Box::new(iter::empty()),
+        };
+        rows.map(move |row| self.decode(&variables, row?))
+    }
+
+    /// Joins the patterns one at a time with the solutions of the previous
+    /// ones.
+    ///
+    /// A pattern is merge joined when its matches come sorted by the same
+    /// variable as the solutions so far, which reads its chunks once, and
+    /// otherwise index nested-loop joined, which looks it up once per
+    /// solution with the variables it shares replaced by their values.
+    fn join(&self, patterns: &[Encoded], width: usize, graphs: &Arc<Graphs>) -> Rows<'_> {
+        let mut rows: Rows<'_> = Box::new(iter::once(Ok(vec![None; width])));
+        let mut order = None;
+        for (position, pattern) in self.plan(patterns, width).into_iter().enumerate() {
+            let pattern_order = sort_order(&pattern);
+            rows = match order {
+                Some((variable, _)) if pattern_order == order => {
+                    let matches = self.index_join(vec![None; width], pattern, graphs.clone());
+                    Box::new(MergeJoin::new(
+                        &self.dictionary,
+                        rows,
+                        Box::new(matches),
+                        variable,
+                    ))
+                }
+                _ => {
+                    let graphs = graphs.clone();
+                    Box::new(rows.flat_map(move |row| -> Rows<'_> {
+                        match row {
+                            Ok(row) => Box::new(self.index_join(row, pattern, graphs.clone())),
+                            Err(error) => Box::new(iter::once(Err(error))),
+                        }
+                    }))
+                }
+            };
+            // Both joins keep the order of the solutions so far
+            if position == 0 {
+                order = pattern_order;
+            }
+        }
+        rows
+    }
+
+    /// Orders the patterns so that each one is expected to have the fewest
+    /// matches for every solution of the previous ones, which starts from the
+    /// most selective and avoids cartesian products.
+    ///
+    /// A pattern is expected to have as many matches as the smallest chunk
+    /// it can be looked up in: the chunk of one of its constants, or an
+    /// average chunk for a variable bound by the previous patterns, see
+    /// [`Storage::estimate`]. Patterns with nothing bound scan every quad.
+    fn plan(&self, patterns: &[Encoded], width: usize) -> Vec<Encoded> {
+        let averages = POSITIONS.map(|index| self.estimate(index, None));
+        let quads = self.num_quads(Index::Predicate).unwrap_or(u64::MAX);
+        let mut bound = vec![false; width];
+        let mut remaining = patterns.to_vec();
+        let mut planned = Vec::with_capacity(patterns.len());
+        while !remaining.is_empty() {
+            let cost = |pattern: &Encoded| {
+                pattern
+                    .triple
+                    .iter()
+                    .zip(POSITIONS)
+                    .zip(averages)
+                    .filter_map(|((slot, index), average)| match *slot {
+                        Slot::Constant(id) => Some(self.estimate(index, Some(id))),
+                        Slot::Variable(variable) => bound[variable].then_some(average),
+                    })
+                    .min()
+                    .unwrap_or(quads)
+            };
+            // Ties keep the order of the query
+            let next = remaining
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, pattern)| cost(pattern))
+                .map_or(0, |(position, _)| position);
+            let pattern = remaining.remove(next);
+            for slot in pattern.triple.into_iter().chain(pattern.graph) {
+                if let Slot::Variable(variable) = slot {
+                    bound[variable] = true;
+                }
+            }
+            planned.push(pattern);
+        }
+        planned
+    }
+
+    /// Extends `row` with each match of the pattern, looked up with the
+    /// variables bound by `row` replaced by their values.
+    fn index_join(
+        &self,
+        row: Row,
+        pattern: Encoded,
+        graphs: Arc<Graphs>,
+    ) -> impl Iterator<Item = Result<Row, ZarrDfError>> + '_ {
+        let dictionary = &self.dictionary;
+        substitute(dictionary, &row, &pattern)
+            .into_iter()
+            .flat_map(move |ids| {
+                let row = row.clone();
+                self.graph_matching(ids, pattern.graph.is_some(), graphs.clone())
+                    .filter_map(move |quad| match quad {
+                        Ok(quad) => extend(dictionary, &row, &pattern, quad).map(Ok),
+                        Err(error) => Some(Err(error)),
+                    })
+            })
+    }
+
+    /// Returns the quads matching a pattern in the named graphs of `graphs`
+    /// if `named` is true, and otherwise the triples matching it in the
+    /// default graph, along with the graph of one of their quads.
+    fn graph_matching(
+        &self,
+        [subject, predicate, object, graph]: [Option<TermId>; 4],
+        named: bool,
+        graphs: Arc<Graphs>,
+    ) -> Box<dyn Iterator<Item = Result<[TermId; 4], ZarrDfError>> + '_> {
+        if named {
+            return Box::new(
+                self.quads_matching_iter(subject, predicate, object, graph)
+                    .filter(move |quad| {
+                        quad.as_ref().map_or(true, |quad| graphs.is_named(quad[3]))
+                    }),
+            );
+        }
+        let graph = match graphs.default.as_deref() {
+            None => {
+                return Box::new(
+                    self.triples_matching_iter(subject, predicate, object)
+                        .map(|triple| triple.map(|[s, p, o]| [s, p, o, DEFAULT_GRAPH])),
+                )
+            }
+            // A single graph is filtered by the index
+            Some(&[graph]) => Some(graph),
+            Some(_) => None,
+        };
+        let mut last = None;
+        Box::new(
+            self.quads_matching_iter(subject, predicate, object, graph)
+                .filter(move |quad| {
+                    let Ok([s, p, o, g]) = quad else {
+                        return true;
+                    };
+                    if !graphs.is_default(*g) {
+                        return false;
+                    }
+                    // Merging the graphs drops the adjacent copies of a
+                    // triple, like `triples_matching_iter`
+                    let triple = Some([*s, *p, *o]);
+                    let new = last != triple;
+                    last = triple;
+                    new
+                }),
+        )
+    }
+
+    /// Resolves the graphs of a dataset, leaving out those that are not in
+    /// the dictionary, as they are empty.
+    pub(crate) fn graphs(&self, dataset: Option<&QueryDataset>) -> Graphs {
+        let ids = |graphs: &[NamedNode]| {
+            graphs
+                .iter()
+                .filter_map(|graph| {
+                    Position::Graph.locate(&self.dictionary, &serialize_term(graph.clone()))
+                })
+                .collect()
+        };
+        Graphs {
+            default: dataset.map(|dataset| ids(&dataset.default)),
+            named: dataset.map(|dataset| ids(&dataset.named)),
+        }
+    }
+
+    fn decode(&self, variables: &Arc<[Variable]>, row: Row) -> Result<Solution, ZarrDfError> {
+        let values = row
+            .into_iter()
+            .map(|binding| {
+                binding
+                    .map(|binding| binding.position.term(&self.dictionary, binding.id))
+                    .transpose()
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Solution {
+            variables: variables.clone(),
+            values,
+        })
+    }
+}
+
+fn key(row: &Row, variable: usize) -> Option<TermId> {
+    row[variable].map(|binding| binding.id)
+}
+
+/// Merge join of two streams of rows sorted by the identifier of a variable,
+/// bound from the same position in both.
+struct MergeJoin<'a> {
+    dictionary: &'a Dictionary,
+    left: Peekable<Rows<'a>>,
+    right: Peekable<Rows<'a>>,
+    variable: usize,
+    left_group: Vec<Row>,
+    right_group: Vec<Row>,
+    position: (usize, usize),
+}
+
+impl<'a> MergeJoin<'a> {
+    fn new(dictionary: &'a Dictionary, left: Rows<'a>, right: Rows<'a>, variable: usize) -> Self {
+        MergeJoin {
+            dictionary,
+            left: left.peekable(),
+            right: right.peekable(),
+            variable,
+            left_group: Vec::new(),
+            right_group: Vec::new(),
+            position: (0, 0),
+        }
+    }
+
+    /// Takes the rows at the front of `rows` whose key is `key`, leaving
+    /// errors to be returned by the next call to `next`.
+    fn group(rows: &mut Peekable<Rows<'a>>, variable: usize, key: Option<TermId>) -> Vec<Row> {
+        let mut group = Vec::new();
+        while let Some(Ok(row)) =
+            rows.next_if(|row| matches!(row, Ok(row) if self::key(row, variable) == key))
+        {
+            group.push(row);
+        }
+        group
+    }
+}
+
+impl Iterator for MergeJoin<'_> {
+    type Item = Result<Row, ZarrDfError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            // Pairs up the rows of the current groups, which share their key
+            let (i, j) = self.position;
+            if i < self.left_group.len() {
+                self.position = if j + 1 < self.right_group.len() {
+                    (i, j + 1)
+                } else {
+                    (i + 1, 0)
+                };
+                match merge(self.dictionary, &self.left_group[i], &self.right_group[j]) {
+                    Some(row) => return Some(Ok(row)),
+                    None => continue,
+                }
+            }
+
+            let left = match self.left.peek()? {
+                Ok(row) => key(row, self.variable),
+                Err(_) => return self.left.next(),
+            };
+            let right = match self.right.peek()? {
+                Ok(row) => key(row, self.variable),
+                Err(_) => return self.right.next(),
+            };
+            match left.cmp(&right) {
+                Ordering::Less => {
+                    self.left.next();
+                }
+                Ordering::Greater => {
+                    self.right.next();
+                }
+                Ordering::Equal => {
+                    self.left_group = Self::group(&mut self.left, self.variable, left);
+                    self.right_group = Self::group(&mut self.right, self.variable, right);
+                    self.position = (0, 0);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Parser;
+use zarrdf::error::ZarrDfError;
+use zarrdf::server::Server;
+use zarrdf::server::FRAGMENTS_PATH;
+use zarrdf::server::SPARQL_PATH;
+use zarrdf::storage::Storage;
+use zarrs::filesystem::FilesystemStore;
+
+/// Serves a ZarrDF store over the SPARQL 1.1 Protocol and as Triple Pattern
+/// Fragments.
+#[derive(Parser)]
+#[command(name = "zarrdf-server", version)]
+struct Cli {
+    /// Directory of the store.
+    zarr: PathBuf,
+    /// Address to listen on.
+    #[arg(short, long, default_value = "127.0.0.1:7878")]
+    bind: String,
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<(), ZarrDfError> {
+    let storage = Storage::from_zarr(FilesystemStore::new(cli.zarr)?)?;
+    let server = Server::bind(&cli.bind, storage)?;
+    if let Some(address) = server.local_addr() {
+        eprintln!("SPARQL endpoint at http://{address}{SPARQL_PATH}");
+        eprintln!("Triple Pattern Fragments at http://{address}{FRAGMENTS_PATH}");
+    }
+    server.serve()
+}
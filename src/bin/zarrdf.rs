@@ -0,0 +1,433 @@
+use std::fs::File;
+use std::io::stdout;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::str::FromStr;
+
+use clap::Parser;
+use clap::Subcommand;
+use oxrdf::GraphName;
+use oxrdf::NamedNode;
+use oxrdf::Subject;
+use oxrdf::Term;
+use zarrdf::config::Compression;
+use zarrdf::config::ConfigBuilder;
+use zarrdf::config::DictionaryLayout;
+use zarrdf::config::Sharding;
+use zarrdf::error::ZarrDfError;
+use zarrdf::query::BasicGraphPattern;
+use zarrdf::query::TermPattern;
+use zarrdf::rdf_format::RdfFormat;
+use zarrdf::storage::Storage;
+use zarrs::filesystem::FilesystemStore;
+
+/// Subject, predicate, object and graph of a pattern, where `None` matches
+/// any term.
+type Pattern = (
+    Option<Subject>,
+    Option<NamedNode>,
+    Option<Term>,
+    Option<GraphName>,
+);
+
+/// Converts, queries and inspects ZarrDF stores.
+#[derive(Parser)]
+#[command(name = "zarrdf", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Builds a store from an RDF file.
+    Convert {
+        /// RDF file to read.
+        rdf: String,
+        /// Directory of the store to create.
+        zarr: PathBuf,
+        /// Syntax of the input (nt, ttl, rdf, trig, n3 or nq), guessed from
+        /// its extension by default.
+        #[arg(long)]
+        format: Option<RdfFormat>,
+        /// Bytes kept in memory before spilling to temporary files.
+        #[arg(long)]
+        memory_budget: Option<usize>,
+        /// Directory of the temporary files.
+        #[arg(long)]
+        temp_dir: Option<PathBuf>,
+        /// Temporary files merged at once.
+        #[arg(long)]
+        merge_fan_in: Option<usize>,
+        /// Shares the identifiers of terms that are both subjects and objects.
+        #[arg(long)]
+        shared_dictionary: bool,
+        /// Compression of the arrays: none, gzip[:level], zstd[:level] or
+        /// blosc[:compressor[:level]].
+        #[arg(long)]
+        compression: Option<Compression>,
+        /// Stores index chunks column by column.
+        #[arg(long)]
+        transpose: bool,
+        /// Stores the differences between consecutive rows of index chunks,
+        /// with a zarrdf.delta codec that only ZarrDF can decode.
+        #[arg(long)]
+        delta: bool,
+        /// Packs index chunks into shards: rows of every chunk, optionally
+        /// followed by chunks per shard, as in 1024:1024.
+        #[arg(long, num_args = 0..=1, default_missing_value = "1024")]
+        sharding: Option<Sharding>,
+        /// Threads writing the indexes, one per CPU by default.
+        #[arg(long)]
+        threads: Option<usize>,
+    },
+    /// Adds the quads of an RDF file to a store, as a delta merged with it
+    /// by queries and encoded like its arrays.
+    Append {
+        /// RDF file to read.
+        rdf: String,
+        /// Directory of the store.
+        zarr: PathBuf,
+        /// Syntax of the input (nt, ttl, rdf, trig, n3 or nq), guessed from
+        /// its extension by default.
+        #[arg(long)]
+        format: Option<RdfFormat>,
+    },
+    /// Removes the quads matching a pattern from a store, as a delta
+    /// subtracted from it by queries.
+    Delete {
+        /// Directory of the store.
+        zarr: PathBuf,
+        /// Subject, in N-Triples syntax.
+        #[arg(short, long)]
+        subject: Option<String>,
+        /// Predicate, in N-Triples syntax.
+        #[arg(short, long)]
+        predicate: Option<String>,
+        /// Object, in N-Triples syntax.
+        #[arg(short, long)]
+        object: Option<String>,
+        /// Graph, in N-Triples syntax, where an empty string stands for the
+        /// default graph. Quads are removed from every graph when it is
+        /// omitted.
+        #[arg(short, long)]
+        graph: Option<String>,
+    },
+    /// Applies a SPARQL update to a store, committing all its changes at
+    /// once.
+    Update {
+        /// Directory of the store.
+        zarr: PathBuf,
+        /// Update in SPARQL syntax.
+        update: String,
+    },
+    /// Folds the deltas of a store back into its base arrays, dropping
+    /// removed quads and unused terms.
+    Compact {
+        /// Directory of the store.
+        zarr: PathBuf,
+    },
+    /// Prints the quads matching a pattern, one per line in N-Quads.
+    Query {
+        /// Directory of the store.
+        zarr: PathBuf,
+        /// Subject, in N-Triples syntax.
+        #[arg(short, long)]
+        subject: Option<String>,
+        /// Predicate, in N-Triples syntax.
+        #[arg(short, long)]
+        predicate: Option<String>,
+        /// Object, in N-Triples syntax.
+        #[arg(short, long)]
+        object: Option<String>,
+        /// Graph, in N-Triples syntax, where an empty string stands for the
+        /// default graph. All graphs are merged when it is omitted.
+        #[arg(short, long)]
+        graph: Option<String>,
+        /// Maximum number of results.
+        #[arg(short, long)]
+        limit: Option<usize>,
+    },
+    /// Writes the whole store as RDF.
+    Dump {
+        /// Directory of the store.
+        zarr: PathBuf,
+        /// File to write, or the standard output if omitted.
+        rdf: Option<String>,
+        /// Syntax of the output, guessed from its extension by default and
+        /// N-Quads on the standard output.
+        #[arg(long)]
+        format: Option<RdfFormat>,
+        /// Bytes kept in memory before spilling to temporary files.
+        #[arg(long)]
+        memory_budget: Option<usize>,
+        /// Directory of the temporary files.
+        #[arg(long)]
+        temp_dir: Option<PathBuf>,
+        /// Threads reading the store, one per CPU by default.
+        #[arg(long)]
+        threads: Option<usize>,
+    },
+    /// Prints the number of terms and quads of a store.
+    Stats {
+        /// Directory of the store.
+        zarr: PathBuf,
+    },
+    /// Checks that a store is consistent.
+    Verify {
+        /// Directory of the store.
+        zarr: PathBuf,
+    },
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse().command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::from(exit_code(&error))
+        }
+    }
+}
+
+/// Maps errors to exit codes, leaving 2 for usage errors reported by clap.
+fn exit_code(error: &ZarrDfError) -> u8 {
+    match error {
+        ZarrDfError::Io(_) | ZarrDfError::FilesystemCreation(_) => 3,
+        ZarrDfError::UnknownFormat(_)
+        | ZarrDfError::UnknownCompression(_)
+        | ZarrDfError::InvalidSharding(_)
+        | ZarrDfError::TermEncoding(_)
+        | ZarrDfError::TermParsing(_)
+        | ZarrDfError::TermConversion(_)
+        | ZarrDfError::InvalidPrefix(_) => 4,
+        ZarrDfError::SubjectsNotInMetadata
+        | ZarrDfError::PredicatesNotInMetadata
+        | ZarrDfError::ObjectsNotInMetadata
+        | ZarrDfError::GraphsNotInMetadata
+        | ZarrDfError::PathNotFound(_)
+        | ZarrDfError::ArrayCreation(_) => 5,
+        ZarrDfError::Corrupted(_)
+        | ZarrDfError::TermNotFound(_)
+        | ZarrDfError::ChunkNotFound(_) => 6,
+        _ => 1,
+    }
+}
+
+fn run(command: Command) -> Result<(), ZarrDfError> {
+    match command {
+        Command::Convert {
+            rdf,
+            zarr,
+            format,
+            memory_budget,
+            temp_dir,
+            merge_fan_in,
+            shared_dictionary,
+            compression,
+            transpose,
+            delta,
+            sharding,
+            threads,
+        } => {
+            let mut config = ConfigBuilder::new();
+            if let Some(format) = format {
+                config.rdf_format(format);
+            }
+            if let Some(memory_budget) = memory_budget {
+                config.memory_budget(memory_budget);
+            }
+            if let Some(temp_dir) = temp_dir {
+                config.temp_dir(temp_dir);
+            }
+            if let Some(merge_fan_in) = merge_fan_in {
+                config.merge_fan_in(merge_fan_in);
+            }
+            if shared_dictionary {
+                config.dictionary_layout(DictionaryLayout::Shared);
+            }
+            if let Some(compression) = compression {
+                config.compression(compression);
+            }
+            if let Some(threads) = threads {
+                config.threads(threads);
+            }
+            config.transpose(transpose).delta(delta).sharding(sharding);
+            let store = FilesystemStore::new(zarr)?;
+            Storage::from_rdf_and_store_with_config(&rdf, store, &config.build())?;
+            Ok(())
+        }
+        Command::Append { rdf, zarr, format } => {
+            let mut storage = Storage::from_zarr(FilesystemStore::new(zarr)?)?;
+            // The delta is encoded like the arrays of the store
+            let mut config = storage.config_builder()?;
+            if let Some(format) = format {
+                config.rdf_format(format);
+            }
+            storage.append_with_config(&rdf, &config.build())
+        }
+        Command::Delete {
+            zarr,
+            subject,
+            predicate,
+            object,
+            graph,
+        } => {
+            let (subject, predicate, object, graph) =
+                parse_pattern(subject, predicate, object, graph)?;
+            let mut storage = Storage::from_zarr(FilesystemStore::new(zarr)?)?;
+            let removed = storage.delete_matching(
+                subject.as_ref(),
+                predicate.as_ref(),
+                object.as_ref(),
+                graph.as_ref(),
+            )?;
+            println!("removed: {removed}");
+            Ok(())
+        }
+        Command::Update { zarr, update } => {
+            let mut storage = Storage::from_zarr(FilesystemStore::new(zarr)?)?;
+            storage.sparql_update(&update)
+        }
+        Command::Compact { zarr } => {
+            let mut storage = Storage::from_zarr(FilesystemStore::new(zarr)?)?;
+            storage.compact()
+        }
+        Command::Query {
+            zarr,
+            subject,
+            predicate,
+            object,
+            graph,
+            limit,
+        } => {
+            let (subject, predicate, object, graph) =
+                parse_pattern(subject, predicate, object, graph)?;
+            let storage = Storage::from_zarr(FilesystemStore::new(zarr)?)?;
+
+            let mut output = BufWriter::new(stdout().lock());
+            let quads = storage.quads_matching_terms(
+                subject.as_ref(),
+                predicate.as_ref(),
+                object.as_ref(),
+                graph.as_ref(),
+            );
+            // Graphs are merged like in `triples_matching` when none is given
+            let mut last = None;
+            let mut count = 0;
+            for quad in quads {
+                if limit.is_some_and(|limit| count >= limit) {
+                    break;
+                }
+                let quad = quad?;
+                if graph.is_some() {
+                    writeln!(output, "{quad} .")?;
+                } else {
+                    let triple = oxrdf::Triple::from(quad);
+                    if last.as_ref() == Some(&triple) {
+                        continue;
+                    }
+                    writeln!(output, "{triple} .")?;
+                    last = Some(triple);
+                }
+                count += 1;
+            }
+            output.flush()?;
+            Ok(())
+        }
+        Command::Dump {
+            zarr,
+            rdf,
+            format,
+            memory_budget,
+            temp_dir,
+            threads,
+        } => {
+            let storage = Storage::from_zarr(FilesystemStore::new(zarr)?)?;
+            let mut config = ConfigBuilder::new();
+            if let Some(memory_budget) = memory_budget {
+                config.memory_budget(memory_budget);
+            }
+            if let Some(temp_dir) = temp_dir {
+                config.temp_dir(temp_dir);
+            }
+            if let Some(threads) = threads {
+                config.threads(threads);
+            }
+            let config = config.build();
+            match rdf {
+                Some(rdf) => {
+                    let format = match format {
+                        Some(format) => format,
+                        None => RdfFormat::from_path(&rdf)?,
+                    };
+                    let writer = BufWriter::new(File::create(&rdf)?);
+                    storage
+                        .export_with_config(writer, format, &config)?
+                        .flush()?;
+                }
+                None => {
+                    let writer = BufWriter::new(stdout().lock());
+                    let format = format.unwrap_or(RdfFormat::NQuads);
+                    storage
+                        .export_with_config(writer, format, &config)?
+                        .flush()?;
+                }
+            }
+            Ok(())
+        }
+        Command::Stats { zarr } => {
+            let storage = Storage::from_zarr(FilesystemStore::new(zarr)?)?;
+            let dictionary = &storage.dictionary;
+            let statistics = storage.statistics()?;
+            println!("quads: {}", statistics.quads());
+            println!("triples: {}", storage.count_matching(None, None, None)?);
+            println!("subjects: {}", dictionary.subjects_len());
+            println!("predicates: {}", dictionary.predicates_len());
+            println!("objects: {}", dictionary.objects_len());
+            println!("shared: {}", dictionary.shared_len());
+            println!("graphs: {}", dictionary.graphs_len());
+            Ok(())
+        }
+        Command::Verify { zarr } => {
+            let storage = Storage::from_zarr(FilesystemStore::new(zarr)?)?;
+            storage.verify()?;
+            println!("ok");
+            Ok(())
+        }
+    }
+}
+
+/// Parses the components of a pattern given in N-Triples syntax.
+fn parse_pattern(
+    subject: Option<String>,
+    predicate: Option<String>,
+    object: Option<String>,
+    graph: Option<String>,
+) -> Result<Pattern, ZarrDfError> {
+    let subject: Option<Subject> = subject
+        .map(|s| Ok::<_, ZarrDfError>(parse_term(&s)?.try_into()?))
+        .transpose()?;
+    let predicate: Option<NamedNode> = predicate
+        .map(|p| Ok::<_, ZarrDfError>(parse_term(&p)?.try_into()?))
+        .transpose()?;
+    let object = object.map(|o| parse_term(&o)).transpose()?;
+    let graph = graph.map(|g| parse_graph_name(&g)).transpose()?;
+    Ok((subject, predicate, object, graph))
+}
+
+fn parse_term(term: &str) -> Result<Term, ZarrDfError> {
+    Ok(Term::from_str(term)?)
+}
+
+fn parse_graph_name(graph: &str) -> Result<GraphName, ZarrDfError> {
+    if graph.is_empty() {
+        return Ok(GraphName::DefaultGraph);
+    }
+    match Subject::try_from(parse_term(graph)?)? {
+        Subject::NamedNode(iri) => Ok(iri.into()),
+        Subject::BlankNode(id) => Ok(id.into()),
+    }
+}
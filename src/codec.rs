@@ -0,0 +1,240 @@
+use std::sync::Arc;
+
+use zarrs::array::codec::ArrayCodecTraits;
+use zarrs::array::codec::ArrayPartialDecoderTraits;
+use zarrs::array::codec::ArrayPartialEncoderTraits;
+use zarrs::array::codec::ArrayToArrayCodecTraits;
+use zarrs::array::codec::ArrayToArrayPartialEncoderDefault;
+use zarrs::array::codec::Codec;
+use zarrs::array::codec::CodecError;
+use zarrs::array::codec::CodecOptions;
+use zarrs::array::codec::CodecPlugin;
+use zarrs::array::codec::CodecTraits;
+use zarrs::array::ArrayBytes;
+use zarrs::array::ArrayMetadataOptions;
+use zarrs::array::ChunkRepresentation;
+use zarrs::array::ChunkShape;
+use zarrs::array::DataType;
+use zarrs::array::RecommendedConcurrency;
+use zarrs::array_subset::ArraySubset;
+use zarrs::metadata::v3::MetadataV3;
+use zarrs::plugin::PluginCreateError;
+
+use crate::index::mask;
+use crate::utils::delta_decode;
+use crate::utils::delta_encode;
+
+/// Name of the [`DeltaCodec`] in the metadata of the arrays.
+pub(crate) const DELTA_CODEC: &str = "zarrdf.delta";
+
+// Arrays written with the codec can only be opened once it is registered
+inventory::submit! {
+    CodecPlugin::new(DELTA_CODEC, is_delta_codec, create_delta_codec)
+}
+
+fn is_delta_codec(name: &str) -> bool {
+    name == DELTA_CODEC
+}
+
+fn create_delta_codec(_metadata: &MetadataV3) -> Result<Codec, PluginCreateError> {
+    Ok(Codec::ArrayToArray(Arc::new(DeltaCodec)))
+}
+
+/// An array to array codec replacing every row of a chunk but the first with
+/// its difference to the previous row, column by column, see
+/// [`delta_encode`].
+///
+/// It is recorded in the codec chain of the arrays like any other codec, so
+/// readers that do not know it refuse to open them instead of returning the
+/// differences.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct DeltaCodec;
+
+impl DeltaCodec {
+    fn apply(
+        bytes: ArrayBytes<'_>,
+        representation: &ChunkRepresentation,
+        delta: fn(&mut [u64], usize, u64),
+    ) -> Result<ArrayBytes<'static>, CodecError> {
+        let data_type = representation.data_type();
+        let bytes = bytes.into_fixed()?;
+        // Rows span every dimension but the first
+        let columns = representation.shape()[1..]
+            .iter()
+            .map(|size| size.get() as usize)
+            .product();
+        let mut rows = widen(&bytes, data_type)?;
+        delta(&mut rows, columns, mask(data_type));
+        Ok(ArrayBytes::new_flen(narrow(&rows, data_type)))
+    }
+}
+
+impl CodecTraits for DeltaCodec {
+    fn create_metadata_opt(&self, _options: &ArrayMetadataOptions) -> Option<MetadataV3> {
+        Some(MetadataV3::new(DELTA_CODEC))
+    }
+
+    fn partial_decoder_should_cache_input(&self) -> bool {
+        false
+    }
+
+    fn partial_decoder_decodes_all(&self) -> bool {
+        true
+    }
+}
+
+impl ArrayCodecTraits for DeltaCodec {
+    fn recommended_concurrency(
+        &self,
+        _decoded_representation: &ChunkRepresentation,
+    ) -> Result<RecommendedConcurrency, CodecError> {
+        Ok(RecommendedConcurrency::new_maximum(1))
+    }
+}
+
+impl ArrayToArrayCodecTraits for DeltaCodec {
+    fn dynamic(self: Arc<Self>) -> Arc<dyn ArrayToArrayCodecTraits> {
+        self as Arc<dyn ArrayToArrayCodecTraits>
+    }
+
+    fn encode<'a>(
+        &self,
+        bytes: ArrayBytes<'a>,
+        decoded_representation: &ChunkRepresentation,
+        _options: &CodecOptions,
+    ) -> Result<ArrayBytes<'a>, CodecError> {
+        Self::apply(bytes, decoded_representation, delta_encode)
+    }
+
+    fn decode<'a>(
+        &self,
+        bytes: ArrayBytes<'a>,
+        decoded_representation: &ChunkRepresentation,
+        _options: &CodecOptions,
+    ) -> Result<ArrayBytes<'a>, CodecError> {
+        Self::apply(bytes, decoded_representation, delta_decode)
+    }
+
+    fn partial_decoder(
+        self: Arc<Self>,
+        input_handle: Arc<dyn ArrayPartialDecoderTraits>,
+        decoded_representation: &ChunkRepresentation,
+        _options: &CodecOptions,
+    ) -> Result<Arc<dyn ArrayPartialDecoderTraits>, CodecError> {
+        Ok(Arc::new(DeltaPartialDecoder {
+            input_handle,
+            decoded_representation: decoded_representation.clone(),
+        }))
+    }
+
+    fn partial_encoder(
+        self: Arc<Self>,
+        input_handle: Arc<dyn ArrayPartialDecoderTraits>,
+        output_handle: Arc<dyn ArrayPartialEncoderTraits>,
+        decoded_representation: &ChunkRepresentation,
+        _options: &CodecOptions,
+    ) -> Result<Arc<dyn ArrayPartialEncoderTraits>, CodecError> {
+        Ok(Arc::new(ArrayToArrayPartialEncoderDefault::new(
+            input_handle,
+            output_handle,
+            decoded_representation.clone(),
+            self,
+        )))
+    }
+
+    fn compute_encoded_size(
+        &self,
+        decoded_representation: &ChunkRepresentation,
+    ) -> Result<ChunkRepresentation, CodecError> {
+        match decoded_representation.data_type() {
+            DataType::UInt8 | DataType::UInt16 | DataType::UInt32 | DataType::UInt64 => {
+                Ok(decoded_representation.clone())
+            }
+            data_type => Err(CodecError::UnsupportedDataType(
+                data_type.clone(),
+                DELTA_CODEC.to_string(),
+            )),
+        }
+    }
+
+    fn compute_decoded_shape(&self, encoded_shape: ChunkShape) -> Result<ChunkShape, CodecError> {
+        Ok(encoded_shape)
+    }
+}
+
+/// Partial decoder for the [`DeltaCodec`].
+///
+/// Every row depends on the ones before it, so the whole chunk is decoded
+/// before the subsets are extracted.
+struct DeltaPartialDecoder {
+    input_handle: Arc<dyn ArrayPartialDecoderTraits>,
+    decoded_representation: ChunkRepresentation,
+}
+
+impl ArrayPartialDecoderTraits for DeltaPartialDecoder {
+    fn data_type(&self) -> &DataType {
+        self.decoded_representation.data_type()
+    }
+
+    fn partial_decode(
+        &self,
+        array_subsets: &[ArraySubset],
+        options: &CodecOptions,
+    ) -> Result<Vec<ArrayBytes<'_>>, CodecError> {
+        let shape = self.decoded_representation.shape_u64();
+        let chunk = ArraySubset::new_with_shape(shape.clone());
+        let encoded = self
+            .input_handle
+            .partial_decode(&[chunk], options)?
+            .remove(0);
+        let decoded = DeltaCodec::apply(encoded, &self.decoded_representation, delta_decode)?;
+        array_subsets
+            .iter()
+            .map(|subset| {
+                let bytes = decoded.extract_array_subset(subset, &shape, self.data_type())?;
+                Ok(bytes.into_owned())
+            })
+            .collect()
+    }
+}
+
+/// Reads identifiers stored as `data_type` from their bytes.
+fn widen(bytes: &[u8], data_type: &DataType) -> Result<Vec<u64>, CodecError> {
+    Ok(match data_type {
+        DataType::UInt8 => bytes.iter().map(|&id| id as u64).collect(),
+        DataType::UInt16 => bytes
+            .chunks_exact(2)
+            .map(|id| u16::from_ne_bytes([id[0], id[1]]) as u64)
+            .collect(),
+        DataType::UInt32 => bytes
+            .chunks_exact(4)
+            .map(|id| u32::from_ne_bytes(id.try_into().expect("4 bytes")) as u64)
+            .collect(),
+        DataType::UInt64 => bytes
+            .chunks_exact(8)
+            .map(|id| u64::from_ne_bytes(id.try_into().expect("8 bytes")))
+            .collect(),
+        data_type => {
+            return Err(CodecError::UnsupportedDataType(
+                data_type.clone(),
+                DELTA_CODEC.to_string(),
+            ))
+        }
+    })
+}
+
+/// Writes identifiers back as `data_type`, which [`widen`] has checked.
+fn narrow(ids: &[u64], data_type: &DataType) -> Vec<u8> {
+    match data_type {
+        DataType::UInt8 => ids.iter().map(|&id| id as u8).collect(),
+        DataType::UInt16 => ids
+            .iter()
+            .flat_map(|&id| (id as u16).to_ne_bytes())
+            .collect(),
+        DataType::UInt32 => ids
+            .iter()
+            .flat_map(|&id| (id as u32).to_ne_bytes())
+            .collect(),
+        _ => ids.iter().flat_map(|&id| id.to_ne_bytes()).collect(),
+    }
+}
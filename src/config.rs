@@ -1,13 +1,434 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use zarrs::array::codec::array_to_array::transpose::TransposeOrder;
+use zarrs::array::codec::bytes_to_bytes::blosc::BloscShuffleMode;
+use zarrs::array::codec::ArrayToArrayCodecTraits;
+use zarrs::array::codec::BloscCodec;
+use zarrs::array::codec::BytesToBytesCodecTraits;
+use zarrs::array::codec::GzipCodec;
+use zarrs::array::codec::TransposeCodec;
+use zarrs::array::codec::ZstdCodec;
 use zarrs::array::ChunkGrid;
 use zarrs::array::DataType;
 use zarrs::array::FillValue;
 
+pub use zarrs::array::codec::bytes_to_bytes::blosc::BloscCompressor;
+
+use crate::codec::DeltaCodec;
+use crate::codec::DELTA_CODEC;
+use crate::error::ZarrDfError;
 use crate::index::Index;
+use crate::rdf_format::RdfFormat;
+
+/// Default amount of memory, in bytes, that ingestion may use before spilling to disk.
+pub const DEFAULT_MEMORY_BUDGET: usize = 512 * 1024 * 1024;
+
+/// Default number of spilled runs that ingestion merges at once.
+pub const DEFAULT_MERGE_FAN_IN: usize = 64;
+
+/// Compression used when no other is configured.
+pub const DEFAULT_COMPRESSION: Compression = Compression::Gzip(5);
+
+/// Compression applied to the chunks of every array of a store.
+///
+/// It can be parsed from strings such as `none`, `gzip:9`, `zstd:3` or
+/// `blosc:lz4:5`, where omitted levels and compressors take a default value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    /// Gzip, with a level from 0 to 9.
+    Gzip(u32),
+    /// Zstandard, with a level from -7 to 22.
+    Zstd(i32),
+    /// Blosc with byte shuffling, with a level from 0 to 9.
+    Blosc(BloscCompressor, u8),
+}
+
+impl Compression {
+    /// Builds the codecs of an array whose elements take `element_size`
+    /// bytes.
+    pub(crate) fn codecs(
+        &self,
+        element_size: usize,
+    ) -> Result<Vec<Arc<dyn BytesToBytesCodecTraits>>, ZarrDfError> {
+        let codec: Arc<dyn BytesToBytesCodecTraits> = match *self {
+            Compression::None => return Ok(Vec::new()),
+            Compression::Gzip(level) => Arc::new(GzipCodec::new(level)?),
+            Compression::Zstd(level) => Arc::new(ZstdCodec::new(level, false)),
+            Compression::Blosc(compressor, level) => {
+                let level = level.try_into().map_err(|level| {
+                    ZarrDfError::UnknownCompression(format!("blosc level {level}"))
+                })?;
+                let shuffle = match element_size {
+                    1 => BloscShuffleMode::NoShuffle,
+                    _ => BloscShuffleMode::Shuffle,
+                };
+                Arc::new(BloscCodec::new(
+                    compressor,
+                    level,
+                    None,
+                    shuffle,
+                    Some(element_size),
+                )?)
+            }
+        };
+        Ok(vec![codec])
+    }
+}
+
+impl FromStr for Compression {
+    type Err = ZarrDfError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let unknown = || ZarrDfError::UnknownCompression(s.to_string());
+        let mut parts = s.split(':');
+        let compression = match (parts.next(), parts.next(), parts.next()) {
+            (Some("none"), None, None) => Compression::None,
+            (Some("gzip"), level, None) => {
+                Compression::Gzip(level.map_or(Ok(5), str::parse).map_err(|_| unknown())?)
+            }
+            (Some("zstd"), level, None) => {
+                Compression::Zstd(level.map_or(Ok(3), str::parse).map_err(|_| unknown())?)
+            }
+            (Some("blosc"), compressor, level) => {
+                let compressor = match compressor.unwrap_or("lz4") {
+                    "blosclz" => BloscCompressor::BloscLZ,
+                    "lz4" => BloscCompressor::LZ4,
+                    "lz4hc" => BloscCompressor::LZ4HC,
+                    "snappy" => BloscCompressor::Snappy,
+                    "zlib" => BloscCompressor::Zlib,
+                    "zstd" => BloscCompressor::Zstd,
+                    _ => return Err(unknown()),
+                };
+                let level = level.map_or(Ok(5), str::parse).map_err(|_| unknown())?;
+                Compression::Blosc(compressor, level)
+            }
+            _ => return Err(unknown()),
+        };
+        if parts.next().is_some() {
+            return Err(unknown());
+        }
+        Ok(compression)
+    }
+}
 
+/// Layout of sharded index arrays, which pack the rows of many terms into
+/// shards stored as single objects, made of chunks that can still be read on
+/// their own through the index of the shard.
+///
+/// It can be parsed from strings such as `1024` or `1024:256`, giving the
+/// rows of every inner chunk and, optionally, the inner chunks per shard.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sharding {
+    /// Number of rows of every inner chunk.
+    pub chunk_rows: u64,
+    /// Number of inner chunks of every shard.
+    pub chunks_per_shard: u64,
+}
+
+impl Sharding {
+    /// Number of rows of every shard.
+    pub(crate) fn shard_rows(&self) -> u64 {
+        self.chunk_rows * self.chunks_per_shard
+    }
+}
+
+impl Default for Sharding {
+    fn default() -> Self {
+        Sharding {
+            chunk_rows: 1024,
+            chunks_per_shard: 1024,
+        }
+    }
+}
+
+impl FromStr for Sharding {
+    type Err = ZarrDfError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ZarrDfError::InvalidSharding(s.to_string());
+        let (chunk_rows, chunks_per_shard) = match s.split_once(':') {
+            Some((chunk_rows, chunks_per_shard)) => (chunk_rows, Some(chunks_per_shard)),
+            None => (s, None),
+        };
+        let sharding = Sharding {
+            chunk_rows: chunk_rows.parse().map_err(|_| invalid())?,
+            chunks_per_shard: chunks_per_shard
+                .map_or(Ok(Sharding::default().chunks_per_shard), str::parse)
+                .map_err(|_| invalid())?,
+        };
+        if sharding.chunk_rows == 0 || sharding.chunks_per_shard == 0 {
+            return Err(invalid());
+        }
+        Ok(sharding)
+    }
+}
+
+/// How subjects and objects are laid out in the dictionary.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DictionaryLayout {
+    /// Subjects and objects are identified independently.
+    #[default]
+    Separate,
+    /// Terms that are both subjects and objects go to a shared section, as in
+    /// HDT, so their identifiers can be joined across positions.
+    Shared,
+}
+
+/// Settings used when building a ZarrDF store.
+///
+/// Use a [`ConfigBuilder`] to create one.
+#[derive(Clone, Debug)]
 pub struct Config {
+    pub(crate) memory_budget: usize,
+    pub(crate) temp_dir: PathBuf,
+    pub(crate) merge_fan_in: usize,
+    pub(crate) dictionary_layout: DictionaryLayout,
+    pub(crate) rdf_format: Option<RdfFormat>,
+    pub(crate) compression: Compression,
+    pub(crate) transpose: bool,
+    pub(crate) delta: bool,
+    pub(crate) sharding: Option<Sharding>,
+    pub(crate) threads: usize,
+}
+
+impl Config {
+    /// Builds the filters applied to the index arrays before compression.
+    pub(crate) fn filters(&self) -> Vec<Arc<dyn ArrayToArrayCodecTraits>> {
+        let mut filters: Vec<Arc<dyn ArrayToArrayCodecTraits>> = Vec::new();
+        if self.delta {
+            // Comes first, as it works on whole rows
+            filters.push(Arc::new(DeltaCodec));
+        }
+        if self.transpose {
+            // Stores the rows column by column
+            let order = TransposeOrder::new(&[1, 0]).expect("valid permutation");
+            filters.push(Arc::new(TransposeCodec::new(order)));
+        }
+        filters
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        ConfigBuilder::new().build()
+    }
+}
+
+/// A builder for [`Config`].
+#[derive(Clone, Debug)]
+pub struct ConfigBuilder {
+    memory_budget: usize,
+    temp_dir: Option<PathBuf>,
+    merge_fan_in: usize,
+    dictionary_layout: DictionaryLayout,
+    rdf_format: Option<RdfFormat>,
+    compression: Compression,
+    transpose: bool,
+    delta: bool,
+    sharding: Option<Sharding>,
+    threads: usize,
+}
+
+impl Default for ConfigBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConfigBuilder {
+    pub fn new() -> Self {
+        Self {
+            memory_budget: DEFAULT_MEMORY_BUDGET,
+            temp_dir: None,
+            merge_fan_in: DEFAULT_MERGE_FAN_IN,
+            dictionary_layout: DictionaryLayout::default(),
+            rdf_format: None,
+            compression: DEFAULT_COMPRESSION,
+            transpose: false,
+            delta: false,
+            sharding: None,
+            threads: 0,
+        }
+    }
+
+    /// Sets the approximate number of bytes that ingestion keeps in memory.
+    ///
+    /// Terms and triples exceeding the budget are sorted and spilled to
+    /// temporary files, which are merged back when building the store.
+    pub fn memory_budget(&mut self, memory_budget: usize) -> &mut Self {
+        self.memory_budget = memory_budget;
+        self
+    }
+
+    /// Sets the directory where spilled runs are written.
+    ///
+    /// Defaults to [`std::env::temp_dir`].
+    pub fn temp_dir(&mut self, temp_dir: impl Into<PathBuf>) -> &mut Self {
+        self.temp_dir = Some(temp_dir.into());
+        self
+    }
+
+    /// Sets the largest number of spilled runs merged at once, which bounds
+    /// the files kept open while merging.
+    ///
+    /// More runs are first merged into longer ones, level by level. Defaults
+    /// to [`DEFAULT_MERGE_FAN_IN`]; values below two are raised to two.
+    pub fn merge_fan_in(&mut self, merge_fan_in: usize) -> &mut Self {
+        self.merge_fan_in = merge_fan_in;
+        self
+    }
+
+    /// Sets whether subjects and objects share their identifiers.
+    ///
+    /// Defaults to [`DictionaryLayout::Separate`].
+    pub fn dictionary_layout(&mut self, dictionary_layout: DictionaryLayout) -> &mut Self {
+        self.dictionary_layout = dictionary_layout;
+        self
+    }
+
+    /// Sets the syntax of the input, which is otherwise guessed from the
+    /// extension of its path.
+    pub fn rdf_format(&mut self, rdf_format: RdfFormat) -> &mut Self {
+        self.rdf_format = Some(rdf_format);
+        self
+    }
+
+    /// Sets the compression of every array.
+    ///
+    /// Defaults to [`DEFAULT_COMPRESSION`].
+    pub fn compression(&mut self, compression: Compression) -> &mut Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Sets whether index chunks are stored column by column, which groups
+    /// similar identifiers together before compression.
+    pub fn transpose(&mut self, transpose: bool) -> &mut Self {
+        self.transpose = transpose;
+        self
+    }
+
+    /// Sets whether every index chunk stores the differences between
+    /// consecutive rows instead of the identifiers themselves.
+    ///
+    /// As rows are sorted, the differences are small and compress better.
+    /// They are undone by a `zarrdf.delta` codec recorded in the metadata of
+    /// the arrays, which other Zarr readers do not know, so they refuse to
+    /// open such stores rather than return the differences.
+    pub fn delta(&mut self, delta: bool) -> &mut Self {
+        self.delta = delta;
+        self
+    }
+
+    /// Sets whether the chunks of the index arrays are packed into shards,
+    /// which keeps the number of stored objects low when there are many
+    /// terms. Every term is then looked up by its range of rows, recorded in
+    /// the metadata of the array, instead of by its chunk.
+    ///
+    /// Defaults to `None`, which stores one chunk per term.
+    pub fn sharding(&mut self, sharding: Option<Sharding>) -> &mut Self {
+        self.sharding = sharding;
+        self
+    }
+
+    /// Sets the number of threads encoding and writing the indexes, which
+    /// are built concurrently, and reading chunks ahead in a
+    /// [`scan`](crate::storage::Storage::scan_with_config).
+    ///
+    /// Defaults to 0, which uses one thread per CPU.
+    pub fn threads(&mut self, threads: usize) -> &mut Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Sets the compression, filters and sharding recorded in the codecs of
+    /// the metadata of an index array, so that the store can be rewritten
+    /// the way it was written. Codecs that no setting writes are ignored.
+    pub(crate) fn array_metadata(&mut self, metadata: &serde_json::Value) -> &mut Self {
+        let codecs = |metadata: &serde_json::Value| {
+            metadata["codecs"].as_array().cloned().unwrap_or_default()
+        };
+        self.compression = Compression::None;
+        let mut pending = codecs(metadata);
+        while let Some(codec) = pending.pop() {
+            // Codecs without configuration are only named
+            let name = codec.as_str().or_else(|| codec["name"].as_str());
+            let configuration = &codec["configuration"];
+            let level = &configuration["level"];
+            let compression = match name.unwrap_or_default() {
+                "transpose" => {
+                    self.transpose = true;
+                    continue;
+                }
+                DELTA_CODEC => {
+                    self.delta = true;
+                    continue;
+                }
+                "sharding_indexed" => {
+                    let chunk_rows = configuration["chunk_shape"][0].as_u64();
+                    let shard_rows =
+                        metadata["chunk_grid"]["configuration"]["chunk_shape"][0].as_u64();
+                    if let (Some(chunk_rows), Some(shard_rows)) = (chunk_rows, shard_rows) {
+                        self.sharding = Some(Sharding {
+                            chunk_rows,
+                            chunks_per_shard: shard_rows / chunk_rows,
+                        });
+                    }
+                    // Inner chunks hold the filters and the compression
+                    pending.extend(codecs(configuration));
+                    continue;
+                }
+                "gzip" => format!("gzip:{level}"),
+                "zstd" => format!("zstd:{level}"),
+                "blosc" => format!(
+                    "blosc:{}:{}",
+                    configuration["cname"].as_str().unwrap_or_default(),
+                    configuration["clevel"]
+                ),
+                _ => continue,
+            };
+            if let Ok(compression) = compression.parse() {
+                self.compression = compression;
+            }
+        }
+        self
+    }
+
+    pub fn build(&self) -> Config {
+        Config {
+            memory_budget: self.memory_budget,
+            temp_dir: self.temp_dir.clone().unwrap_or_else(std::env::temp_dir),
+            merge_fan_in: self.merge_fan_in.max(2),
+            dictionary_layout: self.dictionary_layout,
+            rdf_format: self.rdf_format,
+            compression: self.compression,
+            transpose: self.transpose,
+            delta: self.delta,
+            sharding: self.sharding,
+            threads: self.threads,
+        }
+    }
+}
+
+pub(crate) struct ArrayConfig {
     pub shape: Vec<u64>,
     pub data_type: DataType,
     pub chunk_grid: ChunkGrid,
     pub fill_value: FillValue,
     pub index: Index,
+    /// Path of the array in the store.
+    pub path: String,
+    /// First row of every term followed by the number of rows, when the
+    /// rows of several terms share a chunk.
+    pub offsets: Option<Vec<u64>>,
+    /// Identifiers with rows, whose offsets are the only ones kept, when
+    /// the other identifiers are left out.
+    pub keys: Option<Vec<u64>>,
+    /// Rows of every chunk, when chunks hold the rows of several terms.
+    pub chunk_rows: Option<u64>,
+    /// Number of quads, which excludes the placeholder rows.
+    pub quads: u64,
 }
@@ -0,0 +1,160 @@
+use oxrdf::GraphName;
+use oxrdf::NamedNode;
+use oxrdf::Quad as OxQuad;
+use oxrdf::Subject;
+use oxrdf::Term;
+use zarrs::array::Array;
+use zarrs::group::GroupBuilder;
+use zarrs::storage::ReadableWritableStorageTraits;
+
+use crate::config::Config;
+use crate::dictionary::Delta;
+use crate::dictionary::TermId;
+use crate::dictionary::DEFAULT_GRAPH;
+use crate::error::ZarrDfError;
+use crate::query::BasicGraphPattern;
+use crate::sort::ExternalSorter;
+use crate::storage::DeltaIndexes;
+use crate::storage::Storage;
+use crate::storage::TOMBSTONES;
+use crate::utils::serialize_graph_name;
+use crate::utils::serialize_term;
+use crate::Quad;
+
+/// Number of quads removed by a deletion, along with the indexes of their
+/// tombstones if there are any.
+type Tombstones<S> = (u64, Option<[Array<S>; 3]>);
+
+impl<S: ReadableWritableStorageTraits + 'static> Storage<S> {
+    /// Removes the given quads, ignoring the ones the store does not hold,
+    /// and returns the number of quads removed.
+    ///
+    /// Removed quads are recorded as tombstones: the indexes of a new delta,
+    /// under its [`TOMBSTONES`] group, whose rows queries subtract from the
+    /// chunks of the arrays before them. Terms stay in the dictionary until
+    /// [`compact`](Storage::compact) drops the ones no quad references.
+    ///
+    /// Tombstones are encoded like the arrays of the store.
+    pub fn delete(&mut self, quads: impl IntoIterator<Item = OxQuad>) -> Result<u64, ZarrDfError> {
+        let config = self.config()?;
+        self.transaction(|storage| storage.delete_quads(quads, &config))
+    }
+
+    /// Removes the given quads within a [`transaction`](Storage::transaction).
+    pub(crate) fn delete_quads(
+        &mut self,
+        quads: impl IntoIterator<Item = OxQuad>,
+        config: &Config,
+    ) -> Result<u64, ZarrDfError> {
+        let quads = quads.into_iter().map(|quad| {
+            Ok([
+                serialize_term(quad.subject),
+                serialize_term(quad.predicate),
+                serialize_term(quad.object),
+                serialize_graph_name(quad.graph_name),
+            ])
+        });
+        // Quads with terms missing from the dictionary cannot be stored
+        let mut sorter = ExternalSorter::new(config.memory_budget, config);
+        for quad in Self::encode(quads, &self.dictionary) {
+            sorter.push(quad?)?;
+        }
+        let sorted = sorter.finish()?;
+        let removed = self.write_tombstones(self.filter_stored(sorted.iter()?, true)?, config)?;
+        Ok(self.push_tombstones(removed))
+    }
+
+    /// Removes the quads matching a pattern, where `None` matches any term,
+    /// and returns the number of quads removed, see [`delete`](Storage::delete).
+    pub fn delete_matching(
+        &mut self,
+        subject: Option<&Subject>,
+        predicate: Option<&NamedNode>,
+        object: Option<&Term>,
+        graph: Option<&GraphName>,
+    ) -> Result<u64, ZarrDfError> {
+        let config = self.config()?;
+        self.transaction(|storage| {
+            storage.delete_quads_matching(subject, predicate, object, graph, &config)
+        })
+    }
+
+    /// Removes the quads matching a pattern within a
+    /// [`transaction`](Storage::transaction).
+    pub(crate) fn delete_quads_matching(
+        &mut self,
+        subject: Option<&Subject>,
+        predicate: Option<&NamedNode>,
+        object: Option<&Term>,
+        graph: Option<&GraphName>,
+        config: &Config,
+    ) -> Result<u64, ZarrDfError> {
+        // A bound term missing from the dictionary cannot match anything
+        let Some([s, p, o, g]) = self.locate_terms(subject, predicate, object, graph) else {
+            return Ok(0);
+        };
+        let quads = self
+            .quads_matching_iter(s, p, o, g)
+            .map(|quad| quad.map(|quad| quad.map(TermId::get)));
+        let removed = self.write_tombstones(quads, config)?;
+        Ok(self.push_tombstones(removed))
+    }
+
+    /// Removes the quads of every named graph within a
+    /// [`transaction`](Storage::transaction).
+    pub(crate) fn delete_named_graphs(&mut self, config: &Config) -> Result<u64, ZarrDfError> {
+        let quads = self
+            .quads_matching_iter(None, None, None, None)
+            .filter(|quad| quad.as_ref().map_or(true, |quad| quad[3] != DEFAULT_GRAPH))
+            .map(|quad| quad.map(|quad| quad.map(TermId::get)));
+        let removed = self.write_tombstones(quads, config)?;
+        Ok(self.push_tombstones(removed))
+    }
+
+    /// Writes the indexes of quads the store holds under the [`TOMBSTONES`]
+    /// group of the next delta, returning them along with their number, or
+    /// writes nothing if there are none.
+    fn write_tombstones(
+        &self,
+        quads: impl Iterator<Item = Result<Quad, ZarrDfError>>,
+        config: &Config,
+    ) -> Result<Tombstones<S>, ZarrDfError> {
+        let mut count = 0;
+        let quads = quads.inspect(|quad| count += u64::from(quad.is_ok()));
+        let sorted = Self::sort_quads(quads, config)?;
+        if count == 0 {
+            return Ok((0, None));
+        }
+
+        // Deletions add no term
+        let prefix = format!(
+            "{}/{TOMBSTONES}",
+            self.create_delta(&Delta::default(), config)?
+        );
+        GroupBuilder::new()
+            .build(self.store.clone(), &prefix)?
+            .store_metadata()?;
+        let removed = Self::write_indexes(
+            &sorted,
+            &prefix,
+            self.store.clone(),
+            &self.dictionary,
+            config,
+            true,
+        )?;
+        Ok((count, Some(removed)))
+    }
+
+    /// Applies the tombstones written by [`write_tombstones`](Storage::write_tombstones)
+    /// to the following queries, returning their number.
+    fn push_tombstones(&mut self, (count, removed): Tombstones<S>) -> u64 {
+        if removed.is_some() {
+            self.dictionary.push_delta(Delta::default());
+            self.deltas.push(DeltaIndexes {
+                added: None,
+                removed,
+            });
+        }
+        count
+    }
+}
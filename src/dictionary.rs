@@ -1,61 +1,505 @@
 use core::str;
+use std::iter;
+
 use fcsd::Set;
+use itertools::process_results;
+
+use oxrdf::GraphName;
+use oxrdf::NamedNode;
+use oxrdf::Quad;
+use oxrdf::Subject;
+use oxrdf::Term;
+use oxrdf::Triple;
 
 use crate::error::ZarrDfError;
+use crate::index::Index;
+use crate::utils::deserialize_term;
+
+/// Identifier of the default graph; named graphs are numbered from one.
+pub const DEFAULT_GRAPH: TermId = TermId(0);
+
+/// Identifier of a term in one position of the [`Dictionary`], which is also
+/// the number of its chunk in the index of that position.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TermId(u64);
+
+impl TermId {
+    pub const fn new(id: u64) -> Self {
+        TermId(id)
+    }
+
+    pub const fn get(self) -> u64 {
+        self.0
+    }
+
+    /// Builds the identifier of the term at `index` of a section.
+    pub(crate) fn from_index(index: usize) -> Self {
+        // usize is never wider than 64 bits on the supported platforms
+        TermId(index as u64)
+    }
+
+    /// Returns the position of the term within a section, which does not fit
+    /// in a usize on 32-bit platforms when there are too many terms.
+    pub(crate) fn index(self) -> Result<usize, ZarrDfError> {
+        usize::try_from(self.0).map_err(|_| ZarrDfError::TermIdOverflow(self.0))
+    }
+}
+
+impl From<u64> for TermId {
+    fn from(id: u64) -> Self {
+        TermId(id)
+    }
+}
+
+impl From<TermId> for u64 {
+    fn from(id: TermId) -> Self {
+        id.0
+    }
+}
+
+impl std::fmt::Display for TermId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A sorted set of terms, front-coded with [`fcsd`].
+///
+/// fcsd cannot build a set without keys, so empty sections hold no set.
+#[derive(Clone, Default)]
+pub struct Section(Option<Set>);
+
+impl Section {
+    /// Builds the section from sorted and deduplicated terms.
+    fn new(
+        terms: impl IntoIterator<Item = Result<String, ZarrDfError>>,
+    ) -> Result<Self, ZarrDfError> {
+        let mut terms = terms.into_iter().peekable();
+        if terms.peek().is_none() {
+            return Ok(Section(None));
+        }
+        let set = process_results(terms, |terms| Set::new(terms))??;
+        Ok(Section(Some(set)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.as_ref().map_or(0, Set::len)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_none()
+    }
+
+    /// Iterates over the identifiers and terms in lexicographical order.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, Vec<u8>)> + '_ {
+        self.0.iter().flat_map(Set::iter)
+    }
 
+    /// Reads a section written by [`to_bytes`](Section::to_bytes).
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<Self, ZarrDfError> {
+        if bytes.is_empty() {
+            return Ok(Section(None));
+        }
+        Ok(Section(Some(Set::deserialize_from(bytes)?)))
+    }
+
+    /// Returns the raw fcsd serialization of the section, which is empty for
+    /// an empty section.
+    pub(crate) fn to_bytes(&self) -> Result<Vec<u8>, ZarrDfError> {
+        let mut bytes = Vec::new();
+        if let Some(set) = &self.0 {
+            bytes.reserve(set.size_in_bytes());
+            set.serialize_into(&mut bytes)?;
+        }
+        Ok(bytes)
+    }
+
+    /// Returns the serialized term with the given identifier.
+    pub fn term(&self, id: usize) -> Option<Vec<u8>> {
+        let set = self.0.as_ref()?;
+        (id < set.len()).then(|| set.decoder().run(id))
+    }
+
+    fn locate(&self, term: &str) -> Option<usize> {
+        self.0.as_ref()?.locator().run(term)
+    }
+}
+
+/// Maps every term to an identifier per position.
+///
+/// Subjects and objects are identified in the shared section first and then
+/// in the section of their own position, as in HDT. With the
+/// [`Shared`](crate::config::DictionaryLayout::Shared) layout, a term that
+/// is both a subject and an object has the same identifier in both positions;
+/// otherwise the shared section is empty.
+///
+/// Terms first seen in [appended](crate::storage::Storage::append) data are
+/// identified after all the others, in the sections of a [`Delta`].
 pub struct Dictionary {
-    subjects: Set,
-    predicates: Set,
-    objects: Set,
+    shared: Section,
+    subjects: Section,
+    predicates: Section,
+    objects: Section,
+    graphs: Section,
+    deltas: Vec<Delta>,
 }
 
-impl Dictionary {
+/// Sections of the terms first seen in one batch of appended data, whose
+/// identifiers follow those of the base sections and of every previous
+/// delta, so that appending never renumbers a term.
+///
+/// Deltas have no shared section: a new term that is both a subject and an
+/// object gets an identifier in each position.
+#[derive(Clone, Default)]
+pub(crate) struct Delta {
+    pub(crate) subjects: Section,
+    pub(crate) predicates: Section,
+    pub(crate) objects: Section,
+    pub(crate) graphs: Section,
+}
+
+impl Delta {
+    /// Builds the delta from sorted and deduplicated terms, which must not
+    /// be in the dictionary it extends.
     pub(crate) fn new(
-        subjects: impl IntoIterator<Item = String>,
-        predicates: impl IntoIterator<Item = String>,
-        objects: impl IntoIterator<Item = String>,
+        subjects: impl IntoIterator<Item = Result<String, ZarrDfError>>,
+        predicates: impl IntoIterator<Item = Result<String, ZarrDfError>>,
+        objects: impl IntoIterator<Item = Result<String, ZarrDfError>>,
+        graphs: impl IntoIterator<Item = Result<String, ZarrDfError>>,
     ) -> Result<Self, ZarrDfError> {
-        let mut subjects: Vec<_> = subjects.into_iter().collect();
-        let mut predicates: Vec<_> = predicates.into_iter().collect();
-        let mut objects: Vec<_> = objects.into_iter().collect();
-
-        subjects.sort();
-        predicates.sort();
-        objects.sort();
+        Ok(Delta {
+            subjects: Section::new(subjects)?,
+            predicates: Section::new(predicates)?,
+            objects: Section::new(objects)?,
+            graphs: Section::new(graphs)?,
+        })
+    }
+}
 
+impl Dictionary {
+    /// Builds the dictionary from sorted and deduplicated terms.
+    ///
+    /// `subjects` and `objects` must not contain the terms in `shared`, and
+    /// `graphs` only holds the named graphs, as the default graph always has
+    /// the identifier [`DEFAULT_GRAPH`].
+    pub(crate) fn new(
+        shared: impl IntoIterator<Item = Result<String, ZarrDfError>>,
+        subjects: impl IntoIterator<Item = Result<String, ZarrDfError>>,
+        predicates: impl IntoIterator<Item = Result<String, ZarrDfError>>,
+        objects: impl IntoIterator<Item = Result<String, ZarrDfError>>,
+        graphs: impl IntoIterator<Item = Result<String, ZarrDfError>>,
+    ) -> Result<Self, ZarrDfError> {
         let dictionary = Dictionary {
-            subjects: Set::new(subjects)?,
-            predicates: Set::new(predicates)?,
-            objects: Set::new(objects)?,
+            shared: Section::new(shared)?,
+            subjects: Section::new(subjects)?,
+            predicates: Section::new(predicates)?,
+            objects: Section::new(objects)?,
+            graphs: Section::new(graphs)?,
+            deltas: Vec::new(),
         };
 
         Ok(dictionary)
     }
 
-    pub fn subjects(&self) -> Set {
-        self.subjects.clone()
+    pub(crate) fn from_sections(
+        shared: Section,
+        subjects: Section,
+        predicates: Section,
+        objects: Section,
+        graphs: Section,
+    ) -> Self {
+        Dictionary {
+            shared,
+            subjects,
+            predicates,
+            objects,
+            graphs,
+            deltas: Vec::new(),
+        }
+    }
+
+    /// Terms that are both subjects and objects, if they are shared.
+    pub fn shared_section(&self) -> &Section {
+        &self.shared
+    }
+
+    /// Subjects outside of the shared section.
+    pub fn subject_section(&self) -> &Section {
+        &self.subjects
+    }
+
+    pub fn predicate_section(&self) -> &Section {
+        &self.predicates
     }
 
-    pub fn predicates(&self) -> Set {
-        self.predicates.clone()
+    /// Objects outside of the shared section.
+    pub fn object_section(&self) -> &Section {
+        &self.objects
     }
 
-    pub fn objects(&self) -> Set {
-        self.objects.clone()
+    pub fn graph_section(&self) -> &Section {
+        &self.graphs
     }
 
-    pub fn get_subject_idx(&self, subject: &str) -> Option<i32> {
-        let mut locator = self.subjects.locator();
-        locator.run(subject).map(|idx| idx as i32)
+    /// Every subject as an fcsd set, or `None` if there are none, as in an
+    /// empty store.
+    ///
+    /// The set is rebuilt from the shared, subject and appended sections, so
+    /// the positions of its terms are not their identifiers.
+    #[deprecated(note = "use `subject_section`, which does not copy the set")]
+    pub fn subjects(&self) -> Option<Set> {
+        self.role_set(Index::Subject)
     }
 
-    pub fn get_predicate_idx(&self, predicate: &str) -> Option<i32> {
-        let mut locator = self.predicates.locator();
-        locator.run(predicate).map(|idx| idx as i32)
+    /// Every predicate as an fcsd set, or `None` if there are none, like
+    /// [`subjects`](Dictionary::subjects).
+    #[deprecated(note = "use `predicate_section`, which does not copy the set")]
+    pub fn predicates(&self) -> Option<Set> {
+        self.role_set(Index::Predicate)
     }
 
-    pub fn get_object_idx(&self, object: &str) -> Option<i32> {
-        let mut locator = self.objects.locator();
-        locator.run(object).map(|idx| idx as i32)
+    /// Every object as an fcsd set, or `None` if there are none, like
+    /// [`subjects`](Dictionary::subjects).
+    #[deprecated(note = "use `object_section`, which does not copy the set")]
+    pub fn objects(&self) -> Option<Set> {
+        self.role_set(Index::Object)
+    }
+
+    /// Merges the sections of a position into one set.
+    fn role_set(&self, index: Index) -> Option<Set> {
+        let mut terms: Vec<_> = self
+            .sections(index)
+            .flat_map(Section::iter)
+            .map(|(_, term)| term)
+            .collect();
+        terms.sort_unstable();
+        terms.dedup();
+        // Sorted and deduplicated terms only fail to build an empty set
+        Set::new(terms).ok()
+    }
+
+    /// Sections of the terms added by every append, oldest first.
+    pub(crate) fn deltas(&self) -> &[Delta] {
+        &self.deltas
+    }
+
+    /// Identifies the terms of `delta` after all the others.
+    pub(crate) fn push_delta(&mut self, delta: Delta) {
+        self.deltas.push(delta);
+    }
+
+    /// Forgets the terms of the last delta.
+    pub(crate) fn pop_delta(&mut self) -> Option<Delta> {
+        self.deltas.pop()
+    }
+
+    /// Forgets the terms of every delta but the first `len` ones.
+    pub(crate) fn truncate_deltas(&mut self, len: usize) {
+        self.deltas.truncate(len);
+    }
+
+    pub fn shared_len(&self) -> usize {
+        self.shared.len()
+    }
+
+    /// Number of subjects, including the shared ones.
+    pub fn subjects_len(&self) -> usize {
+        self.sections(Index::Subject).map(Section::len).sum()
+    }
+
+    pub fn predicates_len(&self) -> usize {
+        self.sections(Index::Predicate).map(Section::len).sum()
+    }
+
+    /// Number of objects, including the shared ones.
+    pub fn objects_len(&self) -> usize {
+        self.sections(Index::Object).map(Section::len).sum()
+    }
+
+    /// Number of graphs, including the default graph.
+    pub fn graphs_len(&self) -> usize {
+        self.graph_sections().map(Section::len).sum::<usize>() + 1
+    }
+
+    /// Number of terms in every position of a quad.
+    pub(crate) fn lens(&self) -> [usize; 4] {
+        [
+            self.subjects_len(),
+            self.predicates_len(),
+            self.objects_len(),
+            self.graphs_len(),
+        ]
+    }
+
+    pub fn get_subject_idx(&self, subject: &str) -> Option<TermId> {
+        locate_in(self.sections(Index::Subject), subject).map(TermId::from_index)
+    }
+
+    pub fn get_predicate_idx(&self, predicate: &str) -> Option<TermId> {
+        locate_in(self.sections(Index::Predicate), predicate).map(TermId::from_index)
+    }
+
+    pub fn get_object_idx(&self, object: &str) -> Option<TermId> {
+        locate_in(self.sections(Index::Object), object).map(TermId::from_index)
+    }
+
+    /// Returns the identifier of a graph serialized by `serialize_graph_name`,
+    /// where the empty string stands for the default graph.
+    pub fn get_graph_idx(&self, graph: &str) -> Option<TermId> {
+        if graph.is_empty() {
+            return Some(DEFAULT_GRAPH);
+        }
+        locate_in(self.graph_sections(), graph).map(|idx| TermId::from_index(idx + 1))
+    }
+
+    /// Returns the identifier as a subject of the object `id`, if the term
+    /// is also a subject.
+    ///
+    /// With a shared section this needs no lookup for shared terms, which
+    /// is what makes subject-object joins cheap.
+    pub fn object_as_subject(&self, id: TermId) -> Option<TermId> {
+        self.convert_shared(Index::Object, Index::Subject, id)
+    }
+
+    /// Returns the identifier as an object of the subject `id`, if the term
+    /// is also an object.
+    pub fn subject_as_object(&self, id: TermId) -> Option<TermId> {
+        self.convert_shared(Index::Subject, Index::Object, id)
+    }
+
+    pub fn subject_term(&self, id: TermId) -> Result<Subject, ZarrDfError> {
+        Ok(self.term(Index::Subject, id)?.try_into()?)
+    }
+
+    pub fn predicate_term(&self, id: TermId) -> Result<NamedNode, ZarrDfError> {
+        Ok(self.term(Index::Predicate, id)?.try_into()?)
+    }
+
+    pub fn object_term(&self, id: TermId) -> Result<Term, ZarrDfError> {
+        self.term(Index::Object, id)
+    }
+
+    /// Returns the graph with the given identifier, where [`DEFAULT_GRAPH`]
+    /// stands for the default graph.
+    pub fn graph_term(&self, id: TermId) -> Result<GraphName, ZarrDfError> {
+        if id == DEFAULT_GRAPH {
+            return Ok(GraphName::DefaultGraph);
+        }
+        let term = term_in(self.graph_sections(), id.index()? - 1);
+        match Self::decode(term, id)? {
+            Term::NamedNode(iri) => Ok(iri.into()),
+            Term::BlankNode(id) => Ok(id.into()),
+            term => Err(NamedNode::try_from(term).unwrap_err().into()),
+        }
+    }
+
+    /// Decodes a triple of identifiers as returned by `triples_matching`.
+    pub fn triple(&self, [s, p, o]: [TermId; 3]) -> Result<Triple, ZarrDfError> {
+        Ok(Triple::new(
+            self.subject_term(s)?,
+            self.predicate_term(p)?,
+            self.object_term(o)?,
+        ))
+    }
+
+    /// Decodes a quad of identifiers as returned by `quads_matching`.
+    pub fn quad(&self, [s, p, o, g]: [TermId; 4]) -> Result<Quad, ZarrDfError> {
+        Ok(Quad::new(
+            self.subject_term(s)?,
+            self.predicate_term(p)?,
+            self.object_term(o)?,
+            self.graph_term(g)?,
+        ))
+    }
+
+    /// Returns the identifier of a serialized term in the given position.
+    pub(crate) fn locate(&self, index: Index, term: &str) -> Option<TermId> {
+        locate_in(self.sections(index), term).map(TermId::from_index)
+    }
+
+    /// Decodes the term with identifier `id` in the given position.
+    pub(crate) fn term(&self, index: Index, id: TermId) -> Result<Term, ZarrDfError> {
+        Self::decode(term_in(self.sections(index), id.index()?), id)
+    }
+
+    /// Returns the identifier in the position `to` of the term whose
+    /// identifier in the position `from` is `id`, if the term occurs there.
+    pub(crate) fn convert(&self, id: TermId, from: Index, to: Index) -> Option<TermId> {
+        match (from, to) {
+            _ if from == to => Some(id),
+            (Index::Subject, Index::Object) => self.subject_as_object(id),
+            (Index::Object, Index::Subject) => self.object_as_subject(id),
+            _ => {
+                let term = self.term_bytes(from, id)?;
+                self.locate(to, str::from_utf8(&term).ok()?)
+            }
+        }
+    }
+
+    /// Returns the serialized term with identifier `id` in the given position.
+    fn term_bytes(&self, index: Index, id: TermId) -> Option<Vec<u8>> {
+        term_in(self.sections(index), id.index().ok()?)
+    }
+
+    /// Sections identifying the terms of a position, in the order of their
+    /// identifiers: the shared section for subjects and objects, the section
+    /// of the position and the sections of the position in every delta.
+    fn sections(&self, index: Index) -> impl Iterator<Item = &Section> {
+        let (section, delta): (_, fn(&Delta) -> &Section) = match index {
+            Index::Subject => (&self.subjects, |delta| &delta.subjects),
+            Index::Predicate => (&self.predicates, |delta| &delta.predicates),
+            Index::Object => (&self.objects, |delta| &delta.objects),
+        };
+        let shared = (index != Index::Predicate).then_some(&self.shared);
+        shared
+            .into_iter()
+            .chain([section])
+            .chain(self.deltas.iter().map(delta))
+    }
+
+    /// Sections identifying the named graphs, like [`sections`](Dictionary::sections).
+    fn graph_sections(&self) -> impl Iterator<Item = &Section> {
+        iter::once(&self.graphs).chain(self.deltas.iter().map(|delta| &delta.graphs))
+    }
+
+    /// Converts the identifier of a subject or an object in the position
+    /// `from` to its identifier in the position `to`.
+    fn convert_shared(&self, from: Index, to: Index, id: TermId) -> Option<TermId> {
+        let idx = id.index().ok()?;
+        if idx < self.shared.len() {
+            return Some(id);
+        }
+        let term = self.term_bytes(from, id)?;
+        self.locate(to, str::from_utf8(&term).ok()?)
+    }
+
+    /// Decodes a term found by its identifier `id`.
+    fn decode(term: Option<Vec<u8>>, id: TermId) -> Result<Term, ZarrDfError> {
+        let term = term.ok_or(ZarrDfError::TermNotFound(id.get()))?;
+        deserialize_term(&term)
+    }
+}
+
+/// Locates a term in consecutive sections, where the identifiers of every
+/// section follow those of the previous ones.
+fn locate_in<'a>(sections: impl Iterator<Item = &'a Section>, term: &str) -> Option<usize> {
+    let mut offset = 0;
+    for section in sections {
+        if let Some(idx) = section.locate(term) {
+            return Some(offset + idx);
+        }
+        offset += section.len();
+    }
+    None
+}
+
+/// Returns the term at `idx` of consecutive sections, see [`locate_in`].
+fn term_in<'a>(sections: impl Iterator<Item = &'a Section>, mut idx: usize) -> Option<Vec<u8>> {
+    for section in sections {
+        if idx < section.len() {
+            return section.term(idx);
+        }
+        idx -= section.len();
     }
+    None
 }
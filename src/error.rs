@@ -1,4 +1,10 @@
 use std::num::TryFromIntError;
+use std::str::Utf8Error;
+
+use oxrdf::IriParseError;
+use oxrdf::TermParseError;
+use oxrdf::TryFromTermError;
+use rayon::ThreadPoolBuildError;
 
 use zarrs::array::codec::bytes_to_bytes::gzip::GzipCompressionLevelError;
 use zarrs::array::ArrayCreateError;
@@ -17,12 +23,30 @@ pub enum ZarrDfError {
     #[error("Unknown RDF format: {0}")]
     UnknownFormat(String),
 
+    #[error("Unknown compression: {0}")]
+    UnknownCompression(String),
+
+    #[error("Invalid sharding: {0}")]
+    InvalidSharding(String),
+
+    #[error("Unsupported data type: {0}")]
+    UnsupportedDataType(String),
+
     #[error("Chunk not found: {0}")]
     ChunkNotFound(u64),
 
     #[error("Invalid query")]
     InvalidQuery,
 
+    #[error("SPARQL syntax error: {0}")]
+    SparqlSyntax(String),
+
+    #[error("Unsupported SPARQL feature: {0}")]
+    UnsupportedQuery(String),
+
+    #[error("Query results cannot be written as {0}")]
+    IncompatibleFormat(String),
+
     #[error("Subjects not in metadata")]
     SubjectsNotInMetadata,
 
@@ -32,6 +56,33 @@ pub enum ZarrDfError {
     #[error("Objects not in metadata")]
     ObjectsNotInMetadata,
 
+    #[error("Graphs not in metadata")]
+    GraphsNotInMetadata,
+
+    #[error("Unsupported store version: {0}")]
+    UnsupportedVersion(String),
+
+    #[error("Corrupted store: {0}")]
+    Corrupted(String),
+
+    #[error("Term identifier overflow: {0}")]
+    TermIdOverflow(u64),
+
+    #[error("Term not found: {0}")]
+    TermNotFound(u64),
+
+    #[error(transparent)]
+    TermEncoding(#[from] Utf8Error),
+
+    #[error(transparent)]
+    TermParsing(#[from] TermParseError),
+
+    #[error(transparent)]
+    TermConversion(#[from] TryFromTermError),
+
+    #[error(transparent)]
+    InvalidPrefix(#[from] IriParseError),
+
     #[error(transparent)]
     RectangularDimensionConversion(#[from] TryFromIntError),
 
@@ -64,4 +115,7 @@ pub enum ZarrDfError {
 
     #[error(transparent)]
     ArraySubset(#[from] IncompatibleDimensionalityError),
+
+    #[error(transparent)]
+    ThreadPool(#[from] ThreadPoolBuildError),
 }
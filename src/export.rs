@@ -0,0 +1,170 @@
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Write;
+use std::ops::ControlFlow;
+
+use oxrdf::NamedNode;
+use oxrdfio::RdfFormat as OxRdfFormat;
+use oxrdfio::RdfSerializer;
+use zarrs::storage::ReadableWritableStorageTraits;
+
+use crate::config::Config;
+use crate::dictionary::TermId;
+use crate::error::ZarrDfError;
+use crate::query::BasicGraphPattern;
+use crate::rdf_format::RdfFormat;
+use crate::scan::ScanOrder;
+use crate::sort::ExternalSorter;
+use crate::storage::Storage;
+
+/// Prefixes declared on export whenever one of their IRIs is used as a
+/// predicate.
+const WELL_KNOWN_PREFIXES: [(&str, &str); 5] = [
+    ("rdf", "http://www.w3.org/1999/02/22-rdf-syntax-ns#"),
+    ("rdfs", "http://www.w3.org/2000/01/rdf-schema#"),
+    ("xsd", "http://www.w3.org/2001/XMLSchema#"),
+    ("owl", "http://www.w3.org/2002/07/owl#"),
+    ("schema", "http://schema.org/"),
+];
+
+impl<S: ReadableWritableStorageTraits + 'static> Storage<S> {
+    /// Serializes the whole dataset into `writer`, returning it once done.
+    ///
+    /// Formats without named graphs get the union of every graph. For Turtle,
+    /// TriG and RDF/XML, the namespaces of the predicates are declared as
+    /// prefixes so the output stays compact.
+    pub fn export<W: Write>(&self, writer: W, format: RdfFormat) -> Result<W, ZarrDfError> {
+        self.export_with_config(writer, format, &Config::default())
+    }
+
+    /// Explicit configuration version of [`export`](Storage::export).
+    ///
+    /// The configuration sets the threads scanning the store and, for TriG,
+    /// the memory budget and temporary directory of the quads sorted by
+    /// graph.
+    pub fn export_with_config<W: Write>(
+        &self,
+        writer: W,
+        format: RdfFormat,
+        config: &Config,
+    ) -> Result<W, ZarrDfError> {
+        let prefixes = self.prefixes()?;
+        self.write_dataset(writer, format, &prefixes, config)
+    }
+
+    /// Explicit prefixes version of [`export`](Storage::export).
+    pub fn export_with_prefixes<W: Write>(
+        &self,
+        writer: W,
+        format: RdfFormat,
+        prefixes: &[(String, String)],
+    ) -> Result<W, ZarrDfError> {
+        self.write_dataset(writer, format, prefixes, &Config::default())
+    }
+
+    fn write_dataset<W: Write>(
+        &self,
+        writer: W,
+        format: RdfFormat,
+        prefixes: &[(String, String)],
+        config: &Config,
+    ) -> Result<W, ZarrDfError> {
+        let mut serializer = RdfSerializer::from_format(format.into());
+        for (name, iri) in prefixes {
+            serializer = serializer.with_prefix(name, iri)?;
+        }
+        let mut serializer = serializer.for_writer(writer);
+
+        match OxRdfFormat::from(format) {
+            // Sorting the quads by graph keeps every graph in a single block,
+            // with a single scan of the dataset
+            OxRdfFormat::TriG => {
+                let mut sorter = ExternalSorter::new(config.memory_budget, config);
+                let error = self.scan_with_config(ScanOrder::Unordered, config, |quad| {
+                    let [s, p, o, g] = quad.map(TermId::get);
+                    match sorter.push([g, s, p, o]) {
+                        Ok(()) => ControlFlow::Continue(()),
+                        Err(error) => ControlFlow::Break(error),
+                    }
+                })?;
+                if let Some(error) = error {
+                    return Err(error);
+                }
+                for record in sorter.finish()?.iter()? {
+                    let [g, s, p, o] = record?.map(TermId::new);
+                    serializer.serialize_quad(&self.dictionary.quad([s, p, o, g])?)?;
+                }
+            }
+            OxRdfFormat::NQuads => {
+                let error = self.scan_with_config(ScanOrder::Ordered, config, |quad| {
+                    let written = self
+                        .dictionary
+                        .quad(quad)
+                        .and_then(|quad| Ok(serializer.serialize_quad(&quad)?));
+                    match written {
+                        Ok(()) => ControlFlow::Continue(()),
+                        Err(error) => ControlFlow::Break(error),
+                    }
+                })?;
+                if let Some(error) = error {
+                    return Err(error);
+                }
+            }
+            _ => {
+                for triple in self.triples_matching_iter(None, None, None) {
+                    serializer.serialize_triple(&self.dictionary.triple(triple?)?)?;
+                }
+            }
+        }
+
+        Ok(serializer.finish()?)
+    }
+
+    /// Serializes the whole dataset into a file, whose format is guessed from
+    /// its extension.
+    pub fn export_to_path(&self, rdf_path: &str) -> Result<(), ZarrDfError> {
+        let format = RdfFormat::from_path(rdf_path)?;
+        let writer = BufWriter::new(File::create(rdf_path)?);
+        self.export(writer, format)?.flush()?;
+        Ok(())
+    }
+
+    /// Derives prefixes from the namespaces of the predicates, naming them
+    /// after well-known vocabularies or numbering them otherwise.
+    fn prefixes(&self) -> Result<Vec<(String, String)>, ZarrDfError> {
+        let mut namespaces = BTreeSet::new();
+        for id in 0..self.dictionary.predicates_len() {
+            let predicate = self.dictionary.predicate_term(TermId::from_index(id))?;
+            if let Some(namespace) = namespace(&predicate) {
+                namespaces.insert(namespace.to_string());
+            }
+        }
+
+        let mut unknown = 0;
+        let prefixes = namespaces
+            .into_iter()
+            .map(|namespace| {
+                let name = match WELL_KNOWN_PREFIXES
+                    .iter()
+                    .find(|(_, iri)| *iri == namespace)
+                {
+                    Some((name, _)) => name.to_string(),
+                    None => {
+                        unknown += 1;
+                        format!("ns{unknown}")
+                    }
+                };
+                (name, namespace)
+            })
+            .collect();
+        Ok(prefixes)
+    }
+}
+
+/// Returns the IRI up to its last `#` or `/`, if anything follows it.
+fn namespace(iri: &NamedNode) -> Option<&str> {
+    let iri = iri.as_str();
+    let end = iri.rfind(['#', '/'])? + 1;
+    (end < iri.len()).then(|| &iri[..end])
+}
@@ -0,0 +1,393 @@
+use std::collections::HashSet;
+use std::io::Write;
+use std::str::FromStr;
+
+use oxrdf::vocab::rdf;
+use oxrdf::vocab::xsd;
+use oxrdf::BlankNode;
+use oxrdf::GraphName;
+use oxrdf::Literal;
+use oxrdf::NamedNode;
+use oxrdf::Subject;
+use oxrdf::Term;
+use oxrdf::Triple;
+use oxrdfio::RdfFormat as OxRdfFormat;
+use oxrdfio::RdfSerializer;
+use zarrs::storage::ReadableWritableStorageTraits;
+
+use crate::dictionary::TermId;
+use crate::error::ZarrDfError;
+use crate::index::Index;
+use crate::index::COLUMNS;
+use crate::query::matching_rows;
+use crate::rdf_format::RdfFormat;
+use crate::storage::Storage;
+
+/// Number of triples per page of a fragment served over HTTP.
+pub const PAGE_SIZE: usize = 100;
+
+const RDF: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+const HYDRA: &str = "http://www.w3.org/ns/hydra/core#";
+const VOID: &str = "http://rdfs.org/ns/void#";
+
+/// One page of the triples matching a triple pattern, as defined by the
+/// Triple Pattern Fragments specification.
+///
+/// Pages follow the rows of the index answering the pattern, and a triple
+/// stored in several graphs is counted and returned once.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fragment {
+    pub subject: Option<Subject>,
+    pub predicate: Option<NamedNode>,
+    pub object: Option<Term>,
+    /// Number of the page, starting at 1.
+    pub page: u64,
+    pub page_size: usize,
+    pub triples: Vec<Triple>,
+    /// Number of matching triples.
+    pub total: u64,
+    /// Whether there is a page after this one.
+    pub has_next: bool,
+}
+
+impl<S: ReadableWritableStorageTraits + 'static> Storage<S> {
+    /// Returns a page of the triples matching a pattern, reading only the
+    /// chunks that hold it.
+    pub fn fragment(
+        &self,
+        subject: Option<&Subject>,
+        predicate: Option<&NamedNode>,
+        object: Option<&Term>,
+        page: u64,
+        page_size: usize,
+    ) -> Result<Fragment, ZarrDfError> {
+        if page == 0 || page_size == 0 {
+            return Err(ZarrDfError::InvalidQuery);
+        }
+        let offset = (page - 1) * page_size as u64;
+        let (triples, total, has_next) = match self.locate_terms(subject, predicate, object, None) {
+            Some([s, p, o, _]) => self.page([s, p, o], offset, page_size)?,
+            // A bound term missing from the dictionary cannot match anything
+            None => (Vec::new(), 0, false),
+        };
+        let triples = triples
+            .into_iter()
+            .map(|triple| self.dictionary.triple(triple))
+            .collect::<Result<_, ZarrDfError>>()?;
+        Ok(Fragment {
+            subject: subject.cloned(),
+            predicate: predicate.cloned(),
+            object: object.cloned(),
+            page,
+            page_size,
+            triples,
+            total,
+            has_next,
+        })
+    }
+
+    /// Returns up to `limit` triples matching the pattern after skipping
+    /// `offset` of them, along with the number of matches and whether there
+    /// are more.
+    fn page(
+        &self,
+        pattern: [Option<TermId>; 3],
+        offset: u64,
+        limit: usize,
+    ) -> Result<(Vec<[TermId; 3]>, u64, bool), ZarrDfError> {
+        let [subject, predicate, object] = pattern;
+        let index = self.access_path(subject, predicate, object);
+        let [s, p, o] = pattern.map(|id| id.map(TermId::get));
+        let bound = [s, p, o, None];
+        let key = bound[index.positions()[0]];
+
+        if key.is_none() && self.dictionary.graphs_len() == 1 {
+            // Every row matches and is a distinct triple, so whole chunks are
+            // skipped by their size in the chunk grid and only the ones
+            // holding the page are fetched, along with the single rows that
+            // may be placeholders
+            let mut skip = offset;
+            let mut triples = Vec::with_capacity(limit + 1);
+            for key in 0..self.num_chunks(index)? {
+                let len = self.quads_in_chunk(index, key)?;
+                if skip >= len {
+                    skip -= len;
+                    continue;
+                }
+                let rows = self.chunk_rows(index, key)?;
+                let remaining = limit + 1 - triples.len();
+                triples.extend(
+                    rows.chunks_exact(COLUMNS)
+                        .skip(skip as usize)
+                        .take(remaining)
+                        .map(|row| triple(index.quad_from_row(key, row))),
+                );
+                skip = 0;
+                if triples.len() > limit {
+                    break;
+                }
+            }
+            let has_next = triples.len() > limit;
+            triples.truncate(limit);
+            let total = self.num_quads(index)?;
+            return Ok((triples, total, has_next));
+        }
+
+        // The copies of a triple in several graphs share the chunk of its
+        // leading term, so they are merged chunk by chunk, all of which are
+        // read to count the matches when no term leads the index
+        let keys = match key {
+            Some(key) => key..key + 1,
+            None => 0..self.num_chunks(index)?,
+        };
+        let mut total = 0;
+        let mut triples = Vec::with_capacity(limit);
+        for key in keys {
+            let matches = self.triples_in_chunk(index, key, &bound)?;
+            let skip = offset.saturating_sub(total) as usize;
+            let remaining = limit - triples.len();
+            triples.extend(matches.iter().skip(skip).take(remaining));
+            total += matches.len() as u64;
+        }
+        Ok((triples, total, offset + (limit as u64) < total))
+    }
+
+    /// Returns the distinct triples of the chunk of `key` in `index` that
+    /// match the bound components, in the order of its rows.
+    fn triples_in_chunk(
+        &self,
+        index: Index,
+        key: u64,
+        bound: &[Option<u64>; 4],
+    ) -> Result<Vec<[TermId; 3]>, ZarrDfError> {
+        // Only the rows matching the leading columns are read if they are
+        // sorted, and then the graph comes last so copies are adjacent
+        let rows = self.chunk_rows(index, key)?;
+        let sorted = self.sorted(index);
+        let range = match sorted && bound[index.positions()[0]].is_some() {
+            true => matching_rows(&rows, index, bound),
+            false => 0..rows.len() / COLUMNS,
+        };
+        let mut seen = HashSet::new();
+        let mut triples: Vec<[TermId; 3]> = Vec::new();
+        for row in rows[range.start * COLUMNS..range.end * COLUMNS].chunks_exact(COLUMNS) {
+            let quad = index.quad_from_row(key, row);
+            let matches = bound
+                .iter()
+                .zip(&quad)
+                .all(|(bound, component)| bound.is_none_or(|bound| bound == *component));
+            let triple = triple(quad);
+            let new = match sorted {
+                true => triples.last() != Some(&triple),
+                false => seen.insert(triple),
+            };
+            if matches && new {
+                triples.push(triple);
+            }
+        }
+        Ok(triples)
+    }
+}
+
+/// Returns the identifiers of the triple of a quad.
+fn triple([s, p, o, _]: [u64; 4]) -> [TermId; 3] {
+    [s, p, o].map(TermId::new)
+}
+
+impl Fragment {
+    /// Serializes the triples of the fragment along with its hypermedia
+    /// controls, for a fragment served by the endpoint at `endpoint`.
+    ///
+    /// Formats with named graphs keep the controls in a graph of their own,
+    /// apart from the data.
+    pub fn write<W: Write>(
+        &self,
+        writer: W,
+        format: RdfFormat,
+        endpoint: &str,
+    ) -> Result<W, ZarrDfError> {
+        let mut serializer = RdfSerializer::from_format(format.into())
+            .with_prefix("hydra", HYDRA)?
+            .with_prefix("void", VOID)?
+            .with_prefix("rdf", RDF)?
+            .for_writer(writer);
+
+        let metadata = match OxRdfFormat::from(format).supports_datasets() {
+            true => {
+                let url = self.url(endpoint, self.page);
+                GraphName::NamedNode(NamedNode::new(format!("{url}#metadata"))?)
+            }
+            false => GraphName::DefaultGraph,
+        };
+
+        for triple in &self.triples {
+            serializer.serialize_triple(triple)?;
+        }
+        for triple in self.controls(endpoint)? {
+            serializer.serialize_quad(&triple.in_graph(metadata.clone()))?;
+        }
+        Ok(serializer.finish()?)
+    }
+
+    /// Returns the URL of a page of the fragment, with the pattern in the
+    /// explicit representation of the Triple Pattern Fragments specification.
+    pub fn url(&self, endpoint: &str, page: u64) -> String {
+        let mut parameters = Vec::new();
+        if let Some(subject) = &self.subject {
+            parameters.push(("subject", representation(&subject.clone().into())));
+        }
+        if let Some(predicate) = &self.predicate {
+            parameters.push(("predicate", representation(&predicate.clone().into())));
+        }
+        if let Some(object) = &self.object {
+            parameters.push(("object", representation(object)));
+        }
+        if page > 1 {
+            parameters.push(("page", page.to_string()));
+        }
+        let query: Vec<_> = parameters
+            .iter()
+            .map(|(name, value)| format!("{name}={}", encode(value)))
+            .collect();
+        match query.is_empty() {
+            true => endpoint.to_string(),
+            false => format!("{endpoint}?{}", query.join("&")),
+        }
+    }
+
+    /// Returns the Hydra and VoID description of the fragment, its paging
+    /// links and the search form of the dataset.
+    fn controls(&self, endpoint: &str) -> Result<Vec<Triple>, ZarrDfError> {
+        let hydra = |name: &str| NamedNode::new_unchecked(format!("{HYDRA}{name}"));
+        let void = |name: &str| NamedNode::new_unchecked(format!("{VOID}{name}"));
+        let page = NamedNode::new(self.url(endpoint, self.page))?;
+        let dataset = NamedNode::new(format!("{endpoint}#dataset"))?;
+        let total = Literal::new_typed_literal(self.total.to_string(), xsd::INTEGER);
+
+        let mut triples = vec![
+            Triple::new(dataset.clone(), rdf::TYPE, void("Dataset")),
+            Triple::new(dataset.clone(), rdf::TYPE, hydra("Collection")),
+            Triple::new(dataset.clone(), void("subset"), page.clone()),
+            Triple::new(page.clone(), rdf::TYPE, hydra("PartialCollectionView")),
+            Triple::new(page.clone(), void("triples"), total.clone()),
+            Triple::new(page.clone(), hydra("totalItems"), total),
+            Triple::new(
+                page.clone(),
+                hydra("itemsPerPage"),
+                Literal::new_typed_literal(self.page_size.to_string(), xsd::INTEGER),
+            ),
+            Triple::new(
+                page.clone(),
+                hydra("first"),
+                NamedNode::new(self.url(endpoint, 1))?,
+            ),
+        ];
+        if self.page > 1 {
+            let previous = NamedNode::new(self.url(endpoint, self.page - 1))?;
+            triples.push(Triple::new(page.clone(), hydra("previous"), previous));
+        }
+        if self.has_next {
+            let next = NamedNode::new(self.url(endpoint, self.page + 1))?;
+            triples.push(Triple::new(page, hydra("next"), next));
+        }
+
+        // Blank node labels are scoped to the document, so they must not
+        // clash with those of the data
+        let search = BlankNode::default();
+        triples.extend([
+            Triple::new(dataset, hydra("search"), search.clone()),
+            Triple::new(
+                search.clone(),
+                hydra("template"),
+                Literal::new_simple_literal(format!("{endpoint}{{?subject,predicate,object}}")),
+            ),
+            Triple::new(
+                search.clone(),
+                hydra("variableRepresentation"),
+                hydra("ExplicitRepresentation"),
+            ),
+        ]);
+        for (variable, property) in [
+            ("subject", rdf::SUBJECT),
+            ("predicate", rdf::PREDICATE),
+            ("object", rdf::OBJECT),
+        ] {
+            let mapping = BlankNode::default();
+            triples.extend([
+                Triple::new(search.clone(), hydra("mapping"), mapping.clone()),
+                Triple::new(
+                    mapping.clone(),
+                    hydra("variable"),
+                    Literal::new_simple_literal(variable),
+                ),
+                Triple::new(mapping, hydra("property"), property),
+            ]);
+        }
+        Ok(triples)
+    }
+}
+
+/// Parses a term in the explicit representation of the Triple Pattern
+/// Fragments specification, where IRIs are not enclosed in angle brackets,
+/// literals are quoted without escapes and variables stand for unbound
+/// components.
+pub fn parse_representation(value: &str) -> Result<Option<Term>, ZarrDfError> {
+    if value.is_empty() || value.starts_with('?') {
+        return Ok(None);
+    }
+    let term = match value
+        .strip_prefix('"')
+        .and_then(|rest| rest.rsplit_once('"'))
+    {
+        Some((lexical, suffix)) => {
+            let mut escaped = String::with_capacity(lexical.len());
+            for c in lexical.chars() {
+                match c {
+                    '"' => escaped.push_str("\\\""),
+                    '\\' => escaped.push_str("\\\\"),
+                    '\n' => escaped.push_str("\\n"),
+                    '\r' => escaped.push_str("\\r"),
+                    c => escaped.push(c),
+                }
+            }
+            match suffix.strip_prefix("^^") {
+                Some(datatype) => {
+                    let datatype = datatype.trim_start_matches('<').trim_end_matches('>');
+                    format!("\"{escaped}\"^^<{datatype}>")
+                }
+                None => format!("\"{escaped}\"{suffix}"),
+            }
+        }
+        None if value.starts_with("_:") => value.to_string(),
+        None => format!("<{}>", value.trim_start_matches('<').trim_end_matches('>')),
+    };
+    Ok(Some(Term::from_str(&term)?))
+}
+
+/// Formats a term in the explicit representation, see
+/// [`parse_representation`].
+pub fn representation(term: &Term) -> String {
+    match term {
+        Term::NamedNode(node) => node.as_str().to_string(),
+        Term::BlankNode(node) => node.to_string(),
+        Term::Literal(literal) => match literal.language() {
+            Some(language) => format!("\"{}\"@{language}", literal.value()),
+            None if literal.datatype() == xsd::STRING => format!("\"{}\"", literal.value()),
+            None => format!("\"{}\"^^{}", literal.value(), literal.datatype().as_str()),
+        },
+    }
+}
+
+/// Percent-encodes a component of a query string.
+fn encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            byte => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
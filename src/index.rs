@@ -1,7 +1,20 @@
+use zarrs::array::DataType;
 use zarrs::array::DimensionName;
+use zarrs::array::FillValue;
 
-use crate::Triple;
+use crate::Quad;
 
+/// Name of the graph column, which is the last column of every index.
+pub const GRAPH: &str = "graph";
+
+/// Name of the array dimension spanning the columns of an index.
+pub const COLUMN: &str = "column";
+
+/// Number of columns of every index: the two components that are not
+/// indexed, followed by the graph.
+pub const COLUMNS: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Index {
     Subject,
     Predicate,
@@ -9,22 +22,102 @@ pub enum Index {
 }
 
 impl Index {
-    pub fn triple_to_component(&self, triple: &Triple) -> u64 {
+    /// Returns the index answering a pattern whose subject, predicate and
+    /// object are bound as given, which is the first one keyed by a bound
+    /// component or a full scan of the subject index.
+    pub fn for_pattern([subject, predicate, object]: [bool; 3]) -> Index {
+        match (subject, predicate, object) {
+            (true, _, _) => Index::Subject,
+            (false, true, _) => Index::Predicate,
+            (false, false, true) => Index::Object,
+            (false, false, false) => Index::Subject,
+        }
+    }
+
+    /// Reorders a quad so that the indexed component comes first, followed
+    /// by the two remaining components in the order of the array dimensions
+    /// and, lastly, the graph.
+    ///
+    /// The three indexes are laid out as SPO, POS and OSP, so every pattern
+    /// with two bound components is a prefix of one of them.
+    pub fn sort_key(&self, quad: &Quad) -> Quad {
+        let [subject, predicate, object, graph] = *quad;
         match self {
-            Index::Subject => triple[0],
-            Index::Predicate => triple[1],
-            Index::Object => triple[2],
+            Index::Subject => [subject, predicate, object, graph],
+            Index::Predicate => [predicate, object, subject, graph],
+            Index::Object => [object, subject, predicate, graph],
         }
     }
 
-    pub fn dimension_names(&self) -> impl Iterator<Item = DimensionName> {
+    /// Rebuilds the quad stored in a `row` of the chunk of `key`.
+    pub fn quad_from_row(&self, key: u64, row: &[u64]) -> Quad {
+        match self {
+            Index::Subject => [key, row[0], row[1], row[2]],
+            Index::Predicate => [row[1], key, row[0], row[2]],
+            Index::Object => [row[0], row[1], key, row[2]],
+        }
+    }
+
+    /// Positions within a quad of the indexed component, followed by the
+    /// positions of the columns.
+    pub fn positions(&self) -> [usize; 4] {
+        match self {
+            Index::Subject => [0, 1, 2, 3],
+            Index::Predicate => [1, 2, 0, 3],
+            Index::Object => [2, 0, 1, 3],
+        }
+    }
+
+    /// Names of the columns stored in every row of the index.
+    pub fn columns(&self) -> impl Iterator<Item = String> {
         match self {
             Index::Subject => [Index::Predicate, Index::Object],
-            Index::Predicate => [Index::Subject, Index::Object],
+            Index::Predicate => [Index::Object, Index::Subject],
             Index::Object => [Index::Subject, Index::Predicate],
         }
         .into_iter()
-        .map(|s| s.to_string().into())
+        .map(|s| s.to_string())
+        .chain([GRAPH.to_string()])
+    }
+
+    /// Rows are grouped by the indexed component and hold one value per
+    /// column, see [`Index::columns`].
+    pub fn dimension_names(&self) -> impl Iterator<Item = DimensionName> {
+        [self.to_string(), COLUMN.to_string()]
+            .into_iter()
+            .map(DimensionName::from)
+    }
+}
+
+/// Returns the narrowest unsigned integer type able to hold the identifiers of
+/// `len` terms while keeping its maximum value free for the fill value.
+pub fn data_type(len: usize) -> DataType {
+    match len as u64 {
+        len if len <= u8::MAX as u64 => DataType::UInt8,
+        len if len <= u16::MAX as u64 => DataType::UInt16,
+        len if len <= u32::MAX as u64 => DataType::UInt32,
+        _ => DataType::UInt64,
+    }
+}
+
+/// Returns the fill value of an index array, which is the maximum value of
+/// its data type so it never collides with an identifier.
+pub fn fill_value(data_type: &DataType) -> FillValue {
+    match data_type {
+        DataType::UInt8 => FillValue::from(u8::MAX),
+        DataType::UInt16 => FillValue::from(u16::MAX),
+        DataType::UInt32 => FillValue::from(u32::MAX),
+        _ => FillValue::from(u64::MAX),
+    }
+}
+
+/// Returns the mask keeping the bits of an identifier stored as `data_type`.
+pub fn mask(data_type: &DataType) -> u64 {
+    match data_type {
+        DataType::UInt8 => u8::MAX as u64,
+        DataType::UInt16 => u16::MAX as u64,
+        DataType::UInt32 => u32::MAX as u64,
+        _ => u64::MAX,
     }
 }
 
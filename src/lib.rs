@@ -1,10 +1,23 @@
-mod config;
+mod append;
+pub mod bgp;
+mod codec;
+pub mod config;
+mod delete;
 mod dictionary;
 pub mod error;
+mod export;
+pub mod fragments;
 mod index;
 pub mod query;
-mod rdf_format;
+pub mod rdf_format;
+pub mod scan;
+#[cfg(feature = "server")]
+pub mod server;
+mod sort;
+pub mod sparql;
+pub mod statistics;
 pub mod storage;
 mod utils;
+mod verify;
 
-pub(crate) type Triple = [u64; 3];
+pub(crate) type Quad = [u64; 4];
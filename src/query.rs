@@ -1,103 +1,620 @@
+use std::ops::Range;
+
+use itertools::Itertools;
+use oxrdf::GraphName;
+use oxrdf::NamedNode;
+use oxrdf::Quad;
+use oxrdf::Subject;
+use oxrdf::Term;
+use oxrdf::Triple;
 use zarrs::array::Array;
+use zarrs::array::DataType;
 use zarrs::array::ElementOwned;
+use zarrs::array_subset::ArraySubset;
 use zarrs::storage::ReadableWritableStorageTraits;
 
+pub use crate::dictionary::TermId;
+pub use crate::dictionary::DEFAULT_GRAPH;
 use crate::error::ZarrDfError;
+use crate::index::Index;
+use crate::index::COLUMNS;
+use crate::storage::Change;
 use crate::storage::Storage;
+use crate::storage::KEYS_ATTRIBUTE;
+use crate::storage::OFFSETS_ATTRIBUTE;
+use crate::storage::QUADS_ATTRIBUTE;
+use crate::storage::SORTED_ATTRIBUTE;
+use crate::utils::serialize_graph_name;
+use crate::utils::serialize_term;
 
 pub trait Query {
     fn query<T: ElementOwned>(&self, chunk: impl Into<u64>) -> Result<Vec<T>, ZarrDfError>;
+
+    /// Returns the rows of a chunk of an index as identifiers, undoing the
+    /// filters that were applied when writing it.
+    fn rows(&self, chunk: impl Into<u64>) -> Result<Vec<u64>, ZarrDfError>;
+
+    /// Number of chunks in the index, which is one per dictionary identifier,
+    /// or up to the last identifier a delta holds quads of.
+    fn num_chunks(&self) -> u64;
+
+    /// Number of rows of a chunk according to the chunk grid, or the offsets
+    /// of a sharded array, without fetching it. Terms that never occur in the
+    /// indexed position still get a chunk of one row, which holds no quad,
+    /// unless the array is sharded.
+    fn chunk_len(&self, chunk: impl Into<u64>) -> Result<u64, ZarrDfError>;
+
+    /// Number of quads of the index as recorded in the metadata, which
+    /// leaves out placeholder rows. Stores written without the attribute
+    /// count every row.
+    fn num_quads(&self) -> u64;
+
+    /// Whether the rows of every chunk are sorted, as recorded in the
+    /// metadata. Stores written without the attribute are assumed unsorted.
+    fn sorted(&self) -> bool;
 }
 
 impl<S: ReadableWritableStorageTraits + 'static> Query for Array<S> {
     fn query<T: ElementOwned>(&self, chunk: impl Into<u64>) -> Result<Vec<T>, ZarrDfError> {
         let chunk = chunk.into();
-        self.retrieve_chunk_elements_if_exists(&[chunk, 0])?
-            .ok_or(ZarrDfError::ChunkNotFound(chunk))
+        if chunk >= self.num_chunks() {
+            return Err(ZarrDfError::ChunkNotFound(chunk));
+        }
+        // Sharded arrays read the rows of the term from the inner chunks
+        // holding them, located through the index of their shards
+        if let Some(rows) = offsets(self, chunk) {
+            let rows = rows?;
+            if rows.is_empty() {
+                return Ok(Vec::new());
+            }
+            let subset = ArraySubset::new_with_ranges(&[rows, 0..COLUMNS as u64]);
+            return Ok(self.retrieve_array_subset_elements(&subset)?);
+        }
+        // Chunks of terms that never occur in the indexed position only hold
+        // the fill value, so zarrs does not store them
+        Ok(self
+            .retrieve_chunk_elements_if_exists(&[chunk, 0])?
+            .unwrap_or_default())
     }
+
+    fn rows(&self, chunk: impl Into<u64>) -> Result<Vec<u64>, ZarrDfError> {
+        // Identifiers are stored in the narrowest type holding them all
+        Ok(match self.data_type() {
+            DataType::UInt8 => widen(self.query::<u8>(chunk)?),
+            DataType::UInt16 => widen(self.query::<u16>(chunk)?),
+            DataType::UInt32 => widen(self.query::<u32>(chunk)?),
+            DataType::UInt64 => self.query::<u64>(chunk)?,
+            data_type => return Err(ZarrDfError::UnsupportedDataType(data_type.to_string())),
+        })
+    }
+
+    fn num_chunks(&self) -> u64 {
+        // Deltas only list the identifiers they hold, the last one included
+        if let Some(keys) = self.attributes().get(KEYS_ATTRIBUTE) {
+            return keys
+                .as_array()
+                .and_then(|keys| keys.last())
+                .and_then(serde_json::Value::as_u64)
+                .map_or(0, |key| key + 1);
+        }
+        match self.attributes().get(OFFSETS_ATTRIBUTE) {
+            Some(offsets) => offsets
+                .as_array()
+                .map_or(0, |offsets| offsets.len().saturating_sub(1) as u64),
+            None => self.chunk_grid_shape().map_or(0, |shape| shape[0]),
+        }
+    }
+
+    fn chunk_len(&self, chunk: impl Into<u64>) -> Result<u64, ZarrDfError> {
+        let chunk = chunk.into();
+        if chunk >= self.num_chunks() {
+            return Err(ZarrDfError::ChunkNotFound(chunk));
+        }
+        match offsets(self, chunk) {
+            Some(rows) => rows.map(|rows| rows.end - rows.start),
+            None => Ok(self.chunk_shape(&[chunk, 0])?[0].get()),
+        }
+    }
+
+    fn num_quads(&self) -> u64 {
+        let quads = self.attributes().get(QUADS_ATTRIBUTE);
+        quads
+            .and_then(|quads| quads.as_u64())
+            .unwrap_or(self.shape()[0])
+    }
+
+    fn sorted(&self) -> bool {
+        let sorted = self.attributes().get(SORTED_ATTRIBUTE);
+        sorted.and_then(|sorted| sorted.as_bool()).unwrap_or(false)
+    }
+}
+
+/// Returns the range of rows of a sorted chunk of `index` matching the
+/// components of `pattern` bound in its leading columns, found by binary
+/// search. Components bound in later columns are left to filter.
+pub(crate) fn matching_rows(
+    chunk: &[u64],
+    index: Index,
+    pattern: &[Option<u64>; 4],
+) -> Range<usize> {
+    let (rows, _) = chunk.as_chunks::<COLUMNS>();
+    let mut range = 0..rows.len();
+    for (column, position) in index.positions()[1..].iter().enumerate() {
+        let Some(value) = pattern[*position] else {
+            break;
+        };
+        let rows = &rows[range.clone()];
+        let start = rows.partition_point(|row| row[column] < value);
+        let end = rows.partition_point(|row| row[column] <= value);
+        range = range.start + start..range.start + end;
+    }
+    range
+}
+
+/// Returns the rows of a term in a sharded array, where chunks are numbered
+/// by term but stored by consecutive rows, or `None` if the array is not
+/// sharded.
+fn offsets<S: ReadableWritableStorageTraits>(
+    array: &Array<S>,
+    chunk: u64,
+) -> Option<Result<Range<u64>, ZarrDfError>> {
+    let offsets = array.attributes().get(OFFSETS_ATTRIBUTE)?;
+    let offset = |chunk: u64| {
+        offsets
+            .get(chunk as usize)
+            .and_then(serde_json::Value::as_u64)
+            .ok_or_else(|| ZarrDfError::Corrupted(format!("missing offset of chunk {chunk}")))
+    };
+    // The offsets of a delta follow its keys, and identifiers it does not
+    // list have no rows
+    let chunk = match array.attributes().get(KEYS_ATTRIBUTE) {
+        Some(keys) => {
+            let Some(keys) = keys.as_array() else {
+                return Some(Err(ZarrDfError::Corrupted("keys are not a list".into())));
+            };
+            let position = keys.partition_point(|key| key.as_u64() < Some(chunk));
+            if keys.get(position).and_then(serde_json::Value::as_u64) != Some(chunk) {
+                return Some(Ok(0..0));
+            }
+            position as u64
+        }
+        None => chunk,
+    };
+    Some(offset(chunk).and_then(|start| Ok(start..offset(chunk + 1)?)))
+}
+
+fn widen<T: Into<u64>>(ids: Vec<T>) -> Vec<u64> {
+    ids.into_iter().map(Into::into).collect()
 }
 
 pub trait BasicGraphPattern {
-    type Term: ElementOwned;
+    type Term;
 
+    /// Returns the triples matching the pattern in any graph of the dataset,
+    /// without duplicates.
     fn triples_matching(
         &self,
-        subject: Option<i32>,
-        predicate: Option<i32>,
-        object: Option<i32>,
-    ) -> Result<Vec<[Self::Term; 3]>, ZarrDfError>;
+        subject: Option<TermId>,
+        predicate: Option<TermId>,
+        object: Option<TermId>,
+    ) -> Result<Vec<[Self::Term; 3]>, ZarrDfError> {
+        self.triples_matching_iter(subject, predicate, object)
+            .collect()
+    }
+
+    /// Returns the quads matching the pattern, where the graph of the default
+    /// graph is [`DEFAULT_GRAPH`].
+    fn quads_matching(
+        &self,
+        subject: Option<TermId>,
+        predicate: Option<TermId>,
+        object: Option<TermId>,
+        graph: Option<TermId>,
+    ) -> Result<Vec<[Self::Term; 4]>, ZarrDfError> {
+        self.quads_matching_iter(subject, predicate, object, graph)
+            .collect()
+    }
+
+    /// Lazy version of [`triples_matching`](BasicGraphPattern::triples_matching).
+    ///
+    /// Chunks are only fetched once the previous one has been consumed, so
+    /// memory stays bounded by the largest chunk and the iteration can be
+    /// stopped early.
+    fn triples_matching_iter(
+        &self,
+        subject: Option<TermId>,
+        predicate: Option<TermId>,
+        object: Option<TermId>,
+    ) -> impl Iterator<Item = Result<[Self::Term; 3], ZarrDfError>> + '_;
+
+    /// Lazy version of [`quads_matching`](BasicGraphPattern::quads_matching).
+    fn quads_matching_iter(
+        &self,
+        subject: Option<TermId>,
+        predicate: Option<TermId>,
+        object: Option<TermId>,
+        graph: Option<TermId>,
+    ) -> impl Iterator<Item = Result<[Self::Term; 4], ZarrDfError>> + '_;
+
+    /// Returns the number of triples matching the pattern in any graph of
+    /// the dataset, without materializing them.
+    fn count_matching(
+        &self,
+        subject: Option<TermId>,
+        predicate: Option<TermId>,
+        object: Option<TermId>,
+    ) -> Result<u64, ZarrDfError> {
+        self.triples_matching_iter(subject, predicate, object)
+            .try_fold(0, |count, triple| triple.map(|_| count + 1))
+    }
 }
 
 impl<S: ReadableWritableStorageTraits + 'static> BasicGraphPattern for Storage<S> {
-    type Term = u64;
+    type Term = TermId;
 
-    fn triples_matching(
+    fn triples_matching_iter(
         &self,
-        subject: Option<i32>,
-        predicate: Option<i32>,
-        object: Option<i32>,
-    ) -> Result<Vec<[Self::Term; 3]>, ZarrDfError> {
-        let subject = subject.map(|s| s as u64);
-        let predicate = predicate.map(|p| p as u64);
-        let object = object.map(|o| o as u64);
-
-        let components: Vec<_> = match (subject, predicate, object) {
-            (Some(subj), _, _) => self
-                .subject
-                .as_ref()
-                .ok_or(ZarrDfError::InvalidQuery)?
-                .query::<u64>(subj)?,
-            (None, Some(pred), _) => self
-                .predicate
-                .as_ref()
-                .ok_or(ZarrDfError::InvalidQuery)?
-                .query::<u64>(pred)?,
-            (None, None, Some(obj)) => self
-                .object
-                .as_ref()
-                .ok_or(ZarrDfError::InvalidQuery)?
-                .query::<u64>(obj)?,
-            (None, None, None) => {
-                let mut results = Vec::new();
-                let mut i = 0;
-                while let Some(chunk) = self
-                    .subject
-                    .as_ref()
-                    .ok_or(ZarrDfError::InvalidQuery)?
-                    .retrieve_chunk_elements_if_exists(&[i, 0])?
-                {
-                    results.extend(
-                        chunk
-                            .chunks(2)
-                            .into_iter()
-                            .map(|pair: &[u64]| [i, pair[0], pair[1]]),
-                    );
-                    i += 1;
+        subject: Option<TermId>,
+        predicate: Option<TermId>,
+        object: Option<TermId>,
+    ) -> impl Iterator<Item = Result<[Self::Term; 3], ZarrDfError>> + '_ {
+        let mut last = None;
+        self.quads_matching_iter(subject, predicate, object, None)
+            .filter_map(move |quad| {
+                let triple = match quad {
+                    Ok([s, p, o, _]) => [s, p, o],
+                    Err(error) => return Some(Err(error)),
+                };
+                // Rows are sorted with the graph last, so a triple stored in
+                // several graphs yields adjacent quads
+                if last == Some(triple) {
+                    return None;
                 }
-                return Ok(results);
+                last = Some(triple);
+                Some(Ok(triple))
+            })
+    }
+
+    fn quads_matching_iter(
+        &self,
+        subject: Option<TermId>,
+        predicate: Option<TermId>,
+        object: Option<TermId>,
+        graph: Option<TermId>,
+    ) -> impl Iterator<Item = Result<[Self::Term; 4], ZarrDfError>> + '_ {
+        let pattern = [subject, predicate, object, graph].map(|id| id.map(TermId::get));
+
+        let index = self.access_path(subject, predicate, object);
+        let key = pattern[index.positions()[0]];
+
+        QuadsMatching::new(self, index, key, pattern)
+            .map(|quad| quad.map(|quad| quad.map(TermId::new)))
+    }
+
+    /// Answers patterns with at most one bound component from the chunk grid
+    /// of the index keyed by it, as the chunk of a term holds one row per
+    /// quad. Quads only stand for distinct triples when the dataset has a
+    /// single graph, so other datasets and patterns are scanned.
+    fn count_matching(
+        &self,
+        subject: Option<TermId>,
+        predicate: Option<TermId>,
+        object: Option<TermId>,
+    ) -> Result<u64, ZarrDfError> {
+        let pattern = [subject, predicate, object];
+        if self.dictionary.graphs_len() == 1 {
+            match pattern.iter().flatten().count() {
+                0 => {
+                    let index = Index::Predicate;
+                    return (0..self.num_chunks(index)?).try_fold(0, |count, chunk| {
+                        Ok(count + self.quads_in_chunk(index, chunk)?)
+                    });
+                }
+                1 => {
+                    let index = Index::for_pattern(pattern.map(|id| id.is_some()));
+                    let key = pattern[index.positions()[0]].map_or(0, TermId::get);
+                    return self.quads_in_chunk(index, key);
+                }
+                _ => {}
             }
-        };
+        }
+        self.triples_matching_iter(subject, predicate, object)
+            .try_fold(0, |count, triple| triple.map(|_| count + 1))
+    }
+}
+
+/// Number of quads in a chunk, which is its number of rows unless it is the
+/// placeholder of a term that never occurs in the indexed position. Chunks of
+/// a single row are fetched to tell them apart.
+fn quads_in_chunk<S: ReadableWritableStorageTraits + 'static>(
+    array: &Array<S>,
+    chunk: u64,
+) -> Result<u64, ZarrDfError> {
+    match array.chunk_len(chunk)? {
+        1 => Ok((array.rows(chunk)?.len() / COLUMNS) as u64),
+        len => Ok(len),
+    }
+}
+
+/// Pattern matching over RDF terms rather than dictionary identifiers.
+///
+/// Bound terms are looked up in the [`Dictionary`](crate::dictionary::Dictionary)
+/// and the matching identifiers are decoded back into [`oxrdf`] values.
+pub trait TermPattern {
+    fn triples_matching_terms(
+        &self,
+        subject: Option<&Subject>,
+        predicate: Option<&NamedNode>,
+        object: Option<&Term>,
+    ) -> impl Iterator<Item = Result<Triple, ZarrDfError>> + '_;
+
+    fn quads_matching_terms(
+        &self,
+        subject: Option<&Subject>,
+        predicate: Option<&NamedNode>,
+        object: Option<&Term>,
+        graph: Option<&GraphName>,
+    ) -> impl Iterator<Item = Result<Quad, ZarrDfError>> + '_;
+}
 
-        let triples = components
-            .chunks(2)
+impl<S: ReadableWritableStorageTraits + 'static> TermPattern for Storage<S> {
+    fn triples_matching_terms(
+        &self,
+        subject: Option<&Subject>,
+        predicate: Option<&NamedNode>,
+        object: Option<&Term>,
+    ) -> impl Iterator<Item = Result<Triple, ZarrDfError>> + '_ {
+        let ids = self.locate_terms(subject, predicate, object, None);
+        // A bound term missing from the dictionary cannot match anything
+        ids.into_iter()
+            .flat_map(|[s, p, o, _]| self.triples_matching_iter(s, p, o))
+            .map(|triple| self.dictionary.triple(triple?))
+    }
+
+    fn quads_matching_terms(
+        &self,
+        subject: Option<&Subject>,
+        predicate: Option<&NamedNode>,
+        object: Option<&Term>,
+        graph: Option<&GraphName>,
+    ) -> impl Iterator<Item = Result<Quad, ZarrDfError>> + '_ {
+        let ids = self.locate_terms(subject, predicate, object, graph);
+        ids.into_iter()
+            .flat_map(|[s, p, o, g]| self.quads_matching_iter(s, p, o, g))
+            .map(|quad| self.dictionary.quad(quad?))
+    }
+}
+
+/// Reads of an index across its base array and the arrays of every
+/// [append](Storage::append) and [deletion](Storage::delete), applied in
+/// order. Appends only add quads the store does not hold and deletions only
+/// remove quads it holds.
+impl<S: ReadableWritableStorageTraits + 'static> Storage<S> {
+    /// Returns the rows of the chunk of `key` in every array of `index`,
+    /// merged so that they stay sorted when every array is.
+    pub(crate) fn chunk_rows(&self, index: Index, key: u64) -> Result<Vec<u64>, ZarrDfError> {
+        if key >= self.num_chunks(index)? {
+            return Err(ZarrDfError::ChunkNotFound(key));
+        }
+        let mut rows = Vec::new();
+        for (change, array) in self.layers(&index)? {
+            // Terms added by later deltas have no chunk in earlier arrays
+            if key >= array.num_chunks() {
+                continue;
+            }
+            let chunk = array.rows(key)?;
+            rows = match change {
+                _ if chunk.is_empty() => rows,
+                Change::Added if rows.is_empty() => chunk,
+                Change::Added => merge_rows(&rows, &chunk),
+                Change::Removed => remove_rows(&rows, &chunk),
+            };
+        }
+        Ok(rows)
+    }
+
+    /// Number of chunks of `index`, which is one per identifier of its
+    /// position, including the ones added by deltas.
+    pub(crate) fn num_chunks(&self, index: Index) -> Result<u64, ZarrDfError> {
+        let layers = self.layers(&index)?;
+        Ok(layers
+            .iter()
+            .map(|(_, array)| array.num_chunks())
+            .max()
+            .unwrap_or(0))
+    }
+
+    /// Number of rows added to the chunk of `key` by every array of `index`,
+    /// without fetching them, see [`Query::chunk_len`]. Every array may add
+    /// the placeholder row of a term that does not occur in it, and removed
+    /// quads are still counted.
+    pub(crate) fn chunk_len(&self, index: Index, key: u64) -> Result<u64, ZarrDfError> {
+        if key >= self.num_chunks(index)? {
+            return Err(ZarrDfError::ChunkNotFound(key));
+        }
+        self.layers(&index)?
             .into_iter()
-            .filter_map(|pair: &[u64]| {
-                let triple = match (subject, predicate, object) {
-                    (Some(subject), _, _) => [subject, pair[0], pair[1]],
-                    (None, Some(predicate), _) => [pair[0], predicate, pair[1]],
-                    (None, None, Some(object)) => [pair[0], pair[1], object],
-                    _ => return None, // this should never happen due to prior checks
-                };
-                if predicate.map_or(true, |pred| triple[1] == pred)
-                    && object.map_or(true, |obj| triple[2] == obj)
-                {
-                    Some(triple)
-                } else {
-                    None
+            .filter(|(change, array)| *change == Change::Added && key < array.num_chunks())
+            .try_fold(0, |len, (_, array)| Ok(len + array.chunk_len(key)?))
+    }
+
+    /// Number of quads in the chunk of `key` in every array of `index`, see
+    /// [`quads_in_chunk`].
+    pub(crate) fn quads_in_chunk(&self, index: Index, key: u64) -> Result<u64, ZarrDfError> {
+        if key >= self.num_chunks(index)? {
+            return Err(ZarrDfError::ChunkNotFound(key));
+        }
+        let (mut added, mut removed) = (0, 0);
+        for (change, array) in self.layers(&index)? {
+            if key < array.num_chunks() {
+                match change {
+                    Change::Added => added += quads_in_chunk(array, key)?,
+                    Change::Removed => removed += quads_in_chunk(array, key)?,
                 }
-            })
-            .collect();
+            }
+        }
+        Ok(added.saturating_sub(removed))
+    }
+
+    /// Number of quads of `index`: the quads added by every array minus the
+    /// ones removed, read from the metadata, see [`Query::num_quads`].
+    pub(crate) fn num_quads(&self, index: Index) -> Result<u64, ZarrDfError> {
+        let (mut added, mut removed) = (0, 0);
+        for (change, array) in self.layers(&index)? {
+            match change {
+                Change::Added => added += array.num_quads(),
+                Change::Removed => removed += array.num_quads(),
+            }
+        }
+        Ok(added.saturating_sub(removed))
+    }
+
+    /// Whether the rows of every chunk of every array of `index` are sorted.
+    pub(crate) fn sorted(&self, index: Index) -> bool {
+        self.layers(&index)
+            .is_ok_and(|layers| layers.iter().all(|(_, array)| array.sorted()))
+    }
+}
+
+/// Merges two sorted chunks holding distinct rows.
+fn merge_rows(rows: &[u64], added: &[u64]) -> Vec<u64> {
+    let (rows, _) = rows.as_chunks::<COLUMNS>();
+    let (added, _) = added.as_chunks::<COLUMNS>();
+    rows.iter().merge(added).flatten().copied().collect()
+}
+
+/// Returns the rows of a sorted chunk that are not in `removed`, which is
+/// sorted too.
+fn remove_rows(rows: &[u64], removed: &[u64]) -> Vec<u64> {
+    let (rows, _) = rows.as_chunks::<COLUMNS>();
+    let (removed, _) = removed.as_chunks::<COLUMNS>();
+    rows.iter()
+        .filter(|row| removed.binary_search(row).is_err())
+        .flatten()
+        .copied()
+        .collect()
+}
 
-        Ok(triples)
+impl<S: ReadableWritableStorageTraits> Storage<S> {
+    /// Returns the identifiers of the bound terms, or `None` if any of them is
+    /// not in the dictionary.
+    pub(crate) fn locate_terms(
+        &self,
+        subject: Option<&Subject>,
+        predicate: Option<&NamedNode>,
+        object: Option<&Term>,
+        graph: Option<&GraphName>,
+    ) -> Option<[Option<TermId>; 4]> {
+        let dictionary = &self.dictionary;
+        let subject = match subject {
+            Some(subject) => Some(dictionary.get_subject_idx(&serialize_term(subject.clone()))?),
+            None => None,
+        };
+        let predicate = match predicate {
+            Some(predicate) => {
+                Some(dictionary.get_predicate_idx(&serialize_term(predicate.clone()))?)
+            }
+            None => None,
+        };
+        let object = match object {
+            Some(object) => Some(dictionary.get_object_idx(&serialize_term(object.clone()))?),
+            None => None,
+        };
+        let graph = match graph {
+            Some(graph) => Some(dictionary.get_graph_idx(&serialize_graph_name(graph.clone()))?),
+            None => None,
+        };
+        Some([subject, predicate, object, graph])
+    }
+}
+
+/// Iterator over the quads of an index matching a pattern, which fetches the
+/// chunks of the index one at a time.
+struct QuadsMatching<'a, S: ReadableWritableStorageTraits> {
+    storage: &'a Storage<S>,
+    index: Index,
+    sorted: bool,
+    chunks: Range<u64>,
+    pattern: [Option<u64>; 4],
+    key: u64,
+    chunk: Vec<u64>,
+    position: usize,
+    end: usize,
+    error: Option<ZarrDfError>,
+}
+
+impl<'a, S: ReadableWritableStorageTraits + 'static> QuadsMatching<'a, S> {
+    fn new(
+        storage: &'a Storage<S>,
+        index: Index,
+        key: Option<u64>,
+        pattern: [Option<u64>; 4],
+    ) -> Self {
+        let (chunks, error) = match (storage.num_chunks(index), key) {
+            (Ok(_), Some(key)) => (key..key + 1, None),
+            // Without a bound key every chunk of the index is scanned
+            (Ok(num_chunks), None) => (0..num_chunks, None),
+            (Err(error), _) => (0..0, Some(error)),
+        };
+        Self {
+            storage,
+            index,
+            sorted: storage.sorted(index),
+            chunks,
+            pattern,
+            key: 0,
+            chunk: Vec::new(),
+            position: 0,
+            end: 0,
+            error,
+        }
+    }
+
+    fn matches(&self, quad: &[u64; 4]) -> bool {
+        self.pattern
+            .iter()
+            .zip(quad)
+            .all(|(bound, component)| bound.is_none_or(|bound| bound == *component))
+    }
+}
+
+impl<S: ReadableWritableStorageTraits + 'static> Iterator for QuadsMatching<'_, S> {
+    type Item = Result<[u64; 4], ZarrDfError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(error) = self.error.take() {
+            return Some(Err(error));
+        }
+        loop {
+            while self.position < self.end {
+                let row = &self.chunk[self.position..self.position + COLUMNS];
+                self.position += COLUMNS;
+                let quad = self.index.quad_from_row(self.key, row);
+                if self.matches(&quad) {
+                    return Some(Ok(quad));
+                }
+            }
+
+            let key = self.chunks.next()?;
+            match self.storage.chunk_rows(self.index, key) {
+                Ok(chunk) => {
+                    // Sorted chunks only need the rows matching the columns
+                    // bound after the key
+                    let rows = if self.sorted {
+                        matching_rows(&chunk, self.index, &self.pattern)
+                    } else {
+                        0..chunk.len() / COLUMNS
+                    };
+                    self.key = key;
+                    self.chunk = chunk;
+                    self.position = rows.start * COLUMNS;
+                    self.end = rows.end * COLUMNS;
+                }
+                Err(error) => {
+                    self.chunks = 0..0;
+                    return Some(Err(error));
+                }
+            }
+        }
     }
 }
@@ -4,6 +4,8 @@ use oxrdfio::RdfFormat as OxRdfFormat;
 
 use crate::error::ZarrDfError;
 
+/// The RDF syntaxes that can be read into and exported from a ZarrDF store.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum RdfFormat {
     NTriples,
     Turtle,
@@ -14,13 +16,35 @@ pub enum RdfFormat {
 }
 
 impl RdfFormat {
+    const ALL: [RdfFormat; 6] = [
+        RdfFormat::NTriples,
+        RdfFormat::Turtle,
+        RdfFormat::RdfXml,
+        RdfFormat::TriG,
+        RdfFormat::N3,
+        RdfFormat::NQuads,
+    ];
+
     pub fn from_path(path: &str) -> Result<Self, ZarrDfError> {
-        let extension = match path.split('.').last() {
+        let extension = match path.split('.').next_back() {
             Some(extension) => extension,
             None => return Err(ZarrDfError::UnknownFormat(path.to_string())),
         };
         RdfFormat::from_str(extension)
     }
+
+    /// Returns the format of an IANA media type, ignoring its parameters.
+    pub fn from_media_type(media_type: &str) -> Option<Self> {
+        let format = OxRdfFormat::from_media_type(media_type)?;
+        RdfFormat::ALL
+            .into_iter()
+            .find(|candidate| OxRdfFormat::from(*candidate) == format)
+    }
+
+    /// IANA media type of the format.
+    pub fn media_type(self) -> &'static str {
+        OxRdfFormat::from(self).media_type()
+    }
 }
 
 impl FromStr for RdfFormat {
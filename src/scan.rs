@@ -0,0 +1,145 @@
+use std::collections::BTreeMap;
+use std::ops::ControlFlow;
+use std::sync::mpsc;
+use std::sync::Condvar;
+use std::sync::Mutex;
+
+use zarrs::storage::ReadableWritableStorageTraits;
+
+use crate::config::Config;
+use crate::dictionary::TermId;
+use crate::error::ZarrDfError;
+use crate::index::Index;
+use crate::index::COLUMNS;
+use crate::storage::Storage;
+
+/// Number of chunks a scan reads ahead of the ones being visited, which
+/// bounds its memory.
+pub const PREFETCH: u64 = 64;
+
+/// Order in which a [`scan`](Storage::scan) visits the quads.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScanOrder {
+    /// Same order as [`quads_matching_iter`](crate::query::BasicGraphPattern::quads_matching_iter)
+    /// with nothing bound, which is sorted by subject, predicate, object and
+    /// graph.
+    #[default]
+    Ordered,
+    /// Every chunk is visited as soon as it has been read, so a slow chunk
+    /// never holds back the others. Quads of a chunk stay sorted.
+    Unordered,
+}
+
+/// Chunks handed out to the threads of a scan.
+struct Claims {
+    /// Next chunk to read.
+    next: u64,
+    /// Number of chunks visited, which chunks being read may not get more
+    /// than [`PREFETCH`] ahead of.
+    visited: u64,
+    stopped: bool,
+}
+
+impl<S: ReadableWritableStorageTraits + 'static> Storage<S> {
+    /// Visits every quad of the dataset, reading the chunks of the subject
+    /// index concurrently with one thread per CPU, up to [`PREFETCH`] chunks
+    /// ahead of the visited ones.
+    ///
+    /// The scan stops as soon as `visit` breaks, returning its value.
+    pub fn scan<B>(
+        &self,
+        order: ScanOrder,
+        visit: impl FnMut([TermId; 4]) -> ControlFlow<B>,
+    ) -> Result<Option<B>, ZarrDfError> {
+        self.scan_with_config(order, &Config::default(), visit)
+    }
+
+    /// Explicit configuration version of [`scan`](Storage::scan), reading
+    /// the chunks on a pool of [`threads`](crate::config::ConfigBuilder::threads)
+    /// threads, which the storage builds on the first scan and keeps.
+    pub fn scan_with_config<B>(
+        &self,
+        order: ScanOrder,
+        config: &Config,
+        mut visit: impl FnMut([TermId; 4]) -> ControlFlow<B>,
+    ) -> Result<Option<B>, ZarrDfError> {
+        let index = Index::Subject;
+        let chunks = self.num_chunks(index)?;
+        let pool = self.thread_pool(config.threads)?;
+        let claims = Mutex::new(Claims {
+            next: 0,
+            visited: 0,
+            stopped: false,
+        });
+        let condvar = Condvar::new();
+
+        // Readers run on the pool while the calling thread visits the chunks
+        pool.in_place_scope(|scope| {
+            // Chunks are never read more than PREFETCH ahead, so sending
+            // never blocks
+            let (sender, receiver) = mpsc::sync_channel(PREFETCH as usize);
+            for _ in 0..pool.current_num_threads() {
+                let sender = sender.clone();
+                let (claims, condvar) = (&claims, &condvar);
+                scope.spawn(move |_| {
+                    while let Some(key) = claim(claims, condvar, chunks) {
+                        if sender.send((key, self.chunk_rows(index, key))).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            drop(sender);
+
+            let mut pending = BTreeMap::new();
+            let mut visited = 0;
+            let result = (|| {
+                for (key, rows) in &receiver {
+                    pending.insert(key, rows?);
+                    loop {
+                        let next = match order {
+                            ScanOrder::Ordered => pending.remove_entry(&visited),
+                            ScanOrder::Unordered => pending.pop_first(),
+                        };
+                        let Some((key, rows)) = next else {
+                            break;
+                        };
+                        for row in rows.chunks_exact(COLUMNS) {
+                            let quad = index.quad_from_row(key, row).map(TermId::new);
+                            if let ControlFlow::Break(value) = visit(quad) {
+                                return Ok(Some(value));
+                            }
+                        }
+                        visited += 1;
+                        claims.lock().expect("no thread panics holding it").visited = visited;
+                        condvar.notify_all();
+                    }
+                }
+                Ok(None)
+            })();
+
+            // Threads still waiting for a chunk to claim are released
+            claims.lock().expect("no thread panics holding it").stopped = true;
+            condvar.notify_all();
+            drop(receiver);
+            result
+        })
+    }
+}
+
+/// Claims the next chunk to read, waiting until it is close enough to the
+/// visited ones, or returns `None` once every chunk is claimed or the scan
+/// stopped.
+fn claim(claims: &Mutex<Claims>, condvar: &Condvar, chunks: u64) -> Option<u64> {
+    let mut claims = claims.lock().expect("no thread panics holding it");
+    loop {
+        if claims.stopped || claims.next >= chunks {
+            return None;
+        }
+        if claims.next < claims.visited + PREFETCH {
+            claims.next += 1;
+            return Some(claims.next - 1);
+        }
+        claims = condvar.wait(claims).expect("no thread panics holding it");
+    }
+}
@@ -0,0 +1,354 @@
+use std::io;
+use std::net::SocketAddr;
+use std::net::TcpListener;
+use std::net::ToSocketAddrs;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::PoisonError;
+
+use oxrdf::NamedNode;
+use oxrdf::Subject;
+use tiny_http::Header;
+use tiny_http::Method;
+use tiny_http::Request;
+use tiny_http::Response;
+use zarrs::storage::ReadableWritableStorageTraits;
+
+use crate::error::ZarrDfError;
+use crate::fragments::parse_representation;
+use crate::fragments::PAGE_SIZE;
+use crate::rdf_format::RdfFormat;
+use crate::sparql::QueryResults;
+use crate::sparql::ResultsFormat;
+use crate::storage::Storage;
+
+/// Path of the SPARQL endpoint.
+pub const SPARQL_PATH: &str = "/sparql";
+
+/// Path of the Triple Pattern Fragments interface.
+pub const FRAGMENTS_PATH: &str = "/fragments";
+
+/// Formats offered for `CONSTRUCT` and `DESCRIBE` results, the first being
+/// the default.
+const GRAPH_FORMATS: [RdfFormat; 3] = [RdfFormat::Turtle, RdfFormat::NTriples, RdfFormat::RdfXml];
+
+/// Formats offered for fragments, the first being the default.
+const FRAGMENT_FORMATS: [RdfFormat; 4] = [
+    RdfFormat::Turtle,
+    RdfFormat::TriG,
+    RdfFormat::NTriples,
+    RdfFormat::NQuads,
+];
+
+/// Formats offered for `SELECT` and `ASK` results, the first being the
+/// default.
+const RESULTS_FORMATS: [ResultsFormat; 4] = [
+    ResultsFormat::Json,
+    ResultsFormat::Xml,
+    ResultsFormat::Csv,
+    ResultsFormat::Tsv,
+];
+
+/// HTTP server answering SPARQL 1.1 Protocol requests at [`SPARQL_PATH`]
+/// and Triple Pattern Fragments requests at [`FRAGMENTS_PATH`] over a store.
+///
+/// Requests are handled one at a time, in the calling thread of
+/// [`serve`](Server::serve), and each one sees the latest state of the
+/// store, which is [refreshed](Storage::refresh) before answering it.
+pub struct Server<S: ReadableWritableStorageTraits> {
+    http: tiny_http::Server,
+    storage: Mutex<Storage<S>>,
+}
+
+impl<S: ReadableWritableStorageTraits + 'static> Server<S> {
+    /// Listens on `address`, where port 0 picks any free port.
+    pub fn bind(address: impl ToSocketAddrs, storage: Storage<S>) -> Result<Self, ZarrDfError> {
+        let listener = TcpListener::bind(address)?;
+        let http = tiny_http::Server::from_listener(listener, None).map_err(io::Error::other)?;
+        Ok(Server {
+            http,
+            storage: Mutex::new(storage),
+        })
+    }
+
+    fn storage(&self) -> MutexGuard<'_, Storage<S>> {
+        // A request that panicked cannot have left the storage half changed,
+        // as only refreshing changes it
+        self.storage.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Address the server listens on.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.http.server_addr().to_ip()
+    }
+
+    /// Answers requests until the listener fails.
+    pub fn serve(&self) -> Result<(), ZarrDfError> {
+        loop {
+            let request = self.http.recv()?;
+            // A client going away must not bring the server down
+            let _ = self.handle(request);
+        }
+    }
+
+    fn handle(&self, mut request: Request) -> io::Result<()> {
+        // Other processes may have compacted or changed the store
+        if let Err(error) = self.storage().refresh() {
+            return request.respond(text(500, &error.to_string()));
+        }
+        let (path, parameters) = match request.url().split_once('?') {
+            Some((path, parameters)) => (path.to_string(), parameters.to_string()),
+            None => (request.url().to_string(), String::new()),
+        };
+        if path == FRAGMENTS_PATH && *request.method() == Method::Get {
+            let response = self.fragment(&request, &parameters);
+            return request.respond(response);
+        }
+        if path != SPARQL_PATH {
+            return request.respond(text(404, "Not found"));
+        }
+
+        let query = match request.method() {
+            Method::Get => query_parameter(&parameters),
+            Method::Post => {
+                let mut body = String::new();
+                if request.as_reader().read_to_string(&mut body).is_err() {
+                    return request.respond(text(400, "The body is not valid UTF-8"));
+                }
+                match header(&request, "Content-Type").as_deref().map(media_type) {
+                    Some("application/x-www-form-urlencoded") => query_parameter(&body),
+                    Some("application/sparql-query") => Ok(body),
+                    _ => return request.respond(text(415, "Unsupported content type")),
+                }
+            }
+            _ => {
+                let response = text(405, "Method not allowed").with_header(
+                    Header::from_bytes("Allow", "GET, POST").expect("header is valid"),
+                );
+                return request.respond(response);
+            }
+        };
+        let response = match query {
+            Ok(query) => self.answer(&query, header(&request, "Accept").as_deref()),
+            Err(message) => text(400, message),
+        };
+        request.respond(response)
+    }
+
+    /// Answers a request for a page of the triples matching a pattern.
+    fn fragment(&self, request: &Request, parameters: &str) -> Response<io::Cursor<Vec<u8>>> {
+        let Some(parameters) = parse_form(parameters) else {
+            return text(400, "Invalid URL encoding");
+        };
+        let parameter = |name: &str| {
+            parameters
+                .iter()
+                .find(|(key, _)| key == name)
+                .map_or("", |(_, value)| value.as_str())
+        };
+        let page = match parameter("page") {
+            "" => 1,
+            page => match page.parse() {
+                Ok(page) if page > 0 => page,
+                _ => return text(400, "Invalid page"),
+            },
+        };
+        let pattern = (|| -> Result<_, ZarrDfError> {
+            let subject = parse_representation(parameter("subject"))?;
+            let predicate = parse_representation(parameter("predicate"))?;
+            let object = parse_representation(parameter("object"))?;
+            Ok((
+                subject.map(Subject::try_from).transpose()?,
+                predicate.map(NamedNode::try_from).transpose()?,
+                object,
+            ))
+        })();
+        let (subject, predicate, object) = match pattern {
+            Ok(pattern) => pattern,
+            Err(error) => return text(400, &error.to_string()),
+        };
+
+        let offered = FRAGMENT_FORMATS.map(RdfFormat::media_type);
+        let accept = header(request, "Accept");
+        let Some(format) = negotiate(accept.as_deref(), &offered, |range| {
+            RdfFormat::from_media_type(range).map(RdfFormat::media_type)
+        }) else {
+            return text(406, "No acceptable format for a fragment");
+        };
+        let format = FRAGMENT_FORMATS[format];
+
+        // Hypermedia controls are absolute, so they follow the host the
+        // client used to reach the server
+        let host = header(request, "Host")
+            .or_else(|| self.local_addr().map(|address| address.to_string()))
+            .unwrap_or_default();
+        let endpoint = format!("http://{host}{FRAGMENTS_PATH}");
+        let body = self
+            .storage()
+            .fragment(
+                subject.as_ref(),
+                predicate.as_ref(),
+                object.as_ref(),
+                page,
+                PAGE_SIZE,
+            )
+            .and_then(|fragment| fragment.write(Vec::new(), format, &endpoint));
+        match body {
+            Ok(body) => Response::from_data(body).with_header(
+                Header::from_bytes("Content-Type", format.media_type()).expect("header is valid"),
+            ),
+            Err(error) => text(500, &error.to_string()),
+        }
+    }
+
+    /// Evaluates the query and serializes its results in the format that
+    /// best matches the `Accept` header.
+    fn answer(&self, query: &str, accept: Option<&str>) -> Response<io::Cursor<Vec<u8>>> {
+        let results = match self.storage().sparql(query) {
+            Ok(results) => results,
+            Err(
+                error @ (ZarrDfError::SparqlSyntax(_)
+                | ZarrDfError::UnsupportedQuery(_)
+                | ZarrDfError::InvalidQuery),
+            ) => return text(400, &error.to_string()),
+            Err(error) => return text(500, &error.to_string()),
+        };
+
+        let (media_type, body) = match &results {
+            QueryResults::Graph(_) => {
+                let offered = GRAPH_FORMATS.map(RdfFormat::media_type);
+                let Some(format) = negotiate(accept, &offered, |range| {
+                    RdfFormat::from_media_type(range).map(RdfFormat::media_type)
+                }) else {
+                    return text(406, "No acceptable format for a graph");
+                };
+                let format = GRAPH_FORMATS[format];
+                (format.media_type(), results.write_graph(Vec::new(), format))
+            }
+            results => {
+                let formats: Vec<_> = RESULTS_FORMATS
+                    .into_iter()
+                    .filter(|format| {
+                        format.supports_boolean() || !matches!(results, QueryResults::Boolean(_))
+                    })
+                    .collect();
+                let offered: Vec<_> = formats.iter().map(|format| format.media_type()).collect();
+                let Some(format) = negotiate(accept, &offered, |range| {
+                    ResultsFormat::from_media_type(range).map(ResultsFormat::media_type)
+                }) else {
+                    return text(406, "No acceptable format for query results");
+                };
+                let format = formats[format];
+                (format.media_type(), results.write(Vec::new(), format))
+            }
+        };
+        match body {
+            Ok(body) => Response::from_data(body).with_header(
+                Header::from_bytes("Content-Type", media_type).expect("header is valid"),
+            ),
+            Err(error) => text(500, &error.to_string()),
+        }
+    }
+}
+
+fn text(status: u16, message: &str) -> Response<io::Cursor<Vec<u8>>> {
+    Response::from_string(message)
+        .with_status_code(status)
+        .with_header(Header::from_bytes("Content-Type", "text/plain").expect("header is valid"))
+}
+
+fn header(request: &Request, name: &'static str) -> Option<String> {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv(name))
+        .map(|header| header.value.to_string())
+}
+
+/// Strips the parameters of a media type.
+fn media_type(value: &str) -> &str {
+    value.split(';').next().unwrap_or_default().trim()
+}
+
+/// Returns the position in `offered` of the media type preferred by an
+/// `Accept` header, or the first one if there is no header. Media types are
+/// compared once mapped by `canonical`, so that aliases are accepted.
+fn negotiate(
+    accept: Option<&str>,
+    offered: &[&'static str],
+    canonical: impl Fn(&str) -> Option<&'static str>,
+) -> Option<usize> {
+    let Some(accept) = accept else {
+        return Some(0);
+    };
+    let mut best: Option<(f32, usize)> = None;
+    for range in accept.split(',') {
+        let mut parts = range.split(';');
+        let range = parts.next().unwrap_or_default().trim().to_ascii_lowercase();
+        let quality = parts
+            .filter_map(|parameter| parameter.trim().strip_prefix("q="))
+            .find_map(|quality| quality.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        if quality <= 0.0 {
+            continue;
+        }
+        let matches = |media_type: &&str| match range.split_once('/') {
+            Some(("*", "*")) => true,
+            Some((kind, "*")) => media_type.split('/').next() == Some(kind),
+            _ => canonical(&range) == Some(*media_type),
+        };
+        if let Some(position) = offered.iter().position(matches) {
+            if best.is_none_or(|(best, _)| quality > best) {
+                best = Some((quality, position));
+            }
+        }
+    }
+    best.map(|(_, position)| position)
+}
+
+/// Returns the single `query` parameter of a URL-encoded form.
+fn query_parameter(form: &str) -> Result<String, &'static str> {
+    let mut query = None;
+    for (name, value) in parse_form(form).ok_or("Invalid URL encoding")? {
+        match name.as_str() {
+            "query" if query.is_some() => return Err("More than one query"),
+            "query" => query = Some(value),
+            "default-graph-uri" | "named-graph-uri" => {
+                return Err("Setting the dataset of a query is not supported")
+            }
+            _ => {}
+        }
+    }
+    query.ok_or("Missing query parameter")
+}
+
+/// Decodes the name and value of every parameter of a URL-encoded form, or
+/// returns `None` if any of them is malformed.
+fn parse_form(form: &str) -> Option<Vec<(String, String)>> {
+    form.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            Some((decode(name)?, decode(value)?))
+        })
+        .collect()
+}
+
+/// Decodes a component of a URL-encoded form, or returns `None` if it is
+/// malformed.
+fn decode(component: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(component.len());
+    let mut input = component.bytes();
+    while let Some(byte) = input.next() {
+        match byte {
+            b'+' => bytes.push(b' '),
+            b'%' => {
+                let high = char::from(input.next()?).to_digit(16)?;
+                let low = char::from(input.next()?).to_digit(16)?;
+                bytes.push((high * 16 + low) as u8);
+            }
+            byte => bytes.push(byte),
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
@@ -0,0 +1,267 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::io::BufReader;
+use std::io::BufWriter;
+use std::io::ErrorKind;
+use std::io::Read;
+use std::io::Write;
+use std::path::PathBuf;
+
+use tempfile::NamedTempFile;
+
+use crate::config::Config;
+use crate::error::ZarrDfError;
+
+/// A value that can be spilled to disk by the [`ExternalSorter`].
+pub(crate) trait Record: Ord + Clone {
+    /// Approximate number of bytes the value occupies in memory.
+    fn size(&self) -> usize;
+
+    fn write_to<W: Write>(&self, writer: &mut W) -> std::io::Result<()>;
+
+    /// Reads the next value, returning `None` once the reader is exhausted.
+    fn read_from<R: Read>(reader: &mut R) -> std::io::Result<Option<Self>>;
+}
+
+impl Record for String {
+    fn size(&self) -> usize {
+        std::mem::size_of::<String>() + self.len()
+    }
+
+    fn write_to<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_all(&(self.len() as u64).to_le_bytes())?;
+        writer.write_all(self.as_bytes())
+    }
+
+    fn read_from<R: Read>(reader: &mut R) -> std::io::Result<Option<Self>> {
+        let mut len = [0; 8];
+        if !read_header(reader, &mut len)? {
+            return Ok(None);
+        }
+        let mut bytes = vec![0; u64::from_le_bytes(len) as usize];
+        reader.read_exact(&mut bytes)?;
+        String::from_utf8(bytes)
+            .map(Some)
+            .map_err(|error| std::io::Error::new(ErrorKind::InvalidData, error))
+    }
+}
+
+impl<const N: usize> Record for [u64; N] {
+    fn size(&self) -> usize {
+        std::mem::size_of::<Self>()
+    }
+
+    fn write_to<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        self.iter()
+            .try_for_each(|value| writer.write_all(&value.to_le_bytes()))
+    }
+
+    fn read_from<R: Read>(reader: &mut R) -> std::io::Result<Option<Self>> {
+        let mut record = [0; N];
+        let mut bytes = [0; 8];
+        for (i, value) in record.iter_mut().enumerate() {
+            if i == 0 {
+                if !read_header(reader, &mut bytes)? {
+                    return Ok(None);
+                }
+            } else {
+                reader.read_exact(&mut bytes)?;
+            }
+            *value = u64::from_le_bytes(bytes);
+        }
+        Ok(Some(record))
+    }
+}
+
+/// Fills `buf`, returning `false` if the reader was already at its end.
+fn read_header<R: Read>(reader: &mut R, buf: &mut [u8]) -> std::io::Result<bool> {
+    match reader.read_exact(buf) {
+        Ok(()) => Ok(true),
+        Err(error) if error.kind() == ErrorKind::UnexpectedEof => Ok(false),
+        Err(error) => Err(error),
+    }
+}
+
+/// Sorts and deduplicates an arbitrary number of records within a bounded
+/// amount of memory.
+///
+/// Records are buffered until the memory budget is reached, at which point
+/// the buffer is sorted and written to a temporary file as a run. Runs are
+/// merged back lazily when iterating over the [`SortedRuns`].
+///
+/// No more than the merge fan-in of the [`Config`] are merged at once: as in
+/// a counter in that base, every time that many runs of the same level are
+/// spilled they are merged into one of the next level, and the smallest runs
+/// left are merged until the final merge is within the fan-in.
+pub(crate) struct ExternalSorter<T: Record> {
+    buffer: Vec<T>,
+    buffer_size: usize,
+    memory_budget: usize,
+    fan_in: usize,
+    temp_dir: PathBuf,
+    /// Runs with their levels, which never increase along the vector.
+    runs: Vec<(usize, NamedTempFile)>,
+}
+
+impl<T: Record> ExternalSorter<T> {
+    /// Builds a sorter keeping up to `memory_budget` bytes in memory, which
+    /// spills and merges runs as set by `config`.
+    pub fn new(memory_budget: usize, config: &Config) -> Self {
+        Self {
+            buffer: Vec::new(),
+            buffer_size: 0,
+            memory_budget,
+            fan_in: config.merge_fan_in,
+            temp_dir: config.temp_dir.clone(),
+            runs: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, record: T) -> Result<(), ZarrDfError> {
+        self.buffer_size += record.size();
+        self.buffer.push(record);
+        if self.buffer_size >= self.memory_budget {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<SortedRuns<T>, ZarrDfError> {
+        if self.runs.is_empty() {
+            self.buffer.sort_unstable();
+            self.buffer.dedup();
+            return Ok(SortedRuns::Memory(self.buffer));
+        }
+        if !self.buffer.is_empty() {
+            self.spill()?;
+        }
+        while self.runs.len() > self.fan_in {
+            self.merge_last(self.fan_in)?;
+        }
+        Ok(SortedRuns::Disk(
+            self.runs.into_iter().map(|(_, run)| run).collect(),
+        ))
+    }
+
+    fn spill(&mut self) -> Result<(), ZarrDfError> {
+        self.buffer.sort_unstable();
+        self.buffer.dedup();
+
+        let records = std::mem::take(&mut self.buffer);
+        let run = self.write_run(records.into_iter().map(Ok))?;
+        self.runs.push((0, run));
+        self.buffer_size = 0;
+
+        // Carries full levels over to the next one
+        while self.runs.len() >= self.fan_in {
+            let level = self.runs[self.runs.len() - 1].0;
+            if self.runs[self.runs.len() - self.fan_in].0 != level {
+                break;
+            }
+            self.merge_last(self.fan_in)?;
+        }
+        Ok(())
+    }
+
+    /// Merges the last `count` runs into one of the level after theirs.
+    fn merge_last(&mut self, count: usize) -> Result<(), ZarrDfError> {
+        let runs = self.runs.split_off(self.runs.len() - count);
+        let level = runs.iter().map(|(level, _)| level + 1).max().unwrap_or(0);
+        let readers = runs
+            .iter()
+            .map(|(_, run)| run.reopen().map(BufReader::new))
+            .collect::<Result<Vec<_>, _>>()?;
+        let run = self.write_run(Merge::new(readers)?)?;
+        self.runs.push((level, run));
+        Ok(())
+    }
+
+    /// Writes sorted records to a new temporary file.
+    fn write_run(
+        &self,
+        records: impl Iterator<Item = Result<T, ZarrDfError>>,
+    ) -> Result<NamedTempFile, ZarrDfError> {
+        let mut run = NamedTempFile::new_in(&self.temp_dir)?;
+        let mut writer = BufWriter::new(run.as_file_mut());
+        for record in records {
+            record?.write_to(&mut writer)?;
+        }
+        writer.flush()?;
+        drop(writer);
+        Ok(run)
+    }
+}
+
+/// The output of an [`ExternalSorter`], which can be iterated several times.
+pub(crate) enum SortedRuns<T: Record> {
+    Memory(Vec<T>),
+    Disk(Vec<NamedTempFile>),
+}
+
+impl<T: Record + 'static> SortedRuns<T> {
+    /// Iterates over the sorted, deduplicated records.
+    pub fn iter(
+        &self,
+    ) -> Result<Box<dyn Iterator<Item = Result<T, ZarrDfError>> + '_>, ZarrDfError> {
+        match self {
+            SortedRuns::Memory(records) => Ok(Box::new(records.iter().cloned().map(Ok))),
+            SortedRuns::Disk(runs) => {
+                let readers = runs
+                    .iter()
+                    .map(|run| run.reopen().map(BufReader::new))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Box::new(Merge::new(readers)?))
+            }
+        }
+    }
+}
+
+/// A k-way merge over sorted runs that drops duplicated records.
+struct Merge<T: Record, R: Read> {
+    readers: Vec<R>,
+    heap: BinaryHeap<Reverse<(T, usize)>>,
+    last: Option<T>,
+    failed: bool,
+}
+
+impl<T: Record, R: Read> Merge<T, R> {
+    fn new(mut readers: Vec<R>) -> Result<Self, ZarrDfError> {
+        let mut heap = BinaryHeap::with_capacity(readers.len());
+        for (i, reader) in readers.iter_mut().enumerate() {
+            if let Some(record) = T::read_from(reader)? {
+                heap.push(Reverse((record, i)));
+            }
+        }
+        Ok(Self {
+            readers,
+            heap,
+            last: None,
+            failed: false,
+        })
+    }
+}
+
+impl<T: Record, R: Read> Iterator for Merge<T, R> {
+    type Item = Result<T, ZarrDfError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        while let Some(Reverse((record, i))) = self.heap.pop() {
+            match T::read_from(&mut self.readers[i]) {
+                Ok(Some(next)) => self.heap.push(Reverse((next, i))),
+                Ok(None) => (),
+                Err(error) => {
+                    self.failed = true;
+                    return Some(Err(error.into()));
+                }
+            }
+            if self.last.as_ref() != Some(&record) {
+                self.last = Some(record.clone());
+                return Some(Ok(record));
+            }
+        }
+        None
+    }
+}
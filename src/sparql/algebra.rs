@@ -0,0 +1,277 @@
+use oxrdf::NamedNode;
+use oxrdf::Quad;
+use oxrdf::Term;
+use oxrdf::Variable;
+
+use crate::bgp::PatternTerm;
+use crate::bgp::QuadPattern;
+use crate::bgp::TriplePattern;
+
+/// Algebra of a graph pattern, following section 18 of the SPARQL 1.1
+/// specification.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Pattern {
+    /// Basic graph pattern, matched with [`GraphPattern`](crate::bgp::GraphPattern).
+    ///
+    /// The graph of `GRAPH` patterns is set on the triples they hold, which
+    /// are otherwise matched in the default graph.
+    Bgp(Vec<QuadPattern>),
+    Join(Box<Pattern>, Box<Pattern>),
+    /// `OPTIONAL`, with the filters of the optional group.
+    LeftJoin(Box<Pattern>, Box<Pattern>, Option<Expression>),
+    Union(Box<Pattern>, Box<Pattern>),
+    Minus(Box<Pattern>, Box<Pattern>),
+    Filter(Expression, Box<Pattern>),
+    /// `BIND`, or an expression of a `SELECT` clause.
+    Extend(Box<Pattern>, Variable, Expression),
+    /// `VALUES`, where `None` stands for `UNDEF`.
+    Values(Vec<Variable>, Vec<Vec<Option<Term>>>),
+    /// Groups the solutions by the values of `keys` and binds each aggregate
+    /// to its variable.
+    Group {
+        pattern: Box<Pattern>,
+        keys: Vec<Variable>,
+        aggregates: Vec<(Variable, Aggregate)>,
+    },
+    OrderBy(Box<Pattern>, Vec<OrderKey>),
+    Project(Box<Pattern>, Vec<Variable>),
+    Distinct(Box<Pattern>),
+    Reduced(Box<Pattern>),
+    Slice {
+        pattern: Box<Pattern>,
+        offset: usize,
+        limit: Option<usize>,
+    },
+}
+
+impl Pattern {
+    /// Joins two patterns, merging basic graph patterns and dropping the
+    /// empty one, which is the identity of the join.
+    pub(crate) fn join(left: Pattern, right: Pattern) -> Pattern {
+        match (left, right) {
+            (Pattern::Bgp(left), right) if left.is_empty() => right,
+            (left, Pattern::Bgp(right)) if right.is_empty() => left,
+            (Pattern::Bgp(mut left), Pattern::Bgp(right)) => {
+                left.extend(right);
+                Pattern::Bgp(left)
+            }
+            (left, right) => Pattern::Join(Box::new(left), Box::new(right)),
+        }
+    }
+
+    /// Matches the triples of a pattern outside of a nested `GRAPH` in the
+    /// given graph, as in `GRAPH graph { pattern }`.
+    pub(crate) fn in_graph(self, graph: &PatternTerm) -> Pattern {
+        let boxed = |pattern: Box<Pattern>| Box::new(pattern.in_graph(graph));
+        match self {
+            Pattern::Bgp(quads) => Pattern::Bgp(
+                quads
+                    .into_iter()
+                    .map(|quad| QuadPattern {
+                        graph: quad.graph.or_else(|| Some(graph.clone())),
+                        ..quad
+                    })
+                    .collect(),
+            ),
+            Pattern::Join(left, right) => Pattern::Join(boxed(left), boxed(right)),
+            Pattern::LeftJoin(left, right, filter) => Pattern::LeftJoin(
+                boxed(left),
+                boxed(right),
+                filter.map(|filter| filter.in_graph(graph)),
+            ),
+            Pattern::Union(left, right) => Pattern::Union(boxed(left), boxed(right)),
+            Pattern::Minus(left, right) => Pattern::Minus(boxed(left), boxed(right)),
+            Pattern::Filter(filter, pattern) => {
+                Pattern::Filter(filter.in_graph(graph), boxed(pattern))
+            }
+            Pattern::Extend(pattern, variable, expression) => {
+                Pattern::Extend(boxed(pattern), variable, expression.in_graph(graph))
+            }
+            Pattern::Values(variables, values) => Pattern::Values(variables, values),
+            Pattern::Group {
+                pattern,
+                keys,
+                aggregates,
+            } => Pattern::Group {
+                pattern: boxed(pattern),
+                keys,
+                aggregates,
+            },
+            Pattern::OrderBy(pattern, keys) => Pattern::OrderBy(boxed(pattern), keys),
+            Pattern::Project(pattern, variables) => Pattern::Project(boxed(pattern), variables),
+            Pattern::Distinct(pattern) => Pattern::Distinct(boxed(pattern)),
+            Pattern::Reduced(pattern) => Pattern::Reduced(boxed(pattern)),
+            Pattern::Slice {
+                pattern,
+                offset,
+                limit,
+            } => Pattern::Slice {
+                pattern: boxed(pattern),
+                offset,
+                limit,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expression {
+    Term(Term),
+    Variable(Variable),
+    Or(Box<Expression>, Box<Expression>),
+    And(Box<Expression>, Box<Expression>),
+    Equal(Box<Expression>, Box<Expression>),
+    Less(Box<Expression>, Box<Expression>),
+    Greater(Box<Expression>, Box<Expression>),
+    LessOrEqual(Box<Expression>, Box<Expression>),
+    GreaterOrEqual(Box<Expression>, Box<Expression>),
+    In(Box<Expression>, Vec<Expression>),
+    Add(Box<Expression>, Box<Expression>),
+    Subtract(Box<Expression>, Box<Expression>),
+    Multiply(Box<Expression>, Box<Expression>),
+    Divide(Box<Expression>, Box<Expression>),
+    UnaryMinus(Box<Expression>),
+    Not(Box<Expression>),
+    Bound(Variable),
+    If(Box<Expression>, Box<Expression>, Box<Expression>),
+    Coalesce(Vec<Expression>),
+    Exists(Box<Pattern>),
+    FunctionCall(Function, Vec<Expression>),
+}
+
+impl Expression {
+    /// Matches the patterns of the `EXISTS` of an expression in the given
+    /// graph, see [`Pattern::in_graph`].
+    fn in_graph(self, graph: &PatternTerm) -> Expression {
+        let boxed = |expression: Box<Expression>| Box::new(expression.in_graph(graph));
+        let all = |list: Vec<Expression>| list.into_iter().map(|e| e.in_graph(graph)).collect();
+        match self {
+            Expression::Exists(pattern) => Expression::Exists(Box::new(pattern.in_graph(graph))),
+            Expression::Term(_) | Expression::Variable(_) | Expression::Bound(_) => self,
+            Expression::Or(left, right) => Expression::Or(boxed(left), boxed(right)),
+            Expression::And(left, right) => Expression::And(boxed(left), boxed(right)),
+            Expression::Equal(left, right) => Expression::Equal(boxed(left), boxed(right)),
+            Expression::Less(left, right) => Expression::Less(boxed(left), boxed(right)),
+            Expression::Greater(left, right) => Expression::Greater(boxed(left), boxed(right)),
+            Expression::LessOrEqual(left, right) => {
+                Expression::LessOrEqual(boxed(left), boxed(right))
+            }
+            Expression::GreaterOrEqual(left, right) => {
+                Expression::GreaterOrEqual(boxed(left), boxed(right))
+            }
+            Expression::Add(left, right) => Expression::Add(boxed(left), boxed(right)),
+            Expression::Subtract(left, right) => Expression::Subtract(boxed(left), boxed(right)),
+            Expression::Multiply(left, right) => Expression::Multiply(boxed(left), boxed(right)),
+            Expression::Divide(left, right) => Expression::Divide(boxed(left), boxed(right)),
+            Expression::UnaryMinus(value) => Expression::UnaryMinus(boxed(value)),
+            Expression::Not(value) => Expression::Not(boxed(value)),
+            Expression::In(value, list) => Expression::In(boxed(value), all(list)),
+            Expression::If(condition, then, otherwise) => {
+                Expression::If(boxed(condition), boxed(then), boxed(otherwise))
+            }
+            Expression::Coalesce(list) => Expression::Coalesce(all(list)),
+            Expression::FunctionCall(function, list) => {
+                Expression::FunctionCall(function, all(list))
+            }
+        }
+    }
+}
+
+/// Built-in functions, and casts to XSD datatypes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Function {
+    Str,
+    Lang,
+    LangMatches,
+    Datatype,
+    Iri,
+    IsIri,
+    IsBlank,
+    IsLiteral,
+    IsNumeric,
+    SameTerm,
+    StrDt,
+    StrLang,
+    StrLen,
+    SubStr,
+    UCase,
+    LCase,
+    StrStarts,
+    StrEnds,
+    Contains,
+    StrBefore,
+    StrAfter,
+    Concat,
+    Replace,
+    Regex,
+    Abs,
+    Ceil,
+    Floor,
+    Round,
+    Cast(NamedNode),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Aggregate {
+    /// `COUNT(*)` when there is no expression.
+    Count(Option<Box<Expression>>, bool),
+    Sum(Box<Expression>, bool),
+    Avg(Box<Expression>, bool),
+    Min(Box<Expression>, bool),
+    Max(Box<Expression>, bool),
+    Sample(Box<Expression>, bool),
+    GroupConcat(Box<Expression>, bool, Option<String>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum OrderKey {
+    Asc(Expression),
+    Desc(Expression),
+}
+
+/// What a query returns for the solutions of its pattern.
+#[derive(Clone, Debug, PartialEq)]
+pub enum QueryForm {
+    /// Solutions binding the given variables.
+    Select(Vec<Variable>),
+    /// Whether there is a solution.
+    Ask,
+    /// The triples of the template instantiated with every solution.
+    Construct(Vec<TriplePattern>),
+    /// The triples about the resources, which may be variables.
+    Describe(Vec<PatternTerm>),
+}
+
+/// Operation of an update, following section 3.1 of the SPARQL 1.1 Update
+/// specification.
+#[derive(Clone, Debug, PartialEq)]
+pub enum UpdateOperation {
+    /// `INSERT DATA`, whose blank nodes are fresh.
+    InsertData(Vec<Quad>),
+    /// `DELETE DATA`, which holds no blank node.
+    DeleteData(Vec<Quad>),
+    /// `DELETE`/`INSERT`, or `DELETE WHERE`, which instantiate their
+    /// templates with every solution of the pattern.
+    DeleteInsert {
+        delete: Vec<QuadPattern>,
+        insert: Vec<QuadPattern>,
+        pattern: Pattern,
+    },
+    /// `LOAD` of an RDF file into the graphs of its quads, or into the given
+    /// graph.
+    Load {
+        source: NamedNode,
+        destination: Option<NamedNode>,
+        silent: bool,
+    },
+    Clear(GraphTarget),
+}
+
+/// Graphs an operation such as `CLEAR` applies to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GraphTarget {
+    Graph(NamedNode),
+    Default,
+    Named,
+    All,
+}
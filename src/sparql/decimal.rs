@@ -0,0 +1,170 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Number of digits of a [`Decimal`] after the decimal point.
+const DIGITS: u32 = 18;
+
+const SCALE: i128 = 10_i128.pow(DIGITS);
+
+/// Value of an `xsd:decimal`, held exactly as a number of 10^-18 units,
+/// which leaves 20 digits before the decimal point.
+///
+/// Operations return `None` when the result does not fit, and digits past
+/// the 18th after the point are truncated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct Decimal(i128);
+
+impl Decimal {
+    pub(crate) fn checked_add(self, other: Decimal) -> Option<Decimal> {
+        self.0.checked_add(other.0).map(Decimal)
+    }
+
+    pub(crate) fn checked_sub(self, other: Decimal) -> Option<Decimal> {
+        self.0.checked_sub(other.0).map(Decimal)
+    }
+
+    pub(crate) fn checked_mul(self, other: Decimal) -> Option<Decimal> {
+        // Trailing zeros are dropped before multiplying so that the product
+        // of the units only overflows if the result does
+        let (left, left_shift) = strip_zeros(self.0);
+        let (right, right_shift) = strip_zeros(other.0);
+        let product = left.checked_mul(right)?;
+        let shift = left_shift + right_shift;
+        Some(Decimal(match shift.checked_sub(DIGITS) {
+            Some(shift) => product.checked_mul(10_i128.checked_pow(shift)?)?,
+            None => product / 10_i128.pow(DIGITS - shift),
+        }))
+    }
+
+    pub(crate) fn checked_div(self, other: Decimal) -> Option<Decimal> {
+        if other.0 == 0 {
+            return None;
+        }
+        // The dividend is scaled up and the divisor down as far as they go,
+        // to keep as many digits of the quotient as possible
+        let mut left = self.0;
+        let mut left_shift = 0;
+        while let Some(shifted) = left.checked_mul(10) {
+            left = shifted;
+            left_shift += 1;
+        }
+        let (right, right_shift) = strip_zeros(other.0);
+        let quotient = left.checked_div(right)?;
+        let shift = left_shift + right_shift;
+        Some(Decimal(match shift.checked_sub(DIGITS) {
+            Some(shift) => 10_i128
+                .checked_pow(shift)
+                .map_or(0, |scale| quotient / scale),
+            None => quotient.checked_mul(10_i128.pow(DIGITS - shift))?,
+        }))
+    }
+
+    pub(crate) fn checked_neg(self) -> Option<Decimal> {
+        self.0.checked_neg().map(Decimal)
+    }
+
+    pub(crate) fn checked_abs(self) -> Option<Decimal> {
+        self.0.checked_abs().map(Decimal)
+    }
+
+    pub(crate) fn floor(self) -> Option<Decimal> {
+        Decimal(self.0.div_euclid(SCALE)).scaled()
+    }
+
+    pub(crate) fn ceil(self) -> Option<Decimal> {
+        Decimal(-self.0.checked_neg()?.div_euclid(SCALE)).scaled()
+    }
+
+    /// Rounds halves towards positive infinity.
+    pub(crate) fn round(self) -> Option<Decimal> {
+        Decimal(self.0.checked_add(SCALE / 2)?).floor()
+    }
+
+    /// Returns the integer part, if it fits.
+    pub(crate) fn trunc(self) -> Option<i64> {
+        i64::try_from(self.0 / SCALE).ok()
+    }
+
+    pub(crate) fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    pub(crate) fn to_f64(self) -> f64 {
+        self.0 as f64 / SCALE as f64
+    }
+
+    /// Converts a finite double, keeping the digits of its shortest
+    /// representation.
+    pub(crate) fn from_f64(value: f64) -> Option<Decimal> {
+        match value.is_finite() {
+            true => value.to_string().parse().ok(),
+            false => None,
+        }
+    }
+
+    /// Turns a number of units into the same number of whole units.
+    fn scaled(self) -> Option<Decimal> {
+        self.0.checked_mul(SCALE).map(Decimal)
+    }
+}
+
+/// Removes the trailing zeros of a number, returning how many there were.
+fn strip_zeros(mut value: i128) -> (i128, u32) {
+    let mut zeros = 0;
+    while value != 0 && value % 10 == 0 {
+        value /= 10;
+        zeros += 1;
+    }
+    (value, zeros)
+}
+
+impl From<i64> for Decimal {
+    fn from(value: i64) -> Self {
+        // An i64 times 10^18 always fits in an i128
+        Decimal(i128::from(value) * SCALE)
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = ();
+
+    /// Parses the lexical form of `xsd:decimal`: an optional sign, followed
+    /// by digits with at most one decimal point.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (negative, digits) = match value.as_bytes().first() {
+            Some(b'-') => (true, &value[1..]),
+            Some(b'+') => (false, &value[1..]),
+            _ => (false, value),
+        };
+        let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        let all_digits = |part: &str| part.bytes().all(|c| c.is_ascii_digit());
+        if integer.len() + fraction.len() == 0 || !all_digits(integer) || !all_digits(fraction) {
+            return Err(());
+        }
+        let mut units: i128 = 0;
+        for c in integer.bytes() {
+            units = units.checked_mul(10).ok_or(())?;
+            units = units.checked_add(i128::from(c - b'0')).ok_or(())?;
+        }
+        let mut units = units.checked_mul(SCALE).ok_or(())?;
+        let mut unit = SCALE;
+        for c in fraction.bytes().take(DIGITS as usize) {
+            unit /= 10;
+            units += i128::from(c - b'0') * unit;
+        }
+        Ok(Decimal(if negative { -units } else { units }))
+    }
+}
+
+/// Writes the canonical form of the value, with at least one digit after the
+/// decimal point.
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let integer = (self.0 / SCALE).unsigned_abs();
+        let fraction = format!("{:018}", (self.0 % SCALE).unsigned_abs());
+        let fraction = fraction.trim_end_matches('0');
+        let fraction = if fraction.is_empty() { "0" } else { fraction };
+        write!(f, "{sign}{integer}.{fraction}")
+    }
+}
@@ -0,0 +1,491 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::iter;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use oxrdf::Term;
+use oxrdf::Variable;
+use regex::Regex;
+use zarrs::storage::ReadableWritableStorageTraits;
+
+use super::algebra::Expression;
+use super::algebra::OrderKey;
+use super::algebra::Pattern;
+use super::expression::effective_boolean_value;
+use super::expression::order;
+use crate::bgp::Graphs;
+use crate::bgp::PatternTerm;
+use crate::bgp::QuadPattern;
+use crate::bgp::QueryDataset;
+use crate::bgp::TriplePattern;
+use crate::error::ZarrDfError;
+use crate::storage::Storage;
+
+/// Values of the variables of a query in one of its solutions, indexed like
+/// [`Evaluator::variables`].
+pub(crate) type Row = Vec<Option<Term>>;
+
+pub(crate) type Rows<'a> = Box<dyn Iterator<Item = Result<Row, ZarrDfError>> + 'a>;
+
+/// Bottom-up evaluation of the algebra of a query over a [`Storage`].
+pub(crate) struct Evaluator<'a, S: ReadableWritableStorageTraits> {
+    storage: &'a Storage<S>,
+    /// Positions within rows of the variables the query may bind.
+    variables: HashMap<Variable, usize>,
+    /// Graphs of the dataset of the query.
+    graphs: Arc<Graphs>,
+    pub(crate) regexes: RefCell<HashMap<(String, String), Option<Regex>>>,
+}
+
+impl<'a, S: ReadableWritableStorageTraits + 'static> Evaluator<'a, S> {
+    pub(crate) fn new(
+        storage: &'a Storage<S>,
+        pattern: &Pattern,
+        dataset: Option<&QueryDataset>,
+    ) -> Self {
+        let mut variables = Vec::new();
+        bound_variables(pattern, &mut variables);
+        Evaluator {
+            storage,
+            variables: variables
+                .into_iter()
+                .enumerate()
+                .map(|(i, v)| (v, i))
+                .collect(),
+            graphs: Arc::new(storage.graphs(dataset)),
+            regexes: RefCell::default(),
+        }
+    }
+
+    pub(crate) fn value<'r>(&self, variable: &Variable, row: &'r Row) -> Option<&'r Term> {
+        row[*self.variables.get(variable)?].as_ref()
+    }
+
+    fn empty_row(&self) -> Row {
+        vec![None; self.variables.len()]
+    }
+
+    /// Returns the solutions of a pattern, which are computed lazily except
+    /// where the whole input is needed, as in `ORDER BY` or aggregates.
+    pub(crate) fn evaluate_pattern<'b>(&'b self, pattern: &'b Pattern) -> Rows<'b> {
+        match pattern {
+            Pattern::Bgp(quads) => self.bgp(quads, self.empty_row()),
+            Pattern::Join(left, right) => match &**right {
+                // Bound variables are replaced by their values, so the
+                // pattern is looked up in the index once per solution
+                Pattern::Bgp(quads) => {
+                    Box::new(self.evaluate_pattern(left).flat_map(move |row| match row {
+                        Ok(row) => self.bgp(quads, row),
+                        Err(error) => Box::new(iter::once(Err(error))),
+                    }))
+                }
+                right => {
+                    let right = match self.materialize(right) {
+                        Ok(right) => right,
+                        Err(error) => return Box::new(iter::once(Err(error))),
+                    };
+                    Box::new(
+                        self.evaluate_pattern(left)
+                            .flat_map(move |row| -> Rows<'b> {
+                                let right = right.clone();
+                                match row {
+                                    Ok(row) => Box::new(
+                                        (0..right.len())
+                                            .filter_map(move |i| merge(&row, &right[i]).map(Ok)),
+                                    ),
+                                    Err(error) => Box::new(iter::once(Err(error))),
+                                }
+                            }),
+                    )
+                }
+            },
+            Pattern::LeftJoin(left, right, filter) => {
+                let materialized = match &**right {
+                    Pattern::Bgp(_) => None,
+                    right => match self.materialize(right) {
+                        Ok(right) => Some(right),
+                        Err(error) => return Box::new(iter::once(Err(error))),
+                    },
+                };
+                Box::new(
+                    self.evaluate_pattern(left)
+                        .flat_map(move |row| -> Rows<'b> {
+                            let row = match row {
+                                Ok(row) => row,
+                                Err(error) => return Box::new(iter::once(Err(error))),
+                            };
+                            let matches: Result<Vec<Row>, ZarrDfError> =
+                                match (&**right, &materialized) {
+                                    (Pattern::Bgp(quads), _) => {
+                                        self.bgp(quads, row.clone()).collect()
+                                    }
+                                    (_, Some(right)) => Ok(right
+                                        .iter()
+                                        .filter_map(|right| merge(&row, right))
+                                        .collect()),
+                                    (_, None) => unreachable!(
+                                        "only basic graph patterns are not materialized"
+                                    ),
+                                };
+                            let matches = matches.and_then(|matches| match filter {
+                                Some(filter) => self.filter(matches, filter),
+                                None => Ok(matches),
+                            });
+                            match matches {
+                                Ok(matches) if matches.is_empty() => Box::new(iter::once(Ok(row))),
+                                Ok(matches) => Box::new(matches.into_iter().map(Ok)),
+                                Err(error) => Box::new(iter::once(Err(error))),
+                            }
+                        }),
+                )
+            }
+            Pattern::Union(left, right) => Box::new(
+                self.evaluate_pattern(left)
+                    .chain(self.evaluate_pattern(right)),
+            ),
+            Pattern::Minus(left, right) => {
+                let right = match self.materialize(right) {
+                    Ok(right) => right,
+                    Err(error) => return Box::new(iter::once(Err(error))),
+                };
+                Box::new(self.evaluate_pattern(left).filter(move |row| {
+                    match row {
+                        Ok(row) => !right.iter().any(|right| {
+                            shares_variable(row, right) && merge(row, right).is_some()
+                        }),
+                        Err(_) => true,
+                    }
+                }))
+            }
+            Pattern::Filter(filter, pattern) => {
+                Box::new(self.evaluate_pattern(pattern).filter_map(move |row| {
+                    let row = match row {
+                        Ok(row) => row,
+                        Err(error) => return Some(Err(error)),
+                    };
+                    match self.evaluate(filter, &row) {
+                        Ok(value) => value
+                            .as_ref()
+                            .and_then(effective_boolean_value)
+                            .unwrap_or(false)
+                            .then_some(Ok(row)),
+                        Err(error) => Some(Err(error)),
+                    }
+                }))
+            }
+            Pattern::Extend(pattern, variable, expression) => {
+                let position = self.variables[variable];
+                Box::new(self.evaluate_pattern(pattern).map(move |row| {
+                    let mut row = row?;
+                    row[position] = self.evaluate(expression, &row)?;
+                    Ok(row)
+                }))
+            }
+            Pattern::Values(variables, values) => Box::new(values.iter().map(move |values| {
+                let mut row = self.empty_row();
+                for (variable, value) in variables.iter().zip(values) {
+                    row[self.variables[variable]] = value.clone();
+                }
+                Ok(row)
+            })),
+            Pattern::Group {
+                pattern,
+                keys,
+                aggregates,
+            } => match self.group(pattern, keys, aggregates) {
+                Ok(rows) => Box::new(rows.into_iter().map(Ok)),
+                Err(error) => Box::new(iter::once(Err(error))),
+            },
+            Pattern::OrderBy(pattern, keys) => match self.order_by(pattern, keys) {
+                Ok(rows) => Box::new(rows.into_iter().map(Ok)),
+                Err(error) => Box::new(iter::once(Err(error))),
+            },
+            Pattern::Project(pattern, variables) => {
+                let positions: Vec<usize> = variables
+                    .iter()
+                    .filter_map(|variable| self.variables.get(variable).copied())
+                    .collect();
+                Box::new(self.evaluate_pattern(pattern).map(move |row| {
+                    let row = row?;
+                    let mut projected = self.empty_row();
+                    for &position in &positions {
+                        projected[position] = row[position].clone();
+                    }
+                    Ok(projected)
+                }))
+            }
+            Pattern::Distinct(pattern) => {
+                let mut seen = HashSet::new();
+                Box::new(self.evaluate_pattern(pattern).filter(move |row| match row {
+                    Ok(row) => seen.insert(row.clone()),
+                    Err(_) => true,
+                }))
+            }
+            // Removing duplicates is allowed but not required
+            Pattern::Reduced(pattern) => self.evaluate_pattern(pattern),
+            Pattern::Slice {
+                pattern,
+                offset,
+                limit,
+            } => {
+                let rows = self.evaluate_pattern(pattern).skip(*offset);
+                match limit {
+                    Some(limit) => Box::new(rows.take(*limit)),
+                    None => Box::new(rows),
+                }
+            }
+        }
+    }
+
+    /// Matches a basic graph pattern, with the variables bound by `row`
+    /// replaced by their values, and extends `row` with each solution.
+    fn bgp<'b>(&'b self, quads: &[QuadPattern], row: Row) -> Rows<'b> {
+        let substitute = |term: &PatternTerm| match term {
+            PatternTerm::Variable(variable) => match self.value(variable, &row) {
+                Some(value) => PatternTerm::Term(value.clone()),
+                None => term.clone(),
+            },
+            term => term.clone(),
+        };
+        let quads: Vec<_> = quads
+            .iter()
+            .map(|quad| QuadPattern {
+                triple: TriplePattern {
+                    subject: substitute(&quad.triple.subject),
+                    predicate: substitute(&quad.triple.predicate),
+                    object: substitute(&quad.triple.object),
+                },
+                graph: quad.graph.as_ref().map(substitute),
+            })
+            .collect();
+        let solutions = self.storage.evaluate_bgp(&quads, &self.graphs);
+        Box::new(solutions.map(move |solution| {
+            let mut row = row.clone();
+            for (variable, value) in solution?.iter() {
+                row[self.variables[variable]] = Some(value.clone());
+            }
+            Ok(row)
+        }))
+    }
+
+    fn materialize(&self, pattern: &Pattern) -> Result<Rc<Vec<Row>>, ZarrDfError> {
+        Ok(Rc::new(
+            self.evaluate_pattern(pattern).collect::<Result<_, _>>()?,
+        ))
+    }
+
+    /// Keeps the rows for which the expression is true.
+    fn filter(&self, rows: Vec<Row>, filter: &Expression) -> Result<Vec<Row>, ZarrDfError> {
+        let mut kept = Vec::with_capacity(rows.len());
+        for row in rows {
+            let value = self.evaluate(filter, &row)?;
+            if value.as_ref().and_then(effective_boolean_value) == Some(true) {
+                kept.push(row);
+            }
+        }
+        Ok(kept)
+    }
+
+    /// Whether a pattern has a solution compatible with `row`.
+    pub(crate) fn exists(&self, pattern: &Pattern, row: &Row) -> Result<bool, ZarrDfError> {
+        let mut rows = match pattern {
+            Pattern::Bgp(quads) => self.bgp(quads, row.clone()),
+            pattern => Box::new(self.evaluate_pattern(pattern).filter(|solution| {
+                solution
+                    .as_ref()
+                    .map_or(true, |solution| merge(row, solution).is_some())
+            })),
+        };
+        Ok(rows.next().transpose()?.is_some())
+    }
+
+    fn group(
+        &self,
+        pattern: &Pattern,
+        keys: &[Variable],
+        aggregates: &[(Variable, super::algebra::Aggregate)],
+    ) -> Result<Vec<Row>, ZarrDfError> {
+        let positions: Vec<Option<usize>> = keys
+            .iter()
+            .map(|key| self.variables.get(key).copied())
+            .collect();
+        let mut groups: Vec<(Vec<Option<Term>>, Vec<Row>)> = Vec::new();
+        let mut indexes = HashMap::new();
+        for row in self.evaluate_pattern(pattern) {
+            let row = row?;
+            let key: Vec<_> = positions
+                .iter()
+                .map(|position| position.and_then(|position| row[position].clone()))
+                .collect();
+            let index = *indexes.entry(key.clone()).or_insert_with(|| {
+                groups.push((key, Vec::new()));
+                groups.len() - 1
+            });
+            groups[index].1.push(row);
+        }
+        // Aggregating without grouping gives a single group, even if empty
+        if groups.is_empty() && keys.is_empty() {
+            groups.push((Vec::new(), Vec::new()));
+        }
+
+        let mut rows = Vec::with_capacity(groups.len());
+        for (key, group) in groups {
+            let mut row = self.empty_row();
+            for (position, value) in positions.iter().zip(key) {
+                if let Some(position) = position {
+                    row[*position] = value;
+                }
+            }
+            for (variable, aggregate) in aggregates {
+                row[self.variables[variable]] = self.aggregate(aggregate, &group)?;
+            }
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+
+    fn order_by(&self, pattern: &Pattern, keys: &[OrderKey]) -> Result<Vec<Row>, ZarrDfError> {
+        let mut rows = Vec::new();
+        for row in self.evaluate_pattern(pattern) {
+            let row = row?;
+            let mut values = Vec::with_capacity(keys.len());
+            for key in keys {
+                let (OrderKey::Asc(expression) | OrderKey::Desc(expression)) = key;
+                values.push(self.evaluate(expression, &row)?);
+            }
+            rows.push((values, row));
+        }
+        rows.sort_by(|(left, _), (right, _)| {
+            keys.iter()
+                .zip(left.iter().zip(right))
+                .map(|(key, (left, right))| {
+                    let ordering = order(left.as_ref(), right.as_ref());
+                    match key {
+                        OrderKey::Asc(_) => ordering,
+                        OrderKey::Desc(_) => ordering.reverse(),
+                    }
+                })
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        Ok(rows.into_iter().map(|(_, row)| row).collect())
+    }
+}
+
+/// Combines two solutions, or returns `None` if they bind a variable to
+/// different values.
+fn merge(left: &Row, right: &Row) -> Option<Row> {
+    left.iter()
+        .zip(right)
+        .map(|(left, right)| match (left, right) {
+            (Some(left), Some(right)) => (left == right).then(|| Some(left.clone())),
+            (Some(value), None) | (None, Some(value)) => Some(Some(value.clone())),
+            (None, None) => Some(None),
+        })
+        .collect()
+}
+
+fn shares_variable(left: &Row, right: &Row) -> bool {
+    left.iter()
+        .zip(right)
+        .any(|(left, right)| left.is_some() && right.is_some())
+}
+
+/// Adds the variables a pattern may bind, including in `EXISTS`, to
+/// `variables`.
+fn bound_variables(pattern: &Pattern, variables: &mut Vec<Variable>) {
+    let mut add = |variable: &Variable| {
+        if !variables.contains(variable) {
+            variables.push(variable.clone());
+        }
+    };
+    match pattern {
+        Pattern::Bgp(quads) => {
+            for quad in quads {
+                let triple = &quad.triple;
+                let terms = [&triple.subject, &triple.predicate, &triple.object];
+                for term in terms.into_iter().chain(&quad.graph) {
+                    if let PatternTerm::Variable(variable) = term {
+                        add(variable);
+                    }
+                }
+            }
+        }
+        Pattern::Values(bound, _) => bound.iter().for_each(add),
+        Pattern::Join(left, right) | Pattern::Union(left, right) | Pattern::Minus(left, right) => {
+            bound_variables(left, variables);
+            bound_variables(right, variables);
+        }
+        Pattern::LeftJoin(left, right, filter) => {
+            bound_variables(left, variables);
+            bound_variables(right, variables);
+            if let Some(filter) = filter {
+                expression_variables(filter, variables);
+            }
+        }
+        Pattern::Filter(filter, pattern) => {
+            expression_variables(filter, variables);
+            bound_variables(pattern, variables);
+        }
+        Pattern::Extend(pattern, variable, expression) => {
+            add(variable);
+            expression_variables(expression, variables);
+            bound_variables(pattern, variables);
+        }
+        Pattern::Group {
+            pattern,
+            keys,
+            aggregates,
+        } => {
+            keys.iter().for_each(&mut add);
+            aggregates.iter().for_each(|(variable, _)| add(variable));
+            bound_variables(pattern, variables);
+        }
+        Pattern::OrderBy(pattern, _)
+        | Pattern::Project(pattern, _)
+        | Pattern::Distinct(pattern)
+        | Pattern::Reduced(pattern)
+        | Pattern::Slice { pattern, .. } => bound_variables(pattern, variables),
+    }
+}
+
+/// Adds the variables bound by the `EXISTS` of an expression to `variables`.
+fn expression_variables(expression: &Expression, variables: &mut Vec<Variable>) {
+    match expression {
+        Expression::Exists(pattern) => bound_variables(pattern, variables),
+        Expression::Term(_) | Expression::Variable(_) | Expression::Bound(_) => {}
+        Expression::Or(left, right)
+        | Expression::And(left, right)
+        | Expression::Equal(left, right)
+        | Expression::Less(left, right)
+        | Expression::Greater(left, right)
+        | Expression::LessOrEqual(left, right)
+        | Expression::GreaterOrEqual(left, right)
+        | Expression::Add(left, right)
+        | Expression::Subtract(left, right)
+        | Expression::Multiply(left, right)
+        | Expression::Divide(left, right) => {
+            expression_variables(left, variables);
+            expression_variables(right, variables);
+        }
+        Expression::UnaryMinus(value) | Expression::Not(value) => {
+            expression_variables(value, variables)
+        }
+        Expression::In(value, list) => {
+            expression_variables(value, variables);
+            list.iter()
+                .for_each(|item| expression_variables(item, variables));
+        }
+        Expression::If(condition, then, otherwise) => {
+            for expression in [condition, then, otherwise] {
+                expression_variables(expression, variables);
+            }
+        }
+        Expression::Coalesce(list) | Expression::FunctionCall(_, list) => {
+            list.iter()
+                .for_each(|item| expression_variables(item, variables));
+        }
+    }
+}
@@ -0,0 +1,711 @@
+use std::cmp::Ordering;
+
+use oxrdf::vocab::xsd;
+use oxrdf::Literal;
+use oxrdf::NamedNode;
+use oxrdf::Term;
+use regex::Regex;
+use regex::RegexBuilder;
+use zarrs::storage::ReadableWritableStorageTraits;
+
+use super::algebra::Aggregate;
+use super::algebra::Expression;
+use super::algebra::Function;
+use super::decimal::Decimal;
+use super::eval::Evaluator;
+use super::eval::Row;
+use crate::error::ZarrDfError;
+
+/// Datatypes whose values are integers.
+const INTEGERS: [&str; 13] = [
+    "integer",
+    "long",
+    "int",
+    "short",
+    "byte",
+    "nonNegativeInteger",
+    "positiveInteger",
+    "nonPositiveInteger",
+    "negativeInteger",
+    "unsignedLong",
+    "unsignedInt",
+    "unsignedShort",
+    "unsignedByte",
+];
+
+/// Value of a numeric literal, ordered by type promotion.
+#[derive(Clone, Copy, Debug)]
+enum Number {
+    Integer(i64),
+    Decimal(Decimal),
+    Float(f64),
+    Double(f64),
+}
+
+impl Number {
+    fn from_term(term: &Term) -> Option<Number> {
+        let Term::Literal(literal) = term else {
+            return None;
+        };
+        let datatype = literal.datatype().as_str();
+        let value = literal.value().trim();
+        let local = datatype.strip_prefix("http://www.w3.org/2001/XMLSchema#")?;
+        match local {
+            "decimal" => Some(Number::Decimal(value.parse().ok()?)),
+            "float" => Some(Number::Float(parse_double(value)?)),
+            "double" => Some(Number::Double(parse_double(value)?)),
+            local if INTEGERS.contains(&local) => Some(Number::Integer(value.parse().ok()?)),
+            _ => None,
+        }
+    }
+
+    fn rank(self) -> u8 {
+        match self {
+            Number::Integer(_) => 0,
+            Number::Decimal(_) => 1,
+            Number::Float(_) => 2,
+            Number::Double(_) => 3,
+        }
+    }
+
+    fn to_f64(self) -> f64 {
+        match self {
+            Number::Integer(value) => value as f64,
+            Number::Decimal(value) => value.to_f64(),
+            Number::Float(value) | Number::Double(value) => value,
+        }
+    }
+
+    /// Returns the value with the type of the given rank if it is wider.
+    fn promote(self, rank: u8) -> Number {
+        match (rank, self) {
+            (rank, number) if rank <= number.rank() => number,
+            (1, Number::Integer(value)) => Number::Decimal(value.into()),
+            (2, number) => Number::Float(number.to_f64()),
+            (_, number) => Number::Double(number.to_f64()),
+        }
+    }
+
+    fn compare(self, other: Number) -> Option<Ordering> {
+        let rank = self.rank().max(other.rank());
+        match (self.promote(rank), other.promote(rank)) {
+            (Number::Integer(left), Number::Integer(right)) => Some(left.cmp(&right)),
+            (Number::Decimal(left), Number::Decimal(right)) => Some(left.cmp(&right)),
+            (left, right) => left.to_f64().partial_cmp(&right.to_f64()),
+        }
+    }
+
+    fn arithmetic(self, other: Number, operator: char) -> Option<Number> {
+        let rank = self.rank().max(other.rank());
+        let (left, right) = (self.promote(rank), other.promote(rank));
+        let apply = |left: f64, right: f64| match operator {
+            '+' => left + right,
+            '-' => left - right,
+            '*' => left * right,
+            _ => left / right,
+        };
+        Some(match (left, right) {
+            (Number::Integer(left), Number::Integer(right)) => match operator {
+                '+' => Number::Integer(left.checked_add(right)?),
+                '-' => Number::Integer(left.checked_sub(right)?),
+                '*' => Number::Integer(left.checked_mul(right)?),
+                // Dividing integers gives a decimal
+                _ => Number::Decimal(Decimal::from(left).checked_div(right.into())?),
+            },
+            (Number::Decimal(left), Number::Decimal(right)) => Number::Decimal(match operator {
+                '+' => left.checked_add(right)?,
+                '-' => left.checked_sub(right)?,
+                '*' => left.checked_mul(right)?,
+                _ => left.checked_div(right)?,
+            }),
+            (Number::Float(left), Number::Float(right)) => Number::Float(apply(left, right)),
+            (left, right) => Number::Double(apply(left.to_f64(), right.to_f64())),
+        })
+    }
+
+    fn map(
+        self,
+        integer: fn(i64) -> Option<i64>,
+        decimal: fn(Decimal) -> Option<Decimal>,
+        float: fn(f64) -> f64,
+    ) -> Option<Number> {
+        Some(match self {
+            Number::Integer(value) => Number::Integer(integer(value)?),
+            Number::Decimal(value) => Number::Decimal(decimal(value)?),
+            Number::Float(value) => Number::Float(float(value)),
+            Number::Double(value) => Number::Double(float(value)),
+        })
+    }
+
+    fn into_term(self) -> Term {
+        let (value, datatype) = match self {
+            Number::Integer(value) => (value.to_string(), xsd::INTEGER),
+            Number::Decimal(value) => (value.to_string(), xsd::DECIMAL),
+            Number::Float(value) => (format_double(value), xsd::FLOAT),
+            Number::Double(value) => (format_double(value), xsd::DOUBLE),
+        };
+        Literal::new_typed_literal(value, datatype).into()
+    }
+}
+
+fn parse_double(value: &str) -> Option<f64> {
+    match value {
+        "INF" | "+INF" => Some(f64::INFINITY),
+        "-INF" => Some(f64::NEG_INFINITY),
+        "NaN" => Some(f64::NAN),
+        value => value.parse().ok(),
+    }
+}
+
+fn format_double(value: f64) -> String {
+    match value {
+        value if value.is_nan() => "NaN".to_string(),
+        f64::INFINITY => "INF".to_string(),
+        f64::NEG_INFINITY => "-INF".to_string(),
+        value => format!("{value:E}"),
+    }
+}
+
+pub(crate) fn boolean(value: bool) -> Term {
+    Literal::new_typed_literal(value.to_string(), xsd::BOOLEAN).into()
+}
+
+pub(crate) fn integer(value: usize) -> Term {
+    Literal::new_typed_literal(value.to_string(), xsd::INTEGER).into()
+}
+
+/// Returns the value and the language of a simple literal, an `xsd:string` or
+/// a language-tagged string.
+fn string(term: &Term) -> Option<(&str, Option<&str>)> {
+    match term {
+        Term::Literal(literal) if literal.language().is_some() => {
+            Some((literal.value(), literal.language()))
+        }
+        Term::Literal(literal) if literal.datatype() == xsd::STRING => {
+            Some((literal.value(), None))
+        }
+        _ => None,
+    }
+}
+
+fn string_literal(value: impl Into<String>, language: Option<&str>) -> Term {
+    match language {
+        Some(language) => Literal::new_language_tagged_literal_unchecked(value, language).into(),
+        None => Literal::new_simple_literal(value).into(),
+    }
+}
+
+/// Effective boolean value of a term, as in section 17.2.2.
+pub(crate) fn effective_boolean_value(term: &Term) -> Option<bool> {
+    if let Some(number) = Number::from_term(term) {
+        return Some(match number {
+            Number::Integer(value) => value != 0,
+            Number::Decimal(value) => !value.is_zero(),
+            number => number.to_f64() != 0.0 && !number.to_f64().is_nan(),
+        });
+    }
+    match term {
+        Term::Literal(literal) if literal.datatype() == xsd::BOOLEAN => {
+            Some(matches!(literal.value(), "true" | "1"))
+        }
+        Term::Literal(literal) if literal.datatype() == xsd::STRING => {
+            Some(!literal.value().is_empty())
+        }
+        _ => None,
+    }
+}
+
+/// Compares two terms by value, if they are comparable with `<`.
+fn compare(left: &Term, right: &Term) -> Option<Ordering> {
+    if let (Some(left), Some(right)) = (Number::from_term(left), Number::from_term(right)) {
+        return left.compare(right);
+    }
+    let (Term::Literal(left), Term::Literal(right)) = (left, right) else {
+        return None;
+    };
+    let comparable = [
+        xsd::STRING,
+        xsd::BOOLEAN,
+        xsd::DATE_TIME,
+        xsd::DATE,
+        xsd::TIME,
+    ];
+    if left.datatype() == right.datatype() && comparable.contains(&left.datatype()) {
+        return Some(left.value().cmp(right.value()));
+    }
+    None
+}
+
+/// Whether two terms are equal, comparing literals by value.
+fn equal(left: &Term, right: &Term) -> Option<bool> {
+    if left == right {
+        return Some(true);
+    }
+    match compare(left, right) {
+        Some(ordering) => Some(ordering == Ordering::Equal),
+        None => Some(false),
+    }
+}
+
+/// Total order of `ORDER BY`, where unbound values come first, followed by
+/// blank nodes, IRIs and literals.
+pub(crate) fn order(left: Option<&Term>, right: Option<&Term>) -> Ordering {
+    let rank = |term: Option<&Term>| match term {
+        None => 0,
+        Some(Term::BlankNode(_)) => 1,
+        Some(Term::NamedNode(_)) => 2,
+        Some(_) => 3,
+    };
+    match (left, right) {
+        (Some(Term::BlankNode(left)), Some(Term::BlankNode(right))) => {
+            left.as_str().cmp(right.as_str())
+        }
+        (Some(Term::NamedNode(left)), Some(Term::NamedNode(right))) => {
+            left.as_str().cmp(right.as_str())
+        }
+        (Some(Term::Literal(l)), Some(Term::Literal(r))) => compare(left.unwrap(), right.unwrap())
+            .unwrap_or_else(|| {
+                (l.value(), l.datatype().as_str(), l.language()).cmp(&(
+                    r.value(),
+                    r.datatype().as_str(),
+                    r.language(),
+                ))
+            }),
+        (left, right) => rank(left).cmp(&rank(right)),
+    }
+}
+
+impl<S: ReadableWritableStorageTraits + 'static> Evaluator<'_, S> {
+    /// Evaluates an expression, where `None` stands for an error of the
+    /// expression, such as an unbound variable or a type mismatch.
+    pub(crate) fn evaluate(
+        &self,
+        expression: &Expression,
+        row: &Row,
+    ) -> Result<Option<Term>, ZarrDfError> {
+        let ebv = |expression| -> Result<Option<bool>, ZarrDfError> {
+            Ok(self
+                .evaluate(expression, row)?
+                .as_ref()
+                .and_then(effective_boolean_value))
+        };
+        let binary = |left, right| -> Result<Option<(Term, Term)>, ZarrDfError> {
+            let left = self.evaluate(left, row)?;
+            let right = self.evaluate(right, row)?;
+            Ok(left.zip(right))
+        };
+        let comparison =
+            |left, right, expected: &[Ordering]| -> Result<Option<Term>, ZarrDfError> {
+                Ok(binary(left, right)?
+                    .and_then(|(left, right)| compare(&left, &right))
+                    .map(|ordering| boolean(expected.contains(&ordering))))
+            };
+        let arithmetic = |left, right, operator| -> Result<Option<Term>, ZarrDfError> {
+            Ok(binary(left, right)?.and_then(|(left, right)| {
+                let left = Number::from_term(&left)?;
+                let right = Number::from_term(&right)?;
+                Some(left.arithmetic(right, operator)?.into_term())
+            }))
+        };
+        Ok(match expression {
+            Expression::Term(term) => Some(term.clone()),
+            Expression::Variable(variable) => self.value(variable, row).cloned(),
+            // Errors are only propagated if the other operand does not decide
+            Expression::Or(left, right) => match (ebv(left)?, ebv(right)?) {
+                (Some(true), _) | (_, Some(true)) => Some(boolean(true)),
+                (Some(false), Some(false)) => Some(boolean(false)),
+                _ => None,
+            },
+            Expression::And(left, right) => match (ebv(left)?, ebv(right)?) {
+                (Some(false), _) | (_, Some(false)) => Some(boolean(false)),
+                (Some(true), Some(true)) => Some(boolean(true)),
+                _ => None,
+            },
+            Expression::Equal(left, right) => binary(left, right)?
+                .and_then(|(left, right)| equal(&left, &right))
+                .map(boolean),
+            Expression::Less(left, right) => comparison(left, right, &[Ordering::Less])?,
+            Expression::Greater(left, right) => comparison(left, right, &[Ordering::Greater])?,
+            Expression::LessOrEqual(left, right) => {
+                comparison(left, right, &[Ordering::Less, Ordering::Equal])?
+            }
+            Expression::GreaterOrEqual(left, right) => {
+                comparison(left, right, &[Ordering::Greater, Ordering::Equal])?
+            }
+            Expression::In(value, list) => {
+                let Some(value) = self.evaluate(value, row)? else {
+                    return Ok(None);
+                };
+                let mut error = false;
+                for item in list {
+                    match self
+                        .evaluate(item, row)?
+                        .and_then(|item| equal(&value, &item))
+                    {
+                        Some(true) => return Ok(Some(boolean(true))),
+                        Some(false) => {}
+                        None => error = true,
+                    }
+                }
+                (!error).then(|| boolean(false))
+            }
+            Expression::Add(left, right) => arithmetic(left, right, '+')?,
+            Expression::Subtract(left, right) => arithmetic(left, right, '-')?,
+            Expression::Multiply(left, right) => arithmetic(left, right, '*')?,
+            Expression::Divide(left, right) => arithmetic(left, right, '/')?,
+            Expression::UnaryMinus(value) => self
+                .evaluate(value, row)?
+                .as_ref()
+                .and_then(Number::from_term)
+                .and_then(|number| {
+                    number.map(i64::checked_neg, Decimal::checked_neg, |value| -value)
+                })
+                .map(Number::into_term),
+            Expression::Not(value) => ebv(value)?.map(|value| boolean(!value)),
+            Expression::Bound(variable) => Some(boolean(self.value(variable, row).is_some())),
+            Expression::If(condition, then, otherwise) => match ebv(condition)? {
+                Some(true) => self.evaluate(then, row)?,
+                Some(false) => self.evaluate(otherwise, row)?,
+                None => None,
+            },
+            Expression::Coalesce(expressions) => {
+                for expression in expressions {
+                    if let Some(value) = self.evaluate(expression, row)? {
+                        return Ok(Some(value));
+                    }
+                }
+                None
+            }
+            Expression::Exists(pattern) => Some(boolean(self.exists(pattern, row)?)),
+            Expression::FunctionCall(function, arguments) => {
+                let mut values = Vec::with_capacity(arguments.len());
+                for argument in arguments {
+                    match self.evaluate(argument, row)? {
+                        Some(value) => values.push(value),
+                        None => return Ok(None),
+                    }
+                }
+                self.function(function, &values)
+            }
+        })
+    }
+
+    fn function(&self, function: &Function, arguments: &[Term]) -> Option<Term> {
+        let string_argument = |i: usize| string(&arguments[i]);
+        let number_argument = |i: usize| Number::from_term(&arguments[i]);
+        match function {
+            Function::Str => match &arguments[0] {
+                Term::NamedNode(iri) => Some(string_literal(iri.as_str(), None)),
+                Term::Literal(literal) => Some(string_literal(literal.value(), None)),
+                _ => None,
+            },
+            Function::Lang => match &arguments[0] {
+                Term::Literal(literal) => {
+                    Some(string_literal(literal.language().unwrap_or(""), None))
+                }
+                _ => None,
+            },
+            Function::LangMatches => {
+                let (tag, _) = string_argument(0)?;
+                let (range, _) = string_argument(1)?;
+                let matches = match range {
+                    "*" => !tag.is_empty(),
+                    range => {
+                        let tag = tag.to_ascii_lowercase();
+                        let range = range.to_ascii_lowercase();
+                        tag == range || tag.starts_with(&format!("{range}-"))
+                    }
+                };
+                Some(boolean(matches))
+            }
+            Function::Datatype => match &arguments[0] {
+                Term::Literal(literal) => Some(literal.datatype().into_owned().into()),
+                _ => None,
+            },
+            Function::Iri => match &arguments[0] {
+                Term::NamedNode(iri) => Some(iri.clone().into()),
+                term => Some(NamedNode::new(string(term)?.0).ok()?.into()),
+            },
+            Function::IsIri => Some(boolean(arguments[0].is_named_node())),
+            Function::IsBlank => Some(boolean(arguments[0].is_blank_node())),
+            Function::IsLiteral => Some(boolean(arguments[0].is_literal())),
+            Function::IsNumeric => Some(boolean(number_argument(0).is_some())),
+            Function::SameTerm => Some(boolean(arguments[0] == arguments[1])),
+            Function::StrDt => {
+                let (value, None) = string_argument(0)? else {
+                    return None;
+                };
+                let Term::NamedNode(datatype) = &arguments[1] else {
+                    return None;
+                };
+                Some(Literal::new_typed_literal(value, datatype.clone()).into())
+            }
+            Function::StrLang => {
+                let (value, None) = string_argument(0)? else {
+                    return None;
+                };
+                let (language, _) = string_argument(1)?;
+                Some(
+                    Literal::new_language_tagged_literal(value, language)
+                        .ok()?
+                        .into(),
+                )
+            }
+            Function::StrLen => Some(integer(string_argument(0)?.0.chars().count())),
+            Function::SubStr => {
+                let (value, language) = string_argument(0)?;
+                // Positions start at one and are rounded like in XPath
+                let start = number_argument(1)?.to_f64().round();
+                let end = match arguments.get(2) {
+                    Some(length) => start + Number::from_term(length)?.to_f64().round(),
+                    None => f64::INFINITY,
+                };
+                let substring: String = value
+                    .chars()
+                    .enumerate()
+                    .filter(|(i, _)| {
+                        let position = (*i + 1) as f64;
+                        position >= start && position < end
+                    })
+                    .map(|(_, c)| c)
+                    .collect();
+                Some(string_literal(substring, language))
+            }
+            Function::UCase => {
+                let (value, language) = string_argument(0)?;
+                Some(string_literal(value.to_uppercase(), language))
+            }
+            Function::LCase => {
+                let (value, language) = string_argument(0)?;
+                Some(string_literal(value.to_lowercase(), language))
+            }
+            Function::StrStarts => Some(boolean(
+                string_argument(0)?.0.starts_with(string_argument(1)?.0),
+            )),
+            Function::StrEnds => Some(boolean(
+                string_argument(0)?.0.ends_with(string_argument(1)?.0),
+            )),
+            Function::Contains => Some(boolean(
+                string_argument(0)?.0.contains(string_argument(1)?.0),
+            )),
+            Function::StrBefore => {
+                let (value, language) = string_argument(0)?;
+                Some(match value.find(string_argument(1)?.0) {
+                    Some(end) => string_literal(&value[..end], language),
+                    None => string_literal("", None),
+                })
+            }
+            Function::StrAfter => {
+                let (value, language) = string_argument(0)?;
+                let pattern = string_argument(1)?.0;
+                Some(match value.find(pattern) {
+                    Some(start) => string_literal(&value[start + pattern.len()..], language),
+                    None => string_literal("", None),
+                })
+            }
+            Function::Concat => {
+                let mut result = String::new();
+                let mut languages = Vec::new();
+                for argument in arguments {
+                    let (value, language) = string(argument)?;
+                    result.push_str(value);
+                    languages.push(language);
+                }
+                // The language is kept only if all the arguments share it
+                let language = match languages.split_first() {
+                    Some((first, rest)) if rest.iter().all(|language| language == first) => *first,
+                    _ => None,
+                };
+                Some(string_literal(result, language))
+            }
+            Function::Regex => {
+                let (value, _) = string_argument(0)?;
+                let regex = self.regex(string_argument(1)?.0, arguments.get(2))?;
+                Some(boolean(regex.is_match(value)))
+            }
+            Function::Replace => {
+                let (value, language) = string_argument(0)?;
+                let regex = self.regex(string_argument(1)?.0, arguments.get(3))?;
+                let replacement = string_argument(2)?.0;
+                Some(string_literal(
+                    regex.replace_all(value, replacement),
+                    language,
+                ))
+            }
+            Function::Abs => number_argument(0)?
+                .map(i64::checked_abs, Decimal::checked_abs, f64::abs)
+                .map(Number::into_term),
+            Function::Ceil => number_argument(0)?
+                .map(Some, Decimal::ceil, f64::ceil)
+                .map(Number::into_term),
+            Function::Floor => number_argument(0)?
+                .map(Some, Decimal::floor, f64::floor)
+                .map(Number::into_term),
+            // Halves are rounded towards positive infinity
+            Function::Round => number_argument(0)?
+                .map(Some, Decimal::round, |value| (value + 0.5).floor())
+                .map(Number::into_term),
+            Function::Cast(datatype) => cast(&arguments[0], datatype),
+        }
+    }
+
+    /// Compiles a regular expression with the flags of section 17.4.3.14,
+    /// caching it as the same one is usually applied to every solution.
+    fn regex(&self, pattern: &str, flags: Option<&Term>) -> Option<Regex> {
+        let flags = match flags {
+            Some(flags) => string(flags)?.0,
+            None => "",
+        };
+        let key = (pattern.to_string(), flags.to_string());
+        if let Some(regex) = self.regexes.borrow().get(&key) {
+            return regex.clone();
+        }
+        let mut builder = match flags.contains('q') {
+            true => RegexBuilder::new(&regex::escape(pattern)),
+            false => RegexBuilder::new(pattern),
+        };
+        for flag in flags.chars() {
+            match flag {
+                'i' => builder.case_insensitive(true),
+                's' => builder.dot_matches_new_line(true),
+                'm' => builder.multi_line(true),
+                'x' => builder.ignore_whitespace(true),
+                'q' => &mut builder,
+                _ => return None,
+            };
+        }
+        let regex = builder.build().ok();
+        self.regexes.borrow_mut().insert(key, regex.clone());
+        regex
+    }
+
+    /// Evaluates an aggregate over the solutions of a group.
+    pub(crate) fn aggregate(
+        &self,
+        aggregate: &Aggregate,
+        rows: &[Row],
+    ) -> Result<Option<Term>, ZarrDfError> {
+        let values =
+            |expression: &Expression, distinct: bool| -> Result<Vec<Option<Term>>, ZarrDfError> {
+                let mut values = Vec::with_capacity(rows.len());
+                for row in rows {
+                    let value = self.evaluate(expression, row)?;
+                    if !distinct || !values.contains(&value) {
+                        values.push(value);
+                    }
+                }
+                Ok(values)
+            };
+        let sum = |values: &[Option<Term>]| {
+            values.iter().try_fold(Number::Integer(0), |sum, value| {
+                sum.arithmetic(Number::from_term(value.as_ref()?)?, '+')
+            })
+        };
+        Ok(match aggregate {
+            Aggregate::Count(None, false) => Some(integer(rows.len())),
+            Aggregate::Count(None, true) => {
+                let mut distinct: Vec<&Row> = Vec::new();
+                for row in rows {
+                    if !distinct.contains(&row) {
+                        distinct.push(row);
+                    }
+                }
+                Some(integer(distinct.len()))
+            }
+            Aggregate::Count(Some(expression), distinct) => Some(integer(
+                values(expression, *distinct)?.iter().flatten().count(),
+            )),
+            Aggregate::Sum(expression, distinct) => {
+                sum(&values(expression, *distinct)?).map(Number::into_term)
+            }
+            Aggregate::Avg(expression, distinct) => {
+                let values = values(expression, *distinct)?;
+                match values.len() {
+                    0 => Some(Number::Integer(0).into_term()),
+                    len => sum(&values)
+                        .and_then(|sum| sum.arithmetic(Number::Integer(len as i64), '/'))
+                        .map(Number::into_term),
+                }
+            }
+            Aggregate::Min(expression, distinct) => values(expression, *distinct)?
+                .into_iter()
+                .min_by(|left, right| order(left.as_ref(), right.as_ref()))
+                .flatten(),
+            Aggregate::Max(expression, distinct) => values(expression, *distinct)?
+                .into_iter()
+                .max_by(|left, right| order(left.as_ref(), right.as_ref()))
+                .flatten(),
+            Aggregate::Sample(expression, _) => {
+                values(expression, false)?.into_iter().flatten().next()
+            }
+            Aggregate::GroupConcat(expression, distinct, separator) => {
+                let values = values(expression, *distinct)?;
+                let mut strings = Vec::with_capacity(values.len());
+                for value in &values {
+                    match value.as_ref().and_then(string) {
+                        Some((value, _)) => strings.push(value),
+                        None => return Ok(None),
+                    }
+                }
+                Some(string_literal(
+                    strings.join(separator.as_deref().unwrap_or(" ")),
+                    None,
+                ))
+            }
+        })
+    }
+}
+
+/// Casts a term to an XSD datatype, as in section 17.5.
+fn cast(term: &Term, datatype: &NamedNode) -> Option<Term> {
+    let value = match term {
+        Term::NamedNode(iri) if datatype.as_ref() == xsd::STRING => iri.as_str(),
+        Term::Literal(literal) => literal.value(),
+        _ => return None,
+    };
+    let number = Number::from_term(term);
+    let target = datatype.as_ref();
+    let cast = if target == xsd::STRING {
+        value.to_string()
+    } else if target == xsd::BOOLEAN {
+        match (number, value) {
+            (Some(number), _) => (number.to_f64() != 0.0).to_string(),
+            (None, "true" | "1") => "true".to_string(),
+            (None, "false" | "0") => "false".to_string(),
+            _ => return None,
+        }
+    } else if target == xsd::INTEGER {
+        match number {
+            Some(Number::Integer(value)) => value.to_string(),
+            Some(Number::Decimal(value)) => value.trunc()?.to_string(),
+            Some(number) => (number.to_f64().trunc() as i64).to_string(),
+            None if value == "true" || value == "false" => i64::from(value == "true").to_string(),
+            None => value.trim().parse::<i64>().ok()?.to_string(),
+        }
+    } else if target == xsd::DECIMAL {
+        let value = match number {
+            Some(Number::Integer(value)) => value.into(),
+            Some(Number::Decimal(value)) => value,
+            Some(number) => Decimal::from_f64(number.to_f64())?,
+            None if value == "true" || value == "false" => i64::from(value == "true").into(),
+            None => value.trim().parse().ok()?,
+        };
+        return Some(Number::Decimal(value).into_term());
+    } else if target == xsd::DOUBLE || target == xsd::FLOAT {
+        let value = match number {
+            Some(number) => number.to_f64(),
+            None if value == "true" || value == "false" => f64::from(u8::from(value == "true")),
+            None => parse_double(value.trim())?,
+        };
+        let number = match target == xsd::FLOAT {
+            true => Number::Float(value),
+            false => Number::Double(value),
+        };
+        return Some(number.into_term());
+    } else if target == xsd::DATE_TIME {
+        value.to_string()
+    } else {
+        return None;
+    };
+    Some(Literal::new_typed_literal(cast, datatype.clone()).into())
+}
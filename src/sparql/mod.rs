@@ -0,0 +1,243 @@
+//! SPARQL 1.1 queries and updates over a [`Storage`], whose basic graph
+//! patterns are evaluated with [`GraphPattern`](crate::bgp::GraphPattern) and
+//! the rest of the algebra in memory.
+
+mod algebra;
+mod decimal;
+mod eval;
+mod expression;
+mod parser;
+mod results;
+mod update;
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use oxrdf::BlankNode;
+use oxrdf::NamedNode;
+use oxrdf::Subject;
+use oxrdf::Term;
+use oxrdf::Triple;
+use oxrdf::Variable;
+use zarrs::storage::ReadableWritableStorageTraits;
+
+pub use self::algebra::Aggregate;
+pub use self::algebra::Expression;
+pub use self::algebra::Function;
+pub use self::algebra::GraphTarget;
+pub use self::algebra::OrderKey;
+pub use self::algebra::Pattern;
+pub use self::algebra::QueryForm;
+pub use self::algebra::UpdateOperation;
+use self::eval::Evaluator;
+use self::eval::Row;
+use self::parser::Parser;
+pub use self::results::ResultsFormat;
+use crate::bgp::PatternTerm;
+pub use crate::bgp::QuadPattern;
+pub use crate::bgp::QueryDataset;
+use crate::bgp::Solution;
+use crate::error::ZarrDfError;
+use crate::query::TermPattern;
+use crate::storage::Storage;
+
+/// Parsed SPARQL query.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Query {
+    pub form: QueryForm,
+    pub pattern: Pattern,
+    /// Graphs of the `FROM` and `FROM NAMED` clauses, if any.
+    pub dataset: Option<QueryDataset>,
+}
+
+impl Query {
+    /// Parses a query in SPARQL syntax, resolving relative IRIs against its
+    /// `BASE`, if any.
+    pub fn parse(query: &str) -> Result<Self, ZarrDfError> {
+        Parser::new(query)?.parse_query()
+    }
+}
+
+impl FromStr for Query {
+    type Err = ZarrDfError;
+
+    fn from_str(query: &str) -> Result<Self, Self::Err> {
+        Query::parse(query)
+    }
+}
+
+/// Parsed SPARQL update.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Update {
+    pub operations: Vec<UpdateOperation>,
+}
+
+impl Update {
+    /// Parses an update in SPARQL syntax, made of operations separated by
+    /// semicolons.
+    pub fn parse(update: &str) -> Result<Self, ZarrDfError> {
+        Parser::new(update)?.parse_update()
+    }
+}
+
+impl FromStr for Update {
+    type Err = ZarrDfError;
+
+    fn from_str(update: &str) -> Result<Self, Self::Err> {
+        Update::parse(update)
+    }
+}
+
+/// Results of a query, depending on its form.
+#[derive(Clone, Debug, PartialEq)]
+pub enum QueryResults {
+    /// Solutions of a `SELECT`, with the projected variables.
+    Solutions(Vec<Variable>, Vec<Solution>),
+    /// Answer to an `ASK`.
+    Boolean(bool),
+    /// Triples built by a `CONSTRUCT` or a `DESCRIBE`, without duplicates.
+    Graph(Vec<Triple>),
+}
+
+impl<S: ReadableWritableStorageTraits + 'static> Storage<S> {
+    /// Parses and evaluates a SPARQL query, whose default graph is the union
+    /// of all the graphs of the dataset unless it has a `FROM` clause.
+    pub fn sparql(&self, query: &str) -> Result<QueryResults, ZarrDfError> {
+        self.evaluate(&Query::parse(query)?)
+    }
+
+    /// Evaluates a parsed query, see [`Storage::sparql`].
+    pub fn evaluate(&self, query: &Query) -> Result<QueryResults, ZarrDfError> {
+        let evaluator = Evaluator::new(self, &query.pattern, query.dataset.as_ref());
+        let mut rows = evaluator.evaluate_pattern(&query.pattern);
+        match &query.form {
+            QueryForm::Select(variables) => {
+                let projection: Arc<[Variable]> = variables.clone().into();
+                let solutions = rows
+                    .map(|row| {
+                        let row = row?;
+                        let values = variables
+                            .iter()
+                            .map(|variable| evaluator.value(variable, &row).cloned())
+                            .collect();
+                        Ok(Solution::new(projection.clone(), values))
+                    })
+                    .collect::<Result<_, ZarrDfError>>()?;
+                Ok(QueryResults::Solutions(variables.clone(), solutions))
+            }
+            QueryForm::Ask => Ok(QueryResults::Boolean(rows.next().transpose()?.is_some())),
+            QueryForm::Construct(template) => {
+                let mut graph = Graph::default();
+                for row in rows {
+                    let row = row?;
+                    // Blank nodes of the template are fresh for every solution
+                    let mut blank_nodes = HashMap::new();
+                    for triple in template {
+                        let mut instantiate = |term: &PatternTerm| match term {
+                            PatternTerm::Variable(variable) => {
+                                evaluator.value(variable, &row).cloned()
+                            }
+                            PatternTerm::Term(Term::BlankNode(blank_node)) => Some(
+                                blank_nodes
+                                    .entry(blank_node.clone())
+                                    .or_insert_with(BlankNode::default)
+                                    .clone()
+                                    .into(),
+                            ),
+                            PatternTerm::Term(term) => Some(term.clone()),
+                        };
+                        let subject = instantiate(&triple.subject);
+                        let predicate = instantiate(&triple.predicate);
+                        let object = instantiate(&triple.object);
+                        if let Some(triple) = triple_from_terms(subject, predicate, object) {
+                            graph.insert(triple);
+                        }
+                    }
+                }
+                Ok(QueryResults::Graph(graph.triples))
+            }
+            QueryForm::Describe(resources) => {
+                let mut described = Vec::new();
+                let mut add = |term: &Term| {
+                    if !described.contains(term) {
+                        described.push(term.clone());
+                    }
+                };
+                let constants: Vec<_> = resources
+                    .iter()
+                    .filter_map(|resource| match resource {
+                        PatternTerm::Term(term) => Some(term),
+                        PatternTerm::Variable(_) => None,
+                    })
+                    .collect();
+                constants.into_iter().for_each(&mut add);
+                if resources
+                    .iter()
+                    .any(|resource| matches!(resource, PatternTerm::Variable(_)))
+                {
+                    for row in rows {
+                        let row: Row = row?;
+                        for resource in resources {
+                            if let PatternTerm::Variable(variable) = resource {
+                                if let Some(term) = evaluator.value(variable, &row) {
+                                    add(term);
+                                }
+                            }
+                        }
+                    }
+                }
+
+                // The description of a resource is the triples it is the
+                // subject of
+                let mut graph = Graph::default();
+                for term in described {
+                    let subject = match term {
+                        Term::NamedNode(node) => Subject::NamedNode(node),
+                        Term::BlankNode(node) => Subject::BlankNode(node),
+                        _ => continue,
+                    };
+                    for triple in self.triples_matching_terms(Some(&subject), None, None) {
+                        graph.insert(triple?);
+                    }
+                }
+                Ok(QueryResults::Graph(graph.triples))
+            }
+        }
+    }
+}
+
+/// Triples in insertion order without duplicates.
+#[derive(Default)]
+struct Graph {
+    triples: Vec<Triple>,
+    seen: HashSet<Triple>,
+}
+
+impl Graph {
+    fn insert(&mut self, triple: Triple) {
+        if self.seen.insert(triple.clone()) {
+            self.triples.push(triple);
+        }
+    }
+}
+
+/// Builds a triple, or returns `None` if a term is unbound or not allowed in
+/// its position, in which case a `CONSTRUCT` template skips it.
+fn triple_from_terms(
+    subject: Option<Term>,
+    predicate: Option<Term>,
+    object: Option<Term>,
+) -> Option<Triple> {
+    let subject = match subject? {
+        Term::NamedNode(node) => Subject::NamedNode(node),
+        Term::BlankNode(node) => Subject::BlankNode(node),
+        _ => return None,
+    };
+    let predicate: NamedNode = match predicate? {
+        Term::NamedNode(node) => node,
+        _ => return None,
+    };
+    Some(Triple::new(subject, predicate, object?))
+}
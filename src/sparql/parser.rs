@@ -0,0 +1,1610 @@
+use std::collections::HashMap;
+
+use oxiri::Iri;
+use oxrdf::vocab::rdf;
+use oxrdf::vocab::xsd;
+use oxrdf::BlankNode;
+use oxrdf::GraphName;
+use oxrdf::Literal;
+use oxrdf::NamedNode;
+use oxrdf::Quad;
+use oxrdf::Term;
+use oxrdf::Variable;
+
+use super::algebra::Aggregate;
+use super::algebra::Expression;
+use super::algebra::Function;
+use super::algebra::GraphTarget;
+use super::algebra::OrderKey;
+use super::algebra::Pattern;
+use super::algebra::QueryForm;
+use super::algebra::UpdateOperation;
+use super::triple_from_terms;
+use super::Query;
+use super::Update;
+use crate::bgp::PatternTerm;
+use crate::bgp::QuadPattern;
+use crate::bgp::QueryDataset;
+use crate::bgp::TriplePattern;
+use crate::error::ZarrDfError;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Iri(String),
+    PrefixedName(String, String),
+    BlankNode(String),
+    Variable(String),
+    String(String),
+    LangTag(String),
+    Integer(String),
+    Decimal(String),
+    Double(String),
+    Keyword(String),
+    Punctuation(&'static str),
+}
+
+/// Punctuation, with the longest symbols first so they take precedence.
+const PUNCTUATION: [&str; 26] = [
+    "^^", "<=", ">=", "!=", "&&", "||", "{", "}", "(", ")", "[", "]", ".", ",", ";", "*", "=", "<",
+    ">", "!", "+", "-", "/", "^", "|", "?",
+];
+
+/// Splits a query into tokens along with their byte offsets.
+fn tokenize(query: &str) -> Result<Vec<(Token, usize)>, ZarrDfError> {
+    let mut tokens = Vec::new();
+    let mut chars = query.char_indices().peekable();
+    while let Some(&(offset, c)) = chars.peek() {
+        let rest = &query[offset..];
+        let token = if c.is_whitespace() {
+            chars.next();
+            continue;
+        } else if c == '#' {
+            while chars.next_if(|&(_, c)| c != '\n').is_some() {}
+            continue;
+        } else if let Some(iri) = iri_ref(rest) {
+            Token::Iri(iri[1..iri.len() - 1].to_string())
+        } else if c == '"' || c == '\'' {
+            let (string, len) =
+                string(rest).ok_or_else(|| syntax("unterminated string", offset))?;
+            tokens.push((Token::String(string), offset));
+            skip(&mut chars, offset + len);
+            continue;
+        } else if (c == '?' || c == '$') && rest[1..].starts_with(is_name_char) {
+            let name = word(&rest[1..], false);
+            tokens.push((Token::Variable(name.to_string()), offset));
+            skip(&mut chars, offset + 1 + name.len());
+            continue;
+        } else if c == '@' && rest[1..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            let len = rest[1..]
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '-')
+                .unwrap_or(rest.len() - 1);
+            Token::LangTag(rest[1..1 + len].to_string())
+        } else if let Some(label) = rest.strip_prefix("_:") {
+            let label = word(label, false);
+            tokens.push((Token::BlankNode(label.to_string()), offset));
+            skip(&mut chars, offset + 2 + label.len());
+            continue;
+        } else if c.is_ascii_digit()
+            || (c == '.' && rest[1..].starts_with(|c: char| c.is_ascii_digit()))
+        {
+            number(rest)
+        } else if is_name_char(c) || c == ':' {
+            let word = word(rest, true);
+            match word.split_once(':') {
+                Some((prefix, local)) => Token::PrefixedName(prefix.to_string(), local.to_string()),
+                None => Token::Keyword(word.to_string()),
+            }
+        } else if let Some(symbol) = PUNCTUATION.iter().find(|symbol| rest.starts_with(**symbol)) {
+            Token::Punctuation(symbol)
+        } else {
+            return Err(syntax(&format!("unexpected character {c:?}"), offset));
+        };
+        let len = token_len(&token, rest);
+        tokens.push((token, offset));
+        skip(&mut chars, offset + len);
+    }
+    Ok(tokens)
+}
+
+fn skip(chars: &mut std::iter::Peekable<std::str::CharIndices>, end: usize) {
+    while chars.next_if(|&(offset, _)| offset < end).is_some() {}
+}
+
+/// Length in the query of the tokens that are not unescaped.
+fn token_len(token: &Token, rest: &str) -> usize {
+    match token {
+        Token::Iri(iri) => iri.len() + 2,
+        Token::LangTag(tag) => tag.len() + 1,
+        Token::Integer(number) | Token::Decimal(number) | Token::Double(number) => number.len(),
+        Token::Keyword(word) => word.len(),
+        Token::PrefixedName(prefix, local) => prefix.len() + 1 + local.len(),
+        Token::Punctuation(symbol) => symbol.len(),
+        _ => rest.len(),
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Returns the name at the start of `rest`, which may hold the characters of
+/// prefixed names, but never ends with a dot.
+fn word(rest: &str, prefixed: bool) -> &str {
+    let allowed =
+        |c: char| is_name_char(c) || (prefixed && matches!(c, '-' | ':' | '.' | '%' | '\\'));
+    let mut end = 0;
+    let mut escaped = false;
+    for (offset, c) in rest.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' && prefixed {
+            escaped = true;
+        } else if !allowed(c) {
+            break;
+        }
+        end = offset + c.len_utf8();
+    }
+    rest[..end].trim_end_matches('.')
+}
+
+/// Returns the IRI reference at the start of `rest`, telling it apart from
+/// the less-than operator as IRIs cannot hold spaces.
+fn iri_ref(rest: &str) -> Option<&str> {
+    if !rest.starts_with('<') {
+        return None;
+    }
+    let end = rest[1..].find(|c: char| {
+        matches!(c, '<' | '>' | '"' | '{' | '}' | '|' | '^' | '`' | '\\') || c <= ' '
+    })?;
+    rest.as_bytes()[end + 1].eq(&b'>').then(|| &rest[..end + 2])
+}
+
+fn number(rest: &str) -> Token {
+    let digits = |s: &str| s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let mut end = digits(rest);
+    let mut decimal = false;
+    if rest[end..].starts_with('.') && rest[end + 1..].starts_with(|c: char| c.is_ascii_digit()) {
+        end += 1 + digits(&rest[end + 1..]);
+        decimal = true;
+    }
+    if rest[end..].starts_with(['e', 'E']) {
+        let mut exponent = end + 1;
+        if rest[exponent..].starts_with(['+', '-']) {
+            exponent += 1;
+        }
+        let len = digits(&rest[exponent..]);
+        if len > 0 {
+            return Token::Double(rest[..exponent + len].to_string());
+        }
+    }
+    match decimal {
+        true => Token::Decimal(rest[..end].to_string()),
+        false => Token::Integer(rest[..end].to_string()),
+    }
+}
+
+/// Unescapes the string literal at the start of `rest`, returning it along
+/// with its length in the query.
+fn string(rest: &str) -> Option<(String, usize)> {
+    let quote = &rest[..1];
+    let long = quote.repeat(3);
+    let (delimiter, start) = match rest.starts_with(&long) {
+        true => (long.as_str(), 3),
+        false => (quote, 1),
+    };
+    let mut value = String::new();
+    let mut chars = rest[start..].char_indices();
+    while let Some((offset, c)) = chars.next() {
+        if rest[start + offset..].starts_with(delimiter) {
+            return Some((value, start + offset + delimiter.len()));
+        }
+        match c {
+            '\\' => {
+                let (_, escape) = chars.next()?;
+                value.push(match escape {
+                    't' => '\t',
+                    'n' => '\n',
+                    'r' => '\r',
+                    'b' => '\u{8}',
+                    'f' => '\u{c}',
+                    'u' | 'U' => {
+                        let len = if escape == 'u' { 4 } else { 8 };
+                        let hex: String = (0..len)
+                            .filter_map(|_| chars.next())
+                            .map(|(_, c)| c)
+                            .collect();
+                        char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?
+                    }
+                    escape => escape,
+                });
+            }
+            '\n' | '\r' if delimiter.len() == 1 => return None,
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+fn syntax(message: &str, offset: usize) -> ZarrDfError {
+    ZarrDfError::SparqlSyntax(format!("{message} at byte {offset}"))
+}
+
+/// Names of the built-in functions along with their minimum and maximum
+/// number of arguments.
+const FUNCTIONS: [(&str, Function, usize, usize); 30] = [
+    ("STR", Function::Str, 1, 1),
+    ("LANG", Function::Lang, 1, 1),
+    ("LANGMATCHES", Function::LangMatches, 2, 2),
+    ("DATATYPE", Function::Datatype, 1, 1),
+    ("IRI", Function::Iri, 1, 1),
+    ("URI", Function::Iri, 1, 1),
+    ("ISIRI", Function::IsIri, 1, 1),
+    ("ISURI", Function::IsIri, 1, 1),
+    ("ISBLANK", Function::IsBlank, 1, 1),
+    ("ISLITERAL", Function::IsLiteral, 1, 1),
+    ("ISNUMERIC", Function::IsNumeric, 1, 1),
+    ("SAMETERM", Function::SameTerm, 2, 2),
+    ("STRDT", Function::StrDt, 2, 2),
+    ("STRLANG", Function::StrLang, 2, 2),
+    ("STRLEN", Function::StrLen, 1, 1),
+    ("SUBSTR", Function::SubStr, 2, 3),
+    ("UCASE", Function::UCase, 1, 1),
+    ("LCASE", Function::LCase, 1, 1),
+    ("STRSTARTS", Function::StrStarts, 2, 2),
+    ("STRENDS", Function::StrEnds, 2, 2),
+    ("CONTAINS", Function::Contains, 2, 2),
+    ("STRBEFORE", Function::StrBefore, 2, 2),
+    ("STRAFTER", Function::StrAfter, 2, 2),
+    ("CONCAT", Function::Concat, 0, usize::MAX),
+    ("REPLACE", Function::Replace, 3, 4),
+    ("REGEX", Function::Regex, 2, 3),
+    ("ABS", Function::Abs, 1, 1),
+    ("CEIL", Function::Ceil, 1, 1),
+    ("FLOOR", Function::Floor, 1, 1),
+    ("ROUND", Function::Round, 1, 1),
+];
+
+const AGGREGATES: [&str; 7] = [
+    "COUNT",
+    "SUM",
+    "MIN",
+    "MAX",
+    "AVG",
+    "SAMPLE",
+    "GROUP_CONCAT",
+];
+
+/// Constructor of a binary operator of [`Expression`].
+type Binary = fn(Box<Expression>, Box<Expression>) -> Expression;
+
+/// Solution modifiers of a query, which apply in the order of the fields.
+#[derive(Default)]
+struct Modifiers {
+    group: Option<Vec<(Expression, Option<Variable>)>>,
+    having: Vec<Expression>,
+    order: Vec<OrderKey>,
+    offset: usize,
+    limit: Option<usize>,
+}
+
+/// Recursive descent parser of the SPARQL 1.1 query and update languages,
+/// translating patterns to their algebra as it goes.
+pub(crate) struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    base: Option<Iri<String>>,
+    prefixes: HashMap<String, String>,
+    /// Variables of the patterns parsed so far, which `SELECT *` projects.
+    visible: Vec<Variable>,
+    /// Aggregates of the `SELECT` being parsed, bound to hidden variables.
+    aggregates: Vec<(Variable, Aggregate)>,
+    aggregates_allowed: bool,
+    /// Whether blank nodes are parsed as such, as in a `CONSTRUCT` template,
+    /// rather than as hidden variables.
+    template: bool,
+    /// Graphs of the `FROM` and `FROM NAMED` clauses of the query.
+    dataset: Option<QueryDataset>,
+    fresh: usize,
+}
+
+impl Parser {
+    pub(crate) fn new(query: &str) -> Result<Self, ZarrDfError> {
+        Ok(Parser {
+            tokens: tokenize(query)?,
+            position: 0,
+            base: None,
+            prefixes: HashMap::new(),
+            visible: Vec::new(),
+            aggregates: Vec::new(),
+            aggregates_allowed: false,
+            template: false,
+            dataset: None,
+            fresh: 0,
+        })
+    }
+
+    pub(crate) fn parse_query(mut self) -> Result<Query, ZarrDfError> {
+        self.prologue()?;
+        let mut query = if self.keyword("SELECT") {
+            let (variables, pattern) = self.select(false)?;
+            Query {
+                form: QueryForm::Select(variables),
+                pattern,
+                dataset: None,
+            }
+        } else if self.keyword("CONSTRUCT") {
+            self.construct()?
+        } else if self.keyword("DESCRIBE") {
+            self.describe()?
+        } else if self.keyword("ASK") {
+            self.dataset()?;
+            let pattern = self.where_clause()?;
+            let pattern = self.modify(pattern)?;
+            Query {
+                form: QueryForm::Ask,
+                pattern,
+                dataset: None,
+            }
+        } else {
+            return Err(self.error("expected SELECT, CONSTRUCT, DESCRIBE or ASK"));
+        };
+        query.dataset = self.dataset.take();
+        let query = self.values_clause(query)?;
+        match self.peek() {
+            None => Ok(query),
+            Some(_) => Err(self.error("unexpected token after the query")),
+        }
+    }
+
+    pub(crate) fn parse_update(mut self) -> Result<Update, ZarrDfError> {
+        let mut operations = Vec::new();
+        loop {
+            // Every operation may declare prefixes, which also apply to the
+            // following ones
+            self.prologue()?;
+            if self.peek().is_none() {
+                break;
+            }
+            operations.push(self.update_operation()?);
+            if !self.punctuation(";") {
+                break;
+            }
+        }
+        match self.peek() {
+            None => Ok(Update { operations }),
+            Some(_) => Err(self.error("unexpected token after the update")),
+        }
+    }
+
+    fn update_operation(&mut self) -> Result<UpdateOperation, ZarrDfError> {
+        if self.keyword("LOAD") {
+            let silent = self.keyword("SILENT");
+            let source = self.iri()?;
+            let destination = match self.keyword("INTO") {
+                true => {
+                    self.expect_keyword("GRAPH")?;
+                    Some(self.iri()?)
+                }
+                false => None,
+            };
+            return Ok(UpdateOperation::Load {
+                source,
+                destination,
+                silent,
+            });
+        }
+        if self.keyword("CLEAR") {
+            // Graphs only exist through their quads, so clearing a graph
+            // never fails and SILENT changes nothing
+            self.keyword("SILENT");
+            let target = if self.keyword("GRAPH") {
+                GraphTarget::Graph(self.iri()?)
+            } else if self.keyword("DEFAULT") {
+                GraphTarget::Default
+            } else if self.keyword("NAMED") {
+                GraphTarget::Named
+            } else if self.keyword("ALL") {
+                GraphTarget::All
+            } else {
+                return Err(self.error("expected GRAPH, DEFAULT, NAMED or ALL"));
+            };
+            return Ok(UpdateOperation::Clear(target));
+        }
+        for keyword in ["DROP", "CREATE", "ADD", "MOVE", "COPY", "WITH"] {
+            if self.peek_keyword(keyword) {
+                return Err(self.unsupported(keyword));
+            }
+        }
+
+        let (delete, insert) = if self.keyword("DELETE") {
+            if self.keyword("DATA") {
+                let quads = self.quad_data(false)?;
+                return Ok(UpdateOperation::DeleteData(quads));
+            }
+            if self.keyword("WHERE") {
+                let quads = self.quad_pattern(false)?;
+                return Ok(UpdateOperation::DeleteInsert {
+                    delete: quads.clone(),
+                    insert: Vec::new(),
+                    pattern: Pattern::Bgp(quads),
+                });
+            }
+            let delete = self.quad_pattern(false)?;
+            let insert = match self.keyword("INSERT") {
+                true => self.quad_pattern(true)?,
+                false => Vec::new(),
+            };
+            (delete, insert)
+        } else if self.keyword("INSERT") {
+            if self.keyword("DATA") {
+                let quads = self.quad_data(true)?;
+                return Ok(UpdateOperation::InsertData(quads));
+            }
+            (Vec::new(), self.quad_pattern(true)?)
+        } else {
+            return Err(self.error("expected INSERT, DELETE, LOAD or CLEAR"));
+        };
+        if self.peek_keyword("USING") {
+            return Err(self.unsupported("USING"));
+        }
+        self.expect_keyword("WHERE")?;
+        let pattern = self.group_graph_pattern()?;
+        Ok(UpdateOperation::DeleteInsert {
+            delete,
+            insert,
+            pattern,
+        })
+    }
+
+    /// Parses the quads of `INSERT DATA` or `DELETE DATA`, where blank nodes
+    /// are only allowed in the former.
+    fn quad_data(&mut self, blank_nodes: bool) -> Result<Vec<Quad>, ZarrDfError> {
+        let mut quads = Vec::new();
+        for quad in self.quad_pattern(blank_nodes)? {
+            let term = |term: PatternTerm| match term {
+                PatternTerm::Term(term) => Some(term),
+                PatternTerm::Variable(_) => None,
+            };
+            let triple = quad.triple;
+            let triple = triple_from_terms(
+                term(triple.subject),
+                term(triple.predicate),
+                term(triple.object),
+            );
+            let graph = match quad.graph.map(term) {
+                None => Some(GraphName::DefaultGraph),
+                Some(Some(Term::NamedNode(graph))) => Some(graph.into()),
+                Some(_) => None,
+            };
+            match (triple, graph) {
+                (Some(triple), Some(graph)) => quads.push(triple.in_graph(graph)),
+                _ => return Err(self.error("variables are not allowed in data")),
+            }
+        }
+        Ok(quads)
+    }
+
+    /// Parses the quads of a template, made of triples and `GRAPH` blocks,
+    /// where blank nodes are only allowed if `blank_nodes` is true.
+    fn quad_pattern(&mut self, blank_nodes: bool) -> Result<Vec<QuadPattern>, ZarrDfError> {
+        self.expect("{")?;
+        self.template = true;
+        let mut quads = Vec::new();
+        while !self.punctuation("}") {
+            let (graph, triples) = if self.keyword("GRAPH") {
+                let graph = match self.variable() {
+                    Some(variable) => PatternTerm::Variable(variable),
+                    None => PatternTerm::Term(self.iri()?.into()),
+                };
+                self.expect("{")?;
+                let triples = self.triples_block()?;
+                self.expect("}")?;
+                (Some(graph), triples)
+            } else if self.triples_ahead() {
+                (None, self.triples_block()?)
+            } else {
+                return Err(self.error("expected triples or GRAPH"));
+            };
+            let blank = |term: &PatternTerm| matches!(term, PatternTerm::Term(Term::BlankNode(_)));
+            for triple in triples {
+                if !blank_nodes && (blank(&triple.subject) || blank(&triple.object)) {
+                    return Err(self.error("blank nodes are not allowed here"));
+                }
+                quads.push(QuadPattern {
+                    triple,
+                    graph: graph.clone(),
+                });
+            }
+            self.punctuation(".");
+        }
+        self.template = false;
+        Ok(quads)
+    }
+
+    fn prologue(&mut self) -> Result<(), ZarrDfError> {
+        loop {
+            if self.keyword("BASE") {
+                let iri = self.iri_ref()?;
+                self.base = Some(self.resolve(&iri)?);
+            } else if self.keyword("PREFIX") {
+                let Some(Token::PrefixedName(prefix, local)) = self.next() else {
+                    return Err(self.error("expected a prefix"));
+                };
+                if !local.is_empty() {
+                    return Err(self.error("expected a prefix"));
+                }
+                let iri = self.iri_ref()?;
+                let iri = self.resolve(&iri)?.into_inner();
+                self.prefixes.insert(prefix, iri);
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Parses the `FROM` and `FROM NAMED` clauses of a query.
+    fn dataset(&mut self) -> Result<(), ZarrDfError> {
+        while self.keyword("FROM") {
+            let named = self.keyword("NAMED");
+            let graph = self.iri()?;
+            let dataset = self.dataset.get_or_insert_with(QueryDataset::default);
+            match named {
+                true => dataset.named.push(graph),
+                false => dataset.default.push(graph),
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses what follows `SELECT`, returning the projected variables along
+    /// with the algebra of the query, where a subquery has no dataset.
+    fn select(&mut self, subquery: bool) -> Result<(Vec<Variable>, Pattern), ZarrDfError> {
+        let outer_visible = std::mem::take(&mut self.visible);
+        let outer_aggregates = std::mem::take(&mut self.aggregates);
+
+        let distinct = self.keyword("DISTINCT");
+        let reduced = !distinct && self.keyword("REDUCED");
+        let mut projection = Vec::new();
+        let mut expressions = Vec::new();
+        let all = self.punctuation("*");
+        if !all {
+            loop {
+                if let Some(variable) = self.variable() {
+                    projection.push(variable);
+                } else if self.punctuation("(") {
+                    self.aggregates_allowed = true;
+                    let expression = self.expression()?;
+                    self.aggregates_allowed = false;
+                    self.expect_keyword("AS")?;
+                    let variable = self.expect_variable()?;
+                    self.expect(")")?;
+                    expressions.push((variable.clone(), expression));
+                    projection.push(variable);
+                } else if projection.is_empty() {
+                    return Err(self.error("expected a variable, an expression or *"));
+                } else {
+                    break;
+                }
+            }
+        }
+        if !subquery {
+            self.dataset()?;
+        }
+        let pattern = self.where_clause()?;
+        let modifiers = self.solution_modifiers(true)?;
+
+        if all {
+            projection = self.visible.clone();
+        }
+        let pattern = self.apply_modifiers(
+            pattern,
+            modifiers,
+            expressions,
+            Some(&projection),
+            distinct,
+            reduced,
+        );
+
+        self.visible = outer_visible;
+        self.aggregates = outer_aggregates;
+        for variable in &projection {
+            self.see(variable);
+        }
+        Ok((projection, pattern))
+    }
+
+    fn construct(&mut self) -> Result<Query, ZarrDfError> {
+        let (template, pattern) = if self.peek() == Some(&Token::Punctuation("{")) {
+            self.expect("{")?;
+            self.template = true;
+            let template = self.triples_block()?;
+            self.template = false;
+            self.expect("}")?;
+            self.dataset()?;
+            (template, self.where_clause()?)
+        } else {
+            self.dataset()?;
+            self.expect_keyword("WHERE")?;
+            self.expect("{")?;
+            let triples = self.triples_block()?;
+            self.expect("}")?;
+            let quads = triples.iter().cloned().map(QuadPattern::from).collect();
+            (triples, Pattern::Bgp(quads))
+        };
+        let pattern = self.modify(pattern)?;
+        Ok(Query {
+            form: QueryForm::Construct(template),
+            pattern,
+            dataset: None,
+        })
+    }
+
+    fn describe(&mut self) -> Result<Query, ZarrDfError> {
+        let mut resources = Vec::new();
+        let all = self.punctuation("*");
+        if !all {
+            loop {
+                if let Some(variable) = self.variable() {
+                    resources.push(PatternTerm::Variable(variable));
+                } else if matches!(self.peek(), Some(Token::Iri(_) | Token::PrefixedName(..))) {
+                    resources.push(PatternTerm::Term(self.iri()?.into()));
+                } else if resources.is_empty() {
+                    return Err(self.error("expected a variable, an IRI or *"));
+                } else {
+                    break;
+                }
+            }
+        }
+        self.dataset()?;
+        let pattern = match self.peek() {
+            Some(Token::Punctuation("{")) => self.group_graph_pattern()?,
+            Some(Token::Keyword(keyword)) if keyword.eq_ignore_ascii_case("WHERE") => {
+                self.where_clause()?
+            }
+            _ => Pattern::Bgp(Vec::new()),
+        };
+        if all {
+            resources = self
+                .visible
+                .iter()
+                .cloned()
+                .map(PatternTerm::Variable)
+                .collect();
+        }
+        let pattern = self.modify(pattern)?;
+        Ok(Query {
+            form: QueryForm::Describe(resources),
+            pattern,
+            dataset: None,
+        })
+    }
+
+    fn where_clause(&mut self) -> Result<Pattern, ZarrDfError> {
+        self.keyword("WHERE");
+        self.group_graph_pattern()
+    }
+
+    /// Parses the solution modifiers, where aggregates are only allowed in a
+    /// `SELECT`.
+    fn solution_modifiers(&mut self, select: bool) -> Result<Modifiers, ZarrDfError> {
+        let mut modifiers = Modifiers::default();
+        self.aggregates_allowed = select;
+        if self.keyword("GROUP") {
+            self.expect_keyword("BY")?;
+            let mut keys = Vec::new();
+            loop {
+                if let Some(variable) = self.variable() {
+                    keys.push((Expression::Variable(variable), None));
+                } else if self.punctuation("(") {
+                    self.aggregates_allowed = false;
+                    let expression = self.expression()?;
+                    self.aggregates_allowed = select;
+                    let variable = match self.keyword("AS") {
+                        true => Some(self.expect_variable()?),
+                        false => None,
+                    };
+                    self.expect(")")?;
+                    keys.push((expression, variable));
+                } else if let Some(call) = self.function_call()? {
+                    keys.push((call, None));
+                } else if keys.is_empty() {
+                    return Err(self.error("expected a grouping condition"));
+                } else {
+                    break;
+                }
+            }
+            modifiers.group = Some(keys);
+        }
+        if self.keyword("HAVING") {
+            modifiers.having.push(self.constraint()?);
+            while matches!(self.peek(), Some(Token::Punctuation("("))) || self.function_call_ahead()
+            {
+                modifiers.having.push(self.constraint()?);
+            }
+        }
+        if self.keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            loop {
+                let key = if self.keyword("ASC") {
+                    OrderKey::Asc(self.bracketted()?)
+                } else if self.keyword("DESC") {
+                    OrderKey::Desc(self.bracketted()?)
+                } else if let Some(variable) = self.variable() {
+                    OrderKey::Asc(Expression::Variable(variable))
+                } else if matches!(self.peek(), Some(Token::Punctuation("(")))
+                    || self.function_call_ahead()
+                {
+                    OrderKey::Asc(self.constraint()?)
+                } else if modifiers.order.is_empty() {
+                    return Err(self.error("expected an ordering condition"));
+                } else {
+                    break;
+                };
+                modifiers.order.push(key);
+            }
+        }
+        self.aggregates_allowed = false;
+        loop {
+            if self.keyword("LIMIT") {
+                modifiers.limit = Some(self.integer()?);
+            } else if self.keyword("OFFSET") {
+                modifiers.offset = self.integer()?;
+            } else {
+                break;
+            }
+        }
+        Ok(modifiers)
+    }
+
+    /// Parses and applies the solution modifiers of a query other than a
+    /// `SELECT`.
+    fn modify(&mut self, pattern: Pattern) -> Result<Pattern, ZarrDfError> {
+        let modifiers = self.solution_modifiers(false)?;
+        if modifiers.group.is_some() || !modifiers.having.is_empty() {
+            return Err(self.error("grouping is only allowed in SELECT queries"));
+        }
+        Ok(self.apply_modifiers(pattern, modifiers, Vec::new(), None, false, false))
+    }
+
+    /// Builds the algebra of the solution modifiers as in section 18.2.4 of
+    /// the specification, where only a `SELECT` has a `projection`.
+    fn apply_modifiers(
+        &mut self,
+        mut pattern: Pattern,
+        modifiers: Modifiers,
+        expressions: Vec<(Variable, Expression)>,
+        projection: Option<&[Variable]>,
+        distinct: bool,
+        reduced: bool,
+    ) -> Pattern {
+        if modifiers.group.is_some() || !self.aggregates.is_empty() {
+            let mut keys = Vec::new();
+            for (expression, variable) in modifiers.group.unwrap_or_default() {
+                let variable = match (expression, variable) {
+                    (Expression::Variable(variable), None) => variable,
+                    (expression, variable) => {
+                        let variable = variable.unwrap_or_else(|| self.hidden("group"));
+                        pattern = Pattern::Extend(Box::new(pattern), variable.clone(), expression);
+                        variable
+                    }
+                };
+                keys.push(variable);
+            }
+            pattern = Pattern::Group {
+                pattern: Box::new(pattern),
+                keys,
+                aggregates: std::mem::take(&mut self.aggregates),
+            };
+        }
+        for having in modifiers.having {
+            pattern = Pattern::Filter(having, Box::new(pattern));
+        }
+        for (variable, expression) in expressions {
+            pattern = Pattern::Extend(Box::new(pattern), variable, expression);
+        }
+        if !modifiers.order.is_empty() {
+            pattern = Pattern::OrderBy(Box::new(pattern), modifiers.order);
+        }
+        if let Some(projection) = projection {
+            pattern = Pattern::Project(Box::new(pattern), projection.to_vec());
+        }
+        if distinct {
+            pattern = Pattern::Distinct(Box::new(pattern));
+        } else if reduced {
+            pattern = Pattern::Reduced(Box::new(pattern));
+        }
+        if modifiers.offset > 0 || modifiers.limit.is_some() {
+            pattern = Pattern::Slice {
+                pattern: Box::new(pattern),
+                offset: modifiers.offset,
+                limit: modifiers.limit,
+            };
+        }
+        pattern
+    }
+
+    /// Joins the trailing `VALUES` clause of a query with its solutions.
+    fn values_clause(&mut self, mut query: Query) -> Result<Query, ZarrDfError> {
+        if self.keyword("VALUES") {
+            let values = self.data_block()?;
+            query.pattern = Pattern::join(query.pattern, values);
+        }
+        Ok(query)
+    }
+
+    fn group_graph_pattern(&mut self) -> Result<Pattern, ZarrDfError> {
+        self.expect("{")?;
+        if self.keyword("SELECT") {
+            let (_, pattern) = self.select(true)?;
+            self.expect("}")?;
+            return Ok(pattern);
+        }
+        let mut pattern = Pattern::Bgp(Vec::new());
+        let mut filters = Vec::new();
+        loop {
+            if self.punctuation("}") {
+                break;
+            }
+            if self.triples_ahead() {
+                let triples = self.triples_block()?;
+                let quads = triples.into_iter().map(QuadPattern::from).collect();
+                pattern = Pattern::join(pattern, Pattern::Bgp(quads));
+                continue;
+            }
+            if self.keyword("OPTIONAL") {
+                let (optional, filter) = match self.group_graph_pattern()? {
+                    Pattern::Filter(filter, optional) => (*optional, Some(filter)),
+                    optional => (optional, None),
+                };
+                pattern = Pattern::LeftJoin(Box::new(pattern), Box::new(optional), filter);
+            } else if self.keyword("MINUS") {
+                let minus = self.group_graph_pattern()?;
+                pattern = Pattern::Minus(Box::new(pattern), Box::new(minus));
+            } else if self.keyword("FILTER") {
+                filters.push(self.constraint()?);
+            } else if self.keyword("BIND") {
+                self.expect("(")?;
+                let expression = self.expression()?;
+                self.expect_keyword("AS")?;
+                let variable = self.expect_variable()?;
+                self.expect(")")?;
+                pattern = Pattern::Extend(Box::new(pattern), variable, expression);
+            } else if self.keyword("VALUES") {
+                let values = self.data_block()?;
+                pattern = Pattern::join(pattern, values);
+            } else if self.peek() == Some(&Token::Punctuation("{")) {
+                let mut union = self.group_graph_pattern()?;
+                while self.keyword("UNION") {
+                    let right = self.group_graph_pattern()?;
+                    union = Pattern::Union(Box::new(union), Box::new(right));
+                }
+                pattern = Pattern::join(pattern, union);
+            } else if self.keyword("GRAPH") {
+                let graph = match self.variable() {
+                    Some(variable) => PatternTerm::Variable(variable),
+                    None => PatternTerm::Term(self.iri()?.into()),
+                };
+                let group = self.group_graph_pattern()?;
+                pattern = Pattern::join(pattern, group.in_graph(&graph));
+            } else if self.peek_keyword("SERVICE") {
+                return Err(self.unsupported("SERVICE"));
+            } else {
+                return Err(self.error("expected a graph pattern"));
+            }
+            self.punctuation(".");
+        }
+        Ok(
+            match filters
+                .into_iter()
+                .reduce(|left, right| Expression::And(Box::new(left), Box::new(right)))
+            {
+                Some(filter) => Pattern::Filter(filter, Box::new(pattern)),
+                None => pattern,
+            },
+        )
+    }
+
+    fn data_block(&mut self) -> Result<Pattern, ZarrDfError> {
+        let (variables, single) = match self.variable() {
+            Some(variable) => (vec![variable], true),
+            None => {
+                self.expect("(")?;
+                let mut variables = Vec::new();
+                while let Some(variable) = self.variable() {
+                    variables.push(variable);
+                }
+                self.expect(")")?;
+                (variables, false)
+            }
+        };
+        self.expect("{")?;
+        let mut rows = Vec::new();
+        while !self.punctuation("}") {
+            if single {
+                rows.push(vec![self.data_value()?]);
+                continue;
+            }
+            self.expect("(")?;
+            let mut row = Vec::new();
+            while !self.punctuation(")") {
+                row.push(self.data_value()?);
+            }
+            if row.len() != variables.len() {
+                return Err(self.error("wrong number of values"));
+            }
+            rows.push(row);
+        }
+        Ok(Pattern::Values(variables, rows))
+    }
+
+    fn data_value(&mut self) -> Result<Option<Term>, ZarrDfError> {
+        if self.keyword("UNDEF") {
+            return Ok(None);
+        }
+        match self.term()? {
+            PatternTerm::Term(Term::BlankNode(_)) | PatternTerm::Variable(_) => {
+                Err(self.error("expected an IRI, a literal or UNDEF"))
+            }
+            PatternTerm::Term(term) => Ok(Some(term)),
+        }
+    }
+
+    /// Whether the next token starts a triple.
+    fn triples_ahead(&self) -> bool {
+        match self.peek() {
+            Some(Token::Keyword(keyword)) => {
+                keyword.eq_ignore_ascii_case("true") || keyword.eq_ignore_ascii_case("false")
+            }
+            Some(Token::Punctuation(symbol)) => matches!(*symbol, "[" | "("),
+            Some(Token::LangTag(_)) | None => false,
+            Some(_) => true,
+        }
+    }
+
+    fn triples_block(&mut self) -> Result<Vec<TriplePattern>, ZarrDfError> {
+        let mut triples = Vec::new();
+        while self.triples_ahead() {
+            let subject = self.graph_node(&mut triples)?;
+            let bracketted = matches!(self.tokens[self.position - 1].0, Token::Punctuation("]"));
+            // A blank node property list may stand on its own
+            if !bracketted || self.verb_ahead() {
+                self.property_list(&subject, &mut triples)?;
+            }
+            if !self.punctuation(".") {
+                break;
+            }
+        }
+        Ok(triples)
+    }
+
+    fn verb_ahead(&self) -> bool {
+        match self.peek() {
+            Some(Token::Keyword(keyword)) => keyword == "a",
+            Some(Token::Iri(_) | Token::PrefixedName(..) | Token::Variable(_)) => true,
+            Some(Token::Punctuation(symbol)) => matches!(*symbol, "^" | "("),
+            _ => false,
+        }
+    }
+
+    fn property_list(
+        &mut self,
+        subject: &PatternTerm,
+        triples: &mut Vec<TriplePattern>,
+    ) -> Result<(), ZarrDfError> {
+        loop {
+            let path = self.path()?;
+            loop {
+                let object = self.graph_node(triples)?;
+                self.path_triples(subject.clone(), &path, object, triples);
+                if !self.punctuation(",") {
+                    break;
+                }
+            }
+            let mut separated = false;
+            while self.punctuation(";") {
+                separated = true;
+            }
+            if !separated || !self.verb_ahead() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Parses a verb, which may be a sequence of possibly inverse IRIs.
+    fn path(&mut self) -> Result<Vec<(bool, PatternTerm)>, ZarrDfError> {
+        if let Some(variable) = self.variable() {
+            return Ok(vec![(false, PatternTerm::Variable(variable))]);
+        }
+        let mut path = Vec::new();
+        loop {
+            let inverse = self.punctuation("^");
+            let iri = if self.keyword("a") {
+                rdf::TYPE.into_owned()
+            } else if matches!(self.peek(), Some(Token::Iri(_) | Token::PrefixedName(..))) {
+                self.iri()?
+            } else {
+                return Err(self.unsupported("this property path"));
+            };
+            path.push((inverse, PatternTerm::Term(iri.into())));
+            if !self.punctuation("/") {
+                break;
+            }
+        }
+        if matches!(self.peek(), Some(Token::Punctuation("|" | "*" | "?"))) {
+            return Err(self.unsupported("this property path"));
+        }
+        Ok(path)
+    }
+
+    /// Adds the triples linking `subject` to `object` through `path`, joining
+    /// its steps with hidden variables.
+    fn path_triples(
+        &mut self,
+        subject: PatternTerm,
+        path: &[(bool, PatternTerm)],
+        object: PatternTerm,
+        triples: &mut Vec<TriplePattern>,
+    ) {
+        let mut start = subject;
+        for (i, (inverse, predicate)) in path.iter().enumerate() {
+            let end = match i + 1 == path.len() {
+                true => object.clone(),
+                false => PatternTerm::Variable(self.hidden("path")),
+            };
+            triples.push(match inverse {
+                true => TriplePattern::new(end.clone(), predicate.clone(), start),
+                false => TriplePattern::new(start, predicate.clone(), end.clone()),
+            });
+            start = end;
+        }
+    }
+
+    /// Parses a subject or an object, adding the triples of the blank node
+    /// property lists and collections it contains.
+    fn graph_node(&mut self, triples: &mut Vec<TriplePattern>) -> Result<PatternTerm, ZarrDfError> {
+        if self.punctuation("[") {
+            let node = self.blank_node(None);
+            if !self.punctuation("]") {
+                self.property_list(&node, triples)?;
+                self.expect("]")?;
+            }
+            return Ok(node);
+        }
+        if self.peek() == Some(&Token::Punctuation("("))
+            && self.tokens.get(self.position + 1).map(|t| &t.0) != Some(&Token::Punctuation(")"))
+        {
+            self.expect("(")?;
+            let mut items = Vec::new();
+            while !self.punctuation(")") {
+                items.push(self.graph_node(triples)?);
+            }
+            let mut list = PatternTerm::Term(rdf::NIL.into_owned().into());
+            for item in items.into_iter().rev() {
+                let node = self.blank_node(None);
+                triples.push(TriplePattern::new(
+                    node.clone(),
+                    rdf::FIRST.into_owned(),
+                    item,
+                ));
+                triples.push(TriplePattern::new(
+                    node.clone(),
+                    rdf::REST.into_owned(),
+                    list,
+                ));
+                list = node;
+            }
+            return Ok(list);
+        }
+        self.term()
+    }
+
+    /// Returns a blank node, which is a hidden variable outside of templates.
+    fn blank_node(&mut self, label: Option<&str>) -> PatternTerm {
+        match (self.template, label) {
+            (true, Some(label)) => PatternTerm::Term(BlankNode::new_unchecked(label).into()),
+            (true, None) => PatternTerm::Term(BlankNode::default().into()),
+            (false, Some(label)) => {
+                PatternTerm::Variable(Variable::new_unchecked(format!("#{label}")))
+            }
+            (false, None) => PatternTerm::Variable(self.hidden("blank")),
+        }
+    }
+
+    /// Returns a fresh variable that cannot clash with those of the query, as
+    /// variable names cannot hold `#`.
+    fn hidden(&mut self, kind: &str) -> Variable {
+        self.fresh += 1;
+        Variable::new_unchecked(format!("#{kind}{}", self.fresh))
+    }
+
+    /// Parses a variable, an IRI, a literal or a blank node.
+    fn term(&mut self) -> Result<PatternTerm, ZarrDfError> {
+        if let Some(variable) = self.variable() {
+            return Ok(PatternTerm::Variable(variable));
+        }
+        let negative = match self.peek() {
+            Some(Token::Punctuation("-")) => true,
+            Some(Token::Punctuation("+")) => false,
+            _ => return self.unsigned_term(),
+        };
+        self.next();
+        match self.unsigned_term()? {
+            PatternTerm::Term(Term::Literal(literal))
+                if is_numeric(literal.datatype().as_str()) =>
+            {
+                let sign = if negative { "-" } else { "+" };
+                let value = format!("{sign}{}", literal.value());
+                Ok(PatternTerm::Term(
+                    Literal::new_typed_literal(value, literal.datatype().into_owned()).into(),
+                ))
+            }
+            _ => Err(self.error("expected a number")),
+        }
+    }
+
+    fn unsigned_term(&mut self) -> Result<PatternTerm, ZarrDfError> {
+        let term: Term = match self.peek().cloned() {
+            Some(Token::Iri(_) | Token::PrefixedName(..)) => self.iri()?.into(),
+            Some(Token::BlankNode(label)) => {
+                self.next();
+                return Ok(self.blank_node(Some(&label)));
+            }
+            Some(Token::Punctuation("["))
+                if self.tokens.get(self.position + 1).map(|t| &t.0)
+                    == Some(&Token::Punctuation("]")) =>
+            {
+                self.position += 2;
+                return Ok(self.blank_node(None));
+            }
+            Some(Token::Punctuation("("))
+                if self.tokens.get(self.position + 1).map(|t| &t.0)
+                    == Some(&Token::Punctuation(")")) =>
+            {
+                self.position += 2;
+                rdf::NIL.into_owned().into()
+            }
+            Some(Token::String(_)) => self.literal()?.into(),
+            Some(Token::Integer(value)) => {
+                self.next();
+                Literal::new_typed_literal(value, xsd::INTEGER).into()
+            }
+            Some(Token::Decimal(value)) => {
+                self.next();
+                Literal::new_typed_literal(value, xsd::DECIMAL).into()
+            }
+            Some(Token::Double(value)) => {
+                self.next();
+                Literal::new_typed_literal(value, xsd::DOUBLE).into()
+            }
+            Some(Token::Keyword(keyword))
+                if keyword.eq_ignore_ascii_case("true")
+                    || keyword.eq_ignore_ascii_case("false") =>
+            {
+                self.next();
+                Literal::new_typed_literal(keyword.to_ascii_lowercase(), xsd::BOOLEAN).into()
+            }
+            _ => return Err(self.error("expected an RDF term")),
+        };
+        Ok(PatternTerm::Term(term))
+    }
+
+    fn literal(&mut self) -> Result<Literal, ZarrDfError> {
+        let Some(Token::String(value)) = self.next() else {
+            return Err(self.error("expected a string"));
+        };
+        if let Some(Token::LangTag(language)) = self.peek().cloned() {
+            self.next();
+            return Literal::new_language_tagged_literal(value, language)
+                .map_err(|error| self.error(&error.to_string()));
+        }
+        if self.punctuation("^^") {
+            return Ok(Literal::new_typed_literal(value, self.iri()?));
+        }
+        Ok(Literal::new_simple_literal(value))
+    }
+
+    fn iri(&mut self) -> Result<NamedNode, ZarrDfError> {
+        let iri = match self.next() {
+            Some(Token::Iri(iri)) => self.resolve(&iri)?.into_inner(),
+            Some(Token::PrefixedName(prefix, local)) => {
+                let namespace = self
+                    .prefixes
+                    .get(&prefix)
+                    .ok_or_else(|| self.error(&format!("undefined prefix {prefix}:")))?;
+                let mut iri = namespace.clone();
+                let mut chars = local.chars();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => iri.extend(chars.next()),
+                        c => iri.push(c),
+                    }
+                }
+                iri
+            }
+            _ => return Err(self.error("expected an IRI")),
+        };
+        NamedNode::new(iri).map_err(|error| self.error(&error.to_string()))
+    }
+
+    fn iri_ref(&mut self) -> Result<String, ZarrDfError> {
+        match self.next() {
+            Some(Token::Iri(iri)) => Ok(iri),
+            _ => Err(self.error("expected an IRI")),
+        }
+    }
+
+    /// Resolves an IRI against the base IRI, if any.
+    fn resolve(&self, iri: &str) -> Result<Iri<String>, ZarrDfError> {
+        let iri = match &self.base {
+            Some(base) => base.resolve(iri),
+            None => Iri::parse(iri.to_string()),
+        };
+        iri.map_err(|error| self.error(&error.to_string()))
+    }
+
+    fn integer(&mut self) -> Result<usize, ZarrDfError> {
+        match self.next() {
+            Some(Token::Integer(value)) => {
+                value.parse().map_err(|_| self.error("integer too large"))
+            }
+            _ => Err(self.error("expected an integer")),
+        }
+    }
+
+    /// Parses the expression of a `FILTER`, a `HAVING` or an `ORDER BY`.
+    fn constraint(&mut self) -> Result<Expression, ZarrDfError> {
+        match self.peek() {
+            Some(Token::Punctuation("(")) => self.bracketted(),
+            _ => self
+                .function_call()?
+                .ok_or_else(|| self.error("expected a constraint")),
+        }
+    }
+
+    fn bracketted(&mut self) -> Result<Expression, ZarrDfError> {
+        self.expect("(")?;
+        let expression = self.expression()?;
+        self.expect(")")?;
+        Ok(expression)
+    }
+
+    fn expression(&mut self) -> Result<Expression, ZarrDfError> {
+        let mut left = self.conjunction()?;
+        while self.punctuation("||") {
+            left = Expression::Or(Box::new(left), Box::new(self.conjunction()?));
+        }
+        Ok(left)
+    }
+
+    fn conjunction(&mut self) -> Result<Expression, ZarrDfError> {
+        let mut left = self.relational()?;
+        while self.punctuation("&&") {
+            left = Expression::And(Box::new(left), Box::new(self.relational()?));
+        }
+        Ok(left)
+    }
+
+    fn relational(&mut self) -> Result<Expression, ZarrDfError> {
+        let left = self.additive()?;
+        let operators: [(&str, Binary); 5] = [
+            ("=", Expression::Equal),
+            ("<", Expression::Less),
+            (">", Expression::Greater),
+            ("<=", Expression::LessOrEqual),
+            (">=", Expression::GreaterOrEqual),
+        ];
+        for (symbol, operator) in operators {
+            if self.punctuation(symbol) {
+                return Ok(operator(Box::new(left), Box::new(self.additive()?)));
+            }
+        }
+        if self.punctuation("!=") {
+            let equal = Expression::Equal(Box::new(left), Box::new(self.additive()?));
+            return Ok(Expression::Not(Box::new(equal)));
+        }
+        if self.keyword("IN") {
+            return Ok(Expression::In(Box::new(left), self.arguments()?));
+        }
+        if self.peek_keyword("NOT")
+            && matches!(self.tokens.get(self.position + 1), Some((Token::Keyword(k), _)) if k.eq_ignore_ascii_case("IN"))
+        {
+            self.position += 2;
+            let values = self.arguments()?;
+            return Ok(Expression::Not(Box::new(Expression::In(
+                Box::new(left),
+                values,
+            ))));
+        }
+        Ok(left)
+    }
+
+    fn additive(&mut self) -> Result<Expression, ZarrDfError> {
+        let mut left = self.multiplicative()?;
+        loop {
+            if self.punctuation("+") {
+                left = Expression::Add(Box::new(left), Box::new(self.multiplicative()?));
+            } else if self.punctuation("-") {
+                left = Expression::Subtract(Box::new(left), Box::new(self.multiplicative()?));
+            } else {
+                return Ok(left);
+            }
+        }
+    }
+
+    fn multiplicative(&mut self) -> Result<Expression, ZarrDfError> {
+        let mut left = self.unary()?;
+        loop {
+            if self.punctuation("*") {
+                left = Expression::Multiply(Box::new(left), Box::new(self.unary()?));
+            } else if self.punctuation("/") {
+                left = Expression::Divide(Box::new(left), Box::new(self.unary()?));
+            } else {
+                return Ok(left);
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expression, ZarrDfError> {
+        if self.punctuation("!") {
+            Ok(Expression::Not(Box::new(self.unary()?)))
+        } else if self.punctuation("-") {
+            Ok(Expression::UnaryMinus(Box::new(self.unary()?)))
+        } else if self.punctuation("+") {
+            self.unary()
+        } else {
+            self.primary()
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expression, ZarrDfError> {
+        if self.peek() == Some(&Token::Punctuation("(")) {
+            return self.bracketted();
+        }
+        if let Some(variable) = self.variable() {
+            return Ok(Expression::Variable(variable));
+        }
+        if let Some(call) = self.function_call()? {
+            return Ok(call);
+        }
+        match self.unsigned_term()? {
+            PatternTerm::Term(Term::BlankNode(_)) | PatternTerm::Variable(_) => {
+                Err(self.error("blank nodes are not allowed in expressions"))
+            }
+            PatternTerm::Term(term) => Ok(Expression::Term(term)),
+        }
+    }
+
+    fn function_call_ahead(&self) -> bool {
+        match self.peek() {
+            Some(Token::Keyword(keyword)) => {
+                let keyword = keyword.to_ascii_uppercase();
+                FUNCTIONS.iter().any(|(name, ..)| *name == keyword)
+                    || AGGREGATES.contains(&keyword.as_str())
+                    || ["BOUND", "IF", "COALESCE", "EXISTS", "NOT"].contains(&keyword.as_str())
+            }
+            Some(Token::Iri(_) | Token::PrefixedName(..)) => {
+                self.tokens.get(self.position + 1).map(|t| &t.0) == Some(&Token::Punctuation("("))
+            }
+            _ => false,
+        }
+    }
+
+    /// Parses a call to a built-in function, an aggregate or a cast, if the
+    /// next token starts one.
+    fn function_call(&mut self) -> Result<Option<Expression>, ZarrDfError> {
+        if !self.function_call_ahead() {
+            return Ok(None);
+        }
+        if let Some(Token::Iri(_) | Token::PrefixedName(..)) = self.peek() {
+            let datatype = self.iri()?;
+            if !is_cast(datatype.as_str()) {
+                return Err(self.unsupported(&format!("the function {datatype}")));
+            }
+            let arguments = self.arguments()?;
+            if arguments.len() != 1 {
+                return Err(self.error("a cast takes a single argument"));
+            }
+            return Ok(Some(Expression::FunctionCall(
+                Function::Cast(datatype),
+                arguments,
+            )));
+        }
+        let Some(Token::Keyword(keyword)) = self.next() else {
+            unreachable!("checked by function_call_ahead");
+        };
+        let name = keyword.to_ascii_uppercase();
+        let expression = match name.as_str() {
+            "BOUND" => {
+                self.expect("(")?;
+                let variable = self.expect_variable()?;
+                self.expect(")")?;
+                Expression::Bound(variable)
+            }
+            "IF" => {
+                let mut arguments = self.arguments()?.into_iter();
+                let (Some(condition), Some(then), Some(otherwise), None) = (
+                    arguments.next(),
+                    arguments.next(),
+                    arguments.next(),
+                    arguments.next(),
+                ) else {
+                    return Err(self.error("IF takes three arguments"));
+                };
+                Expression::If(Box::new(condition), Box::new(then), Box::new(otherwise))
+            }
+            "COALESCE" => Expression::Coalesce(self.arguments()?),
+            "EXISTS" => Expression::Exists(Box::new(self.group_graph_pattern()?)),
+            "NOT" => {
+                self.expect_keyword("EXISTS")?;
+                let exists = Expression::Exists(Box::new(self.group_graph_pattern()?));
+                Expression::Not(Box::new(exists))
+            }
+            name if AGGREGATES.contains(&name) => self.aggregate(name)?,
+            name => {
+                let (_, function, min, max) = FUNCTIONS
+                    .iter()
+                    .find(|(function, ..)| *function == name)
+                    .cloned()
+                    .expect("checked by function_call_ahead");
+                let arguments = self.arguments()?;
+                if arguments.len() < min || arguments.len() > max {
+                    return Err(self.error(&format!("wrong number of arguments for {name}")));
+                }
+                Expression::FunctionCall(function, arguments)
+            }
+        };
+        Ok(Some(expression))
+    }
+
+    /// Parses an aggregate, which is replaced by the hidden variable it will
+    /// be bound to once the solutions are grouped.
+    fn aggregate(&mut self, name: &str) -> Result<Expression, ZarrDfError> {
+        if !self.aggregates_allowed {
+            return Err(self.error("aggregates are only allowed in SELECT, HAVING and ORDER BY"));
+        }
+        self.expect("(")?;
+        self.aggregates_allowed = false;
+        let distinct = self.keyword("DISTINCT");
+        let aggregate = if name == "COUNT" && self.punctuation("*") {
+            Aggregate::Count(None, distinct)
+        } else {
+            let expression = Box::new(self.expression()?);
+            match name {
+                "COUNT" => Aggregate::Count(Some(expression), distinct),
+                "SUM" => Aggregate::Sum(expression, distinct),
+                "MIN" => Aggregate::Min(expression, distinct),
+                "MAX" => Aggregate::Max(expression, distinct),
+                "AVG" => Aggregate::Avg(expression, distinct),
+                "SAMPLE" => Aggregate::Sample(expression, distinct),
+                _ => {
+                    let separator = match self.punctuation(";") {
+                        true => {
+                            self.expect_keyword("SEPARATOR")?;
+                            self.expect("=")?;
+                            match self.next() {
+                                Some(Token::String(separator)) => Some(separator),
+                                _ => return Err(self.error("expected a string")),
+                            }
+                        }
+                        false => None,
+                    };
+                    Aggregate::GroupConcat(expression, distinct, separator)
+                }
+            }
+        };
+        self.aggregates_allowed = true;
+        self.expect(")")?;
+        let variable = self.hidden("aggregate");
+        self.aggregates.push((variable.clone(), aggregate));
+        Ok(Expression::Variable(variable))
+    }
+
+    fn arguments(&mut self) -> Result<Vec<Expression>, ZarrDfError> {
+        self.expect("(")?;
+        let mut arguments = Vec::new();
+        if self.punctuation(")") {
+            return Ok(arguments);
+        }
+        loop {
+            arguments.push(self.expression()?);
+            if self.punctuation(")") {
+                return Ok(arguments);
+            }
+            self.expect(",")?;
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        self.position += 1;
+        token
+    }
+
+    fn variable(&mut self) -> Option<Variable> {
+        let Some(Token::Variable(name)) = self.peek() else {
+            return None;
+        };
+        let variable = Variable::new_unchecked(name.clone());
+        self.position += 1;
+        self.see(&variable);
+        Some(variable)
+    }
+
+    fn expect_variable(&mut self) -> Result<Variable, ZarrDfError> {
+        self.variable()
+            .ok_or_else(|| self.error("expected a variable"))
+    }
+
+    /// Records a variable for `SELECT *`.
+    fn see(&mut self, variable: &Variable) {
+        if !self.visible.contains(variable) {
+            self.visible.push(variable.clone());
+        }
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Keyword(k)) if k.eq_ignore_ascii_case(keyword) && (keyword != "a" || k == "a"))
+    }
+
+    /// Consumes the next token if it is the given keyword, regardless of
+    /// case except for `a`.
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let found = self.peek_keyword(keyword);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), ZarrDfError> {
+        match self.keyword(keyword) {
+            true => Ok(()),
+            false => Err(self.error(&format!("expected {keyword}"))),
+        }
+    }
+
+    fn punctuation(&mut self, symbol: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Punctuation(s)) if *s == symbol);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<(), ZarrDfError> {
+        match self.punctuation(symbol) {
+            true => Ok(()),
+            false => Err(self.error(&format!("expected {symbol}"))),
+        }
+    }
+
+    fn error(&self, message: &str) -> ZarrDfError {
+        let offset = self
+            .tokens
+            .get(self.position.min(self.tokens.len()))
+            .or(self.tokens.last())
+            .map_or(0, |(_, offset)| *offset);
+        syntax(message, offset)
+    }
+
+    fn unsupported(&self, feature: &str) -> ZarrDfError {
+        ZarrDfError::UnsupportedQuery(feature.to_string())
+    }
+}
+
+fn is_numeric(datatype: &str) -> bool {
+    [xsd::INTEGER, xsd::DECIMAL, xsd::DOUBLE, xsd::FLOAT]
+        .iter()
+        .any(|numeric| numeric.as_str() == datatype)
+}
+
+fn is_cast(datatype: &str) -> bool {
+    [xsd::STRING, xsd::BOOLEAN, xsd::DATE_TIME]
+        .iter()
+        .any(|cast| cast.as_str() == datatype)
+        || is_numeric(datatype)
+}
@@ -0,0 +1,244 @@
+use std::io::Write;
+use std::str::FromStr;
+
+use oxrdf::vocab::xsd;
+use oxrdf::Term;
+use oxrdf::Variable;
+use oxrdfio::RdfSerializer;
+use serde_json::json;
+use serde_json::Map;
+use serde_json::Value;
+
+use super::QueryResults;
+use crate::bgp::Solution;
+use crate::error::ZarrDfError;
+use crate::rdf_format::RdfFormat;
+
+/// Syntaxes of the results of `SELECT` and `ASK` queries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResultsFormat {
+    Json,
+    Xml,
+    /// Values only, losing the kind of terms and the datatype of literals.
+    Csv,
+    Tsv,
+}
+
+impl ResultsFormat {
+    /// Returns the format of a media type, ignoring its parameters.
+    pub fn from_media_type(media_type: &str) -> Option<Self> {
+        let media_type = media_type.split(';').next()?.trim().to_ascii_lowercase();
+        match media_type.as_str() {
+            "application/sparql-results+json" | "application/json" => Some(ResultsFormat::Json),
+            "application/sparql-results+xml" | "application/xml" | "text/xml" => {
+                Some(ResultsFormat::Xml)
+            }
+            "text/csv" => Some(ResultsFormat::Csv),
+            "text/tab-separated-values" => Some(ResultsFormat::Tsv),
+            _ => None,
+        }
+    }
+
+    /// IANA media type of the format.
+    pub fn media_type(self) -> &'static str {
+        match self {
+            ResultsFormat::Json => "application/sparql-results+json",
+            ResultsFormat::Xml => "application/sparql-results+xml",
+            ResultsFormat::Csv => "text/csv",
+            ResultsFormat::Tsv => "text/tab-separated-values",
+        }
+    }
+
+    /// Whether the format can represent the answer to an `ASK`.
+    pub fn supports_boolean(self) -> bool {
+        matches!(self, ResultsFormat::Json | ResultsFormat::Xml)
+    }
+}
+
+impl FromStr for ResultsFormat {
+    type Err = ZarrDfError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" | "srj" => Ok(ResultsFormat::Json),
+            "xml" | "srx" => Ok(ResultsFormat::Xml),
+            "csv" => Ok(ResultsFormat::Csv),
+            "tsv" => Ok(ResultsFormat::Tsv),
+            _ => Err(ZarrDfError::UnknownFormat(s.to_string())),
+        }
+    }
+}
+
+impl QueryResults {
+    /// Serializes solutions or a boolean into `writer`, returning it once done.
+    pub fn write<W: Write>(&self, mut writer: W, format: ResultsFormat) -> Result<W, ZarrDfError> {
+        match (self, format) {
+            (QueryResults::Solutions(variables, solutions), ResultsFormat::Json) => {
+                let head =
+                    json!({ "vars": variables.iter().map(Variable::as_str).collect::<Vec<_>>() });
+                let bindings: Vec<Value> = solutions
+                    .iter()
+                    .map(|solution| {
+                        let binding: Map<String, Value> = solution
+                            .iter()
+                            .map(|(variable, value)| {
+                                (variable.as_str().to_string(), json_term(value))
+                            })
+                            .collect();
+                        Value::Object(binding)
+                    })
+                    .collect();
+                let results = json!({ "head": head, "results": { "bindings": bindings } });
+                serde_json::to_writer(&mut writer, &results)?;
+            }
+            (QueryResults::Boolean(value), ResultsFormat::Json) => {
+                serde_json::to_writer(&mut writer, &json!({ "head": {}, "boolean": value }))?;
+            }
+            (QueryResults::Solutions(variables, solutions), ResultsFormat::Xml) => {
+                write_xml_head(&mut writer, variables)?;
+                writeln!(writer, "<results>")?;
+                for solution in solutions {
+                    writeln!(writer, "<result>")?;
+                    for (variable, value) in solution.iter() {
+                        write!(writer, "<binding name=\"{}\">", variable.as_str())?;
+                        write_xml_term(&mut writer, value)?;
+                        writeln!(writer, "</binding>")?;
+                    }
+                    writeln!(writer, "</result>")?;
+                }
+                writeln!(writer, "</results>\n</sparql>")?;
+            }
+            (QueryResults::Boolean(value), ResultsFormat::Xml) => {
+                write_xml_head(&mut writer, &[])?;
+                writeln!(writer, "<boolean>{value}</boolean>\n</sparql>")?;
+            }
+            (QueryResults::Solutions(variables, solutions), ResultsFormat::Csv) => {
+                let header: Vec<_> = variables.iter().map(Variable::as_str).collect();
+                write!(writer, "{}\r\n", header.join(","))?;
+                for solution in solutions {
+                    let row: Vec<_> = values(variables, solution).map(csv_term).collect();
+                    write!(writer, "{}\r\n", row.join(","))?;
+                }
+            }
+            (QueryResults::Solutions(variables, solutions), ResultsFormat::Tsv) => {
+                let header: Vec<_> = variables.iter().map(Variable::to_string).collect();
+                writeln!(writer, "{}", header.join("\t"))?;
+                for solution in solutions {
+                    let row: Vec<_> = values(variables, solution)
+                        .map(|value| value.map(Term::to_string).unwrap_or_default())
+                        .collect();
+                    writeln!(writer, "{}", row.join("\t"))?;
+                }
+            }
+            (QueryResults::Boolean(_) | QueryResults::Graph(_), _) => {
+                return Err(ZarrDfError::IncompatibleFormat(
+                    format.media_type().to_string(),
+                ))
+            }
+        }
+        Ok(writer)
+    }
+
+    /// Serializes the triples of a `CONSTRUCT` or a `DESCRIBE` into `writer`,
+    /// returning it once done.
+    pub fn write_graph<W: Write>(&self, writer: W, format: RdfFormat) -> Result<W, ZarrDfError> {
+        let QueryResults::Graph(triples) = self else {
+            return Err(ZarrDfError::IncompatibleFormat(
+                format.media_type().to_string(),
+            ));
+        };
+        let mut serializer = RdfSerializer::from_format(format.into()).for_writer(writer);
+        for triple in triples {
+            serializer.serialize_triple(triple)?;
+        }
+        Ok(serializer.finish()?)
+    }
+}
+
+/// Values of the variables in a solution, in the order of `variables`.
+fn values<'a>(
+    variables: &'a [Variable],
+    solution: &'a Solution,
+) -> impl Iterator<Item = Option<&'a Term>> {
+    variables
+        .iter()
+        .map(|variable| solution.get(variable.as_str()))
+}
+
+fn json_term(term: &Term) -> Value {
+    match term {
+        Term::NamedNode(node) => json!({ "type": "uri", "value": node.as_str() }),
+        Term::BlankNode(node) => json!({ "type": "bnode", "value": node.as_str() }),
+        Term::Literal(literal) => {
+            let mut value = json!({ "type": "literal", "value": literal.value() });
+            if let Some(language) = literal.language() {
+                value["xml:lang"] = language.into();
+            } else if literal.datatype() != xsd::STRING {
+                value["datatype"] = literal.datatype().as_str().into();
+            }
+            value
+        }
+    }
+}
+
+fn write_xml_head<W: Write>(writer: &mut W, variables: &[Variable]) -> Result<(), ZarrDfError> {
+    writeln!(writer, "<?xml version=\"1.0\"?>")?;
+    writeln!(
+        writer,
+        "<sparql xmlns=\"http://www.w3.org/2005/sparql-results#\">"
+    )?;
+    writeln!(writer, "<head>")?;
+    for variable in variables {
+        writeln!(writer, "<variable name=\"{}\"/>", variable.as_str())?;
+    }
+    writeln!(writer, "</head>")?;
+    Ok(())
+}
+
+fn write_xml_term<W: Write>(writer: &mut W, term: &Term) -> Result<(), ZarrDfError> {
+    match term {
+        Term::NamedNode(node) => write!(writer, "<uri>{}</uri>", escape_xml(node.as_str()))?,
+        Term::BlankNode(node) => write!(writer, "<bnode>{}</bnode>", escape_xml(node.as_str()))?,
+        Term::Literal(literal) => {
+            let value = escape_xml(literal.value());
+            if let Some(language) = literal.language() {
+                write!(writer, "<literal xml:lang=\"{language}\">{value}</literal>")?;
+            } else if literal.datatype() != xsd::STRING {
+                let datatype = escape_xml(literal.datatype().as_str());
+                write!(writer, "<literal datatype=\"{datatype}\">{value}</literal>")?;
+            } else {
+                write!(writer, "<literal>{value}</literal>")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Formats a value as a CSV field, quoting it when needed.
+fn csv_term(term: Option<&Term>) -> String {
+    let value = match term {
+        Some(Term::NamedNode(node)) => node.as_str().to_string(),
+        Some(Term::BlankNode(node)) => format!("_:{}", node.as_str()),
+        Some(Term::Literal(literal)) => literal.value().to_string(),
+        None => String::new(),
+    };
+    if value.contains(['"', ',', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
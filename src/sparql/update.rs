@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+
+use oxrdf::BlankNode;
+use oxrdf::GraphName;
+use oxrdf::Quad;
+use oxrdf::Subject;
+use oxrdf::Term;
+use oxrdf::Variable;
+use zarrs::storage::ReadableWritableStorageTraits;
+
+use super::algebra::GraphTarget;
+use super::algebra::UpdateOperation;
+use super::eval::Evaluator;
+use super::triple_from_terms;
+use super::Update;
+use crate::bgp::PatternTerm;
+use crate::bgp::QuadPattern;
+use crate::config::Config;
+use crate::error::ZarrDfError;
+use crate::query::TermPattern;
+use crate::storage::Storage;
+use crate::utils::serialize_graph_name;
+use crate::utils::serialize_term;
+
+impl<S: ReadableWritableStorageTraits + 'static> Storage<S> {
+    /// Parses and applies a SPARQL update, see [`update`](Storage::update).
+    pub fn sparql_update(&mut self, update: &str) -> Result<(), ZarrDfError> {
+        self.update(&Update::parse(update)?)
+    }
+
+    /// Applies the operations of a parsed update in order, each one seeing
+    /// the changes of the previous ones.
+    ///
+    /// Changes are written as deltas, like by [`append`](Storage::append) and
+    /// [`delete`](Storage::delete), and committed at once when every
+    /// operation succeeded, so a failing update leaves the store as it was.
+    /// Patterns match the union of all the graphs, like in queries, so
+    /// templates without `GRAPH` remove triples from every graph holding
+    /// them, while they insert into the default graph. The deltas are encoded
+    /// like the arrays of the store.
+    pub fn update(&mut self, update: &Update) -> Result<(), ZarrDfError> {
+        let config = self.config()?;
+        self.transaction(|storage| {
+            for operation in &update.operations {
+                let committed = storage.deltas.len();
+                match storage.apply(operation, &config) {
+                    // A silent load that fails changes nothing
+                    Err(_) if matches!(operation, UpdateOperation::Load { silent: true, .. }) => {
+                        storage.discard_deltas(committed);
+                    }
+                    result => result?,
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn apply(&mut self, operation: &UpdateOperation, config: &Config) -> Result<(), ZarrDfError> {
+        match operation {
+            UpdateOperation::InsertData(quads) => {
+                let mut blank_nodes = HashMap::new();
+                let quads = quads
+                    .iter()
+                    .map(|quad| fresh_blank_nodes(quad.clone(), &mut blank_nodes))
+                    .collect();
+                self.insert_quads(quads, config)
+            }
+            UpdateOperation::DeleteData(quads) => {
+                self.delete_quads(quads.iter().cloned(), config)?;
+                Ok(())
+            }
+            UpdateOperation::DeleteInsert {
+                delete,
+                insert,
+                pattern,
+            } => {
+                // Every solution is found before anything changes
+                let mut deletions = Vec::new();
+                let mut insertions = Vec::new();
+                let evaluator = Evaluator::new(self, pattern, None);
+                for row in evaluator.evaluate_pattern(pattern) {
+                    let row = row?;
+                    for quad in delete {
+                        let Some(deleted) =
+                            instantiate(quad, |variable| evaluator.value(variable, &row))
+                        else {
+                            continue;
+                        };
+                        if quad.graph.is_some() {
+                            deletions.push(deleted);
+                            continue;
+                        }
+                        let stored = self.quads_matching_terms(
+                            Some(&deleted.subject),
+                            Some(&deleted.predicate),
+                            Some(&deleted.object),
+                            None,
+                        );
+                        for quad in stored {
+                            deletions.push(quad?);
+                        }
+                    }
+                    // Blank nodes of the template are fresh for every solution
+                    let mut blank_nodes = HashMap::new();
+                    insertions.extend(insert.iter().filter_map(|quad| {
+                        let quad = instantiate(quad, |variable| evaluator.value(variable, &row))?;
+                        Some(fresh_blank_nodes(quad, &mut blank_nodes))
+                    }));
+                }
+                self.delete_quads(deletions, config)?;
+                self.insert_quads(insertions, config)
+            }
+            UpdateOperation::Load {
+                source,
+                destination,
+                ..
+            } => {
+                // Only local files can be loaded
+                let path = source
+                    .as_str()
+                    .strip_prefix("file://")
+                    .ok_or_else(|| ZarrDfError::UnsupportedQuery(format!("LOAD of {source}")))?;
+                let graph = destination.clone().map(serialize_term);
+                let quads = || {
+                    let quads = Self::read_rdf_path(path, config)?;
+                    Ok::<_, ZarrDfError>(quads.map(|[subject, predicate, object, loaded]| {
+                        Ok([subject, predicate, object, graph.clone().unwrap_or(loaded)])
+                    }))
+                };
+                self.append_quads(quads, config)
+            }
+            UpdateOperation::Clear(target) => {
+                let graph = match target {
+                    GraphTarget::Graph(graph) => Some(GraphName::from(graph.clone())),
+                    GraphTarget::Default => Some(GraphName::DefaultGraph),
+                    GraphTarget::Named => {
+                        self.delete_named_graphs(config)?;
+                        return Ok(());
+                    }
+                    GraphTarget::All => None,
+                };
+                self.delete_quads_matching(None, None, None, graph.as_ref(), config)?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Adds the given quads within a [`transaction`](Storage::transaction).
+    fn insert_quads(&mut self, quads: Vec<Quad>, config: &Config) -> Result<(), ZarrDfError> {
+        let quads = || {
+            Ok::<_, ZarrDfError>(quads.iter().cloned().map(|quad| {
+                Ok([
+                    serialize_term(quad.subject),
+                    serialize_term(quad.predicate),
+                    serialize_term(quad.object),
+                    serialize_graph_name(quad.graph_name),
+                ])
+            }))
+        };
+        self.append_quads(quads, config)
+    }
+}
+
+/// Instantiates a template with the values of a solution, or returns `None`
+/// if a term is unbound or not allowed in its position, in which case the
+/// quad is skipped.
+fn instantiate<'r>(
+    quad: &QuadPattern,
+    value: impl Fn(&Variable) -> Option<&'r Term>,
+) -> Option<Quad> {
+    let term = |term: &PatternTerm| match term {
+        PatternTerm::Variable(variable) => value(variable).cloned(),
+        PatternTerm::Term(term) => Some(term.clone()),
+    };
+    let triple = &quad.triple;
+    let triple = triple_from_terms(
+        term(&triple.subject),
+        term(&triple.predicate),
+        term(&triple.object),
+    )?;
+    let graph = match quad.graph.as_ref().map(term) {
+        None => GraphName::DefaultGraph,
+        Some(Some(Term::NamedNode(graph))) => graph.into(),
+        Some(_) => return None,
+    };
+    Some(triple.in_graph(graph))
+}
+
+/// Replaces the blank nodes of a quad with fresh ones, the same for every
+/// occurrence of a label in `blank_nodes`, so that they never clash with
+/// the blank nodes of the store.
+fn fresh_blank_nodes(mut quad: Quad, blank_nodes: &mut HashMap<BlankNode, BlankNode>) -> Quad {
+    let mut fresh = |node: BlankNode| blank_nodes.entry(node).or_default().clone();
+    if let Subject::BlankNode(node) = quad.subject {
+        quad.subject = fresh(node).into();
+    }
+    if let Term::BlankNode(node) = quad.object {
+        quad.object = fresh(node).into();
+    }
+    quad
+}
@@ -0,0 +1,126 @@
+use zarrs::storage::ReadableWritableStorageTraits;
+
+use crate::dictionary::TermId;
+use crate::error::ZarrDfError;
+pub use crate::index::Index;
+use crate::storage::Storage;
+
+/// Number of quads every term takes part in as a subject, a predicate and an
+/// object, for planning queries.
+///
+/// The counts are exact: they are the chunk sizes of the indexes across
+/// every [appended](Storage::append) delta, read from the chunk grids, minus
+/// the quads [deleted](Storage::delete) since. Only the chunks of a single
+/// row are fetched, as they may be the placeholder of a term that never
+/// occurs in the position, which counts as no quad.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Statistics {
+    quads: u64,
+    subjects: Vec<u64>,
+    predicates: Vec<u64>,
+    objects: Vec<u64>,
+}
+
+impl Statistics {
+    /// Number of quads with the given subject, or zero for an unknown one.
+    pub fn subject(&self, id: TermId) -> u64 {
+        count(&self.subjects, id)
+    }
+
+    /// Number of quads with the given predicate, or zero for an unknown one.
+    pub fn predicate(&self, id: TermId) -> u64 {
+        count(&self.predicates, id)
+    }
+
+    /// Number of quads with the given object, or zero for an unknown one.
+    pub fn object(&self, id: TermId) -> u64 {
+        count(&self.objects, id)
+    }
+
+    /// Number of quads of every subject, indexed by identifier.
+    pub fn subjects(&self) -> &[u64] {
+        &self.subjects
+    }
+
+    /// Number of quads of every predicate, indexed by identifier.
+    pub fn predicates(&self) -> &[u64] {
+        &self.predicates
+    }
+
+    /// Number of quads of every object, indexed by identifier.
+    pub fn objects(&self) -> &[u64] {
+        &self.objects
+    }
+
+    /// Number of quads of the dataset, leaving out the placeholder rows and
+    /// the [deleted](Storage::delete) quads.
+    pub fn quads(&self) -> u64 {
+        self.quads
+    }
+}
+
+fn count(counts: &[u64], id: TermId) -> u64 {
+    id.index()
+        .ok()
+        .and_then(|index| counts.get(index))
+        .copied()
+        .unwrap_or(0)
+}
+
+impl<S: ReadableWritableStorageTraits + 'static> Storage<S> {
+    /// Reads the number of quads of every term from the chunk grids of the
+    /// indexes, and the number of quads of the dataset from their metadata,
+    /// see [`Statistics`].
+    pub fn statistics(&self) -> Result<Statistics, ZarrDfError> {
+        let [subjects, predicates, objects] = [Index::Subject, Index::Predicate, Index::Object]
+            .map(|index| {
+                (0..self.num_chunks(index)?)
+                    .map(|chunk| self.quads_in_chunk(index, chunk))
+                    .collect::<Result<Vec<_>, _>>()
+            });
+        Ok(Statistics {
+            quads: self.num_quads(Index::Predicate)?,
+            subjects: subjects?,
+            predicates: predicates?,
+            objects: objects?,
+        })
+    }
+
+    /// Returns the index reading the fewest rows to answer a pattern, which
+    /// is the one keyed by the bound component with the smallest chunk, or
+    /// a full scan of the subject index if nothing is bound. Ties keep the
+    /// order of [`Index::for_pattern`].
+    ///
+    /// The SPO, POS and OSP layouts sort the matches of a pattern the same
+    /// way whichever of the bound components keys the lookup, so the choice
+    /// only changes the number of rows fetched and filtered.
+    pub fn access_path(
+        &self,
+        subject: Option<TermId>,
+        predicate: Option<TermId>,
+        object: Option<TermId>,
+    ) -> Index {
+        [subject, predicate, object]
+            .into_iter()
+            .zip([Index::Subject, Index::Predicate, Index::Object])
+            .filter(|(id, _)| id.is_some())
+            .min_by_key(|&(id, index)| self.estimate(index, id))
+            .map_or(Index::Subject, |(_, index)| index)
+    }
+
+    /// Estimates from the chunk grid of `index` the number of quads having
+    /// `id` in the indexed position, or any term when `id` is `None`, which
+    /// is the average size of a chunk.
+    ///
+    /// Lookups that cannot be estimated are the most expensive, so that the
+    /// error surfaces when the quads are read rather than here.
+    pub(crate) fn estimate(&self, index: Index, id: Option<TermId>) -> u64 {
+        let estimate = match id {
+            Some(id) => self.chunk_len(index, id.get()),
+            None => self
+                .num_quads(index)
+                .and_then(|rows| Ok(rows.div_ceil(self.num_chunks(index)?.max(1)))),
+        };
+        estimate.unwrap_or(u64::MAX)
+    }
+}
@@ -1,146 +1,510 @@
-use std::collections::HashSet;
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::Read;
+use std::iter;
+use std::mem;
 use std::sync::Arc;
+use std::sync::Mutex;
 use std::vec;
 
+use itertools::process_results;
+use itertools::EitherOrBoth;
 use itertools::Itertools;
 use oxrdfio::RdfParser;
+use rayon::iter::IntoParallelIterator;
+use rayon::iter::ParallelIterator;
+use rayon::ThreadPool;
+use rayon::ThreadPoolBuilder;
 use zarrs::array::chunk_grid::RectangularChunkGrid;
-use zarrs::array::codec::GzipCodec;
+use zarrs::array::chunk_grid::RegularChunkGrid;
+use zarrs::array::codec::array_to_bytes::sharding::ShardingCodecBuilder;
 use zarrs::array::Array;
 use zarrs::array::ArrayBuilder;
+use zarrs::array::ArrayCreateError;
 use zarrs::array::ArrayIndices;
 use zarrs::array::ChunkGrid;
+use zarrs::array::ChunkShape;
 use zarrs::array::DataType;
 use zarrs::array::FillValue;
 use zarrs::array_subset::ArraySubset;
 use zarrs::group::Group;
 use zarrs::group::GroupBuilder;
+use zarrs::group::GroupCreateError;
 use zarrs::storage::ReadableWritableStorageTraits;
 
+use crate::config::ArrayConfig;
 use crate::config::Config;
+use crate::config::ConfigBuilder;
+use crate::config::DictionaryLayout;
+use crate::dictionary::Delta;
 use crate::dictionary::Dictionary;
+use crate::dictionary::Section;
 use crate::error::ZarrDfError;
+use crate::index::data_type;
+use crate::index::fill_value;
 use crate::index::Index;
+use crate::index::COLUMNS;
+use crate::index::GRAPH;
 use crate::rdf_format::RdfFormat;
-use crate::utils::rdf_to_value;
+use crate::sort::ExternalSorter;
+use crate::sort::SortedRuns;
+use crate::utils::serialize_graph_name;
 use crate::utils::serialize_term;
-use crate::utils::value_to_rdf;
-use crate::Triple;
+use crate::Quad;
 
 const CHUNK_SIZE: usize = 10000;
 
+/// Group holding the sections of the dictionary.
+pub(crate) const DICTIONARY: &str = "dictionary";
+
+/// Section of the terms shared by subjects and objects.
+const SHARED: &str = "shared";
+
+/// Group holding a group per [append](Storage::append) or
+/// [deletion](Storage::delete), numbered from zero, with the sections of its
+/// new terms and the indexes of the quads it adds laid out as in the root
+/// group, and the indexes of the quads it removes under [`TOMBSTONES`].
+pub(crate) const DELTAS: &str = "deltas";
+
+/// Group of a delta holding the indexes of the quads it removes.
+pub(crate) const TOMBSTONES: &str = "tombstones";
+
+/// Group holding a group per [compaction](Storage::compact), numbered from
+/// one, with the dictionary, indexes and deltas of the store laid out as in
+/// the root group.
+pub(crate) const COMPACTED: &str = "compacted";
+
+/// Attribute of the root group holding the number of the last compaction,
+/// whose group under [`COMPACTED`] holds the store, or the root group itself
+/// when there was none. It is only updated once that group is complete, so a
+/// compaction that fails halfway is never read.
+pub(crate) const GENERATION_ATTRIBUTE: &str = "generation";
+
+/// Attribute of the [`DELTAS`] group holding the number of deltas to read.
+/// It is only updated once every delta of a change is stored, so a change
+/// that fails halfway is never read.
+pub(crate) const COMMITTED_ATTRIBUTE: &str = "committed";
+
+/// Number of bytes in every chunk of the dictionary arrays.
+const DICTIONARY_CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// Attribute of every index array listing the names of its columns.
+const COLUMNS_ATTRIBUTE: &str = "columns";
+
+/// Attribute of every index array telling whether the rows of each chunk are
+/// sorted, so that lookups binding its first columns can binary search them.
+pub(crate) const SORTED_ATTRIBUTE: &str = "sorted";
+
+/// Attribute of every index array holding its number of quads, which unlike
+/// its number of rows excludes the placeholders of terms missing from it.
+pub(crate) const QUADS_ATTRIBUTE: &str = "quads";
+
+/// Attribute of sharded index arrays holding the first row of every term,
+/// followed by the number of rows, as the chunks no longer tell them apart.
+pub(crate) const OFFSETS_ATTRIBUTE: &str = "offsets";
+
+/// Attribute of the index arrays of deltas listing the identifiers they hold
+/// quads of, in order, whose rows start at the matching [`OFFSETS_ATTRIBUTE`].
+pub(crate) const KEYS_ATTRIBUTE: &str = "keys";
+
+/// Identifier of the rows filling the chunks of terms that never occur in the
+/// indexed position, see [`fill_value`].
+const EMPTY: u64 = u64::MAX;
+
 pub struct Storage<S: ReadableWritableStorageTraits> {
     pub dictionary: Dictionary,
     pub subject: Option<Array<S>>,
     pub predicate: Option<Array<S>>,
     pub object: Option<Array<S>>,
+    /// Indexes of every append and deletion, oldest first.
+    pub(crate) deltas: Vec<DeltaIndexes<S>>,
+    /// Number of compactions, see [`GENERATION_ATTRIBUTE`].
+    pub(crate) generation: u64,
+    pub(crate) store: Arc<S>,
+    /// Thread pools of the scans, by number of threads, built once.
+    pub(crate) pools: Mutex<HashMap<usize, Arc<ThreadPool>>>,
+}
+
+/// Path of the group holding the store after `generation` compactions.
+pub(crate) fn base(generation: u64) -> String {
+    match generation {
+        0 => String::new(),
+        generation => format!("/{COMPACTED}/{generation}"),
+    }
+}
+
+/// Subject, predicate and object indexes of the quads added and removed by
+/// an append or a deletion.
+pub(crate) struct DeltaIndexes<S: ReadableWritableStorageTraits> {
+    pub(crate) added: Option<[Array<S>; 3]>,
+    pub(crate) removed: Option<[Array<S>; 3]>,
+}
+
+/// Whether the quads of an index array are added to the quads of the arrays
+/// before it or removed from them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Change {
+    Added,
+    Removed,
 }
 
 impl<S: ReadableWritableStorageTraits + 'static> Storage<S> {
     pub fn from_rdf_and_store(rdf_path: &str, store: S) -> Result<Self, ZarrDfError> {
-        let input_file = File::open(rdf_path)?;
-        let rdf_format = RdfFormat::from_path(rdf_path)?;
-        let triples: Vec<_> = Self::read_rdf(input_file, rdf_format).collect();
-        let store = Arc::new(store);
+        Self::from_rdf_and_store_with_config(rdf_path, store, &Config::default())
+    }
 
-        let mut subjects = HashSet::new();
-        let mut predicates = HashSet::new();
-        let mut objects = HashSet::new();
-        for triple in triples.iter() {
-            subjects.insert(triple[0].clone());
-            predicates.insert(triple[1].clone());
-            objects.insert(triple[2].clone());
-        }
-        let dictionary = Dictionary::new(subjects, predicates, objects)?;
-
-        let triples: Vec<_> = triples
-            .into_iter()
-            .filter_map(|[s, p, o]| {
-                let triple = [
-                    dictionary.get_subject_idx(&s)? as u64,
-                    dictionary.get_predicate_idx(&p)? as u64,
-                    dictionary.get_object_idx(&o)? as u64,
-                ];
-                Some(triple)
-            })
-            .collect();
+    /// Explicit configuration version of [`from_rdf_and_store`](Storage::from_rdf_and_store).
+    ///
+    /// The input is streamed twice: once to build the [`Dictionary`] and once
+    /// to encode the triples. Both passes go through an external sort, so the
+    /// memory used is bounded by the configured budget plus the dictionary.
+    pub fn from_rdf_and_store_with_config(
+        rdf_path: &str,
+        store: S,
+        config: &Config,
+    ) -> Result<Self, ZarrDfError> {
+        let store = Arc::new(store);
 
-        let mut group = GroupBuilder::new().build(store.clone(), "/")?;
+        let quads = || Ok::<_, ZarrDfError>(Self::read_rdf_path(rdf_path, config)?.map(Ok));
+        let dictionary = Self::build_dictionary(quads()?, config)?;
+        let sorted = Self::sort_quads(Self::encode(quads()?, &dictionary), config)?;
 
-        group.attributes_mut().insert(
-            Index::Subject.to_string(),
-            rdf_to_value(dictionary.subjects()),
-        );
-        group.attributes_mut().insert(
-            Index::Predicate.to_string(),
-            rdf_to_value(dictionary.predicates()),
-        );
-        group.attributes_mut().insert(
-            Index::Object.to_string(),
-            rdf_to_value(dictionary.objects()),
-        );
+        let group = GroupBuilder::new().build(store.clone(), "/")?;
 
         group.store_metadata()?;
+        Self::write_dictionary(&dictionary, "", store.clone(), config)?;
+        let [subject, predicate, object] =
+            Self::write_indexes(&sorted, "", store.clone(), &dictionary, config, false)?;
 
         let storage = Self {
+            subject: Some(subject),
+            predicate: Some(predicate),
+            object: Some(object),
             dictionary,
-            subject: Some(Self::write_index(
-                triples.iter().cloned(),
-                store.clone(),
-                Index::Subject,
-            )?),
-            predicate: Some(Self::write_index(
-                triples.iter().cloned(),
-                store.clone(),
-                Index::Predicate,
-            )?),
-            object: Some(Self::write_index(
-                triples.into_iter(),
-                store.clone(),
-                Index::Object,
-            )?),
+            deltas: Vec::new(),
+            generation: 0,
+            store,
+            pools: Mutex::default(),
         };
 
         Ok(storage)
     }
 
     pub fn from_zarr(store: S) -> Result<Self, ZarrDfError> {
-        let store = Arc::new(store);
-        let group = Group::open(store.clone(), "/")?;
-        let attributes = group.attributes();
-
-        let subjects = Array::open(store.clone(), &format!("/{}", Index::Subject))?;
-        let predicates = Array::open(store.clone(), &format!("/{}", Index::Predicate))?;
-        let objects = Array::open(store.clone(), &format!("/{}", Index::Object))?;
-
-        let dictionary = Dictionary::new(
-            match attributes.get(&Index::Subject.to_string()) {
-                Some(subjects) => value_to_rdf(subjects),
-                None => return Err(ZarrDfError::SubjectsNotInMetadata),
-            },
-            match attributes.get(&Index::Predicate.to_string()) {
-                Some(predicates) => value_to_rdf(predicates),
-                None => return Err(ZarrDfError::PredicatesNotInMetadata),
-            },
-            match attributes.get(&Index::Object.to_string()) {
-                Some(objects) => value_to_rdf(objects),
-                None => return Err(ZarrDfError::ObjectsNotInMetadata),
-            },
-        )?;
+        Self::open(Arc::new(store))
+    }
+
+    /// Reopens the store if a compaction or a change committed by another
+    /// storage moved it past the state this one read, returning whether it
+    /// did.
+    ///
+    /// A compaction keeps the arrays of the previous generation, so a storage
+    /// opened before it stays readable until the next one, by which time a
+    /// long-running reader is expected to have refreshed.
+    pub fn refresh(&mut self) -> Result<bool, ZarrDfError> {
+        let generation = Self::read_generation(self.store.clone())?;
+        if generation == self.generation
+            && Self::read_committed(self.store.clone(), &base(generation))?
+                == self.deltas.len() as u64
+        {
+            return Ok(false);
+        }
+        let pools = mem::take(&mut self.pools);
+        *self = Self {
+            pools,
+            ..Self::open(self.store.clone())?
+        };
+        Ok(true)
+    }
+
+    fn open(store: Arc<S>) -> Result<Self, ZarrDfError> {
+        let generation = Self::read_generation(store.clone())?;
+        let base = base(generation);
+        Self::check_version(store.clone(), &base)?;
+        let mut dictionary = Self::read_dictionary(store.clone(), &base)?;
+
+        let subjects = Array::open(store.clone(), &format!("{base}/{}", Index::Subject))?;
+        let predicates = Array::open(store.clone(), &format!("{base}/{}", Index::Predicate))?;
+        let objects = Array::open(store.clone(), &format!("{base}/{}", Index::Object))?;
+
+        let committed = Self::read_committed(store.clone(), &base)?;
+        let mut deltas = Vec::new();
+        while (deltas.len() as u64) < committed {
+            let prefix = format!("{base}/{DELTAS}/{}", deltas.len());
+            let delta = Self::read_delta(store.clone(), &prefix)?
+                .ok_or_else(|| ZarrDfError::Corrupted(format!("missing delta {prefix}")))?;
+            dictionary.push_delta(delta);
+            deltas.push(DeltaIndexes {
+                added: Self::open_indexes(store.clone(), &prefix)?,
+                removed: Self::open_indexes(store.clone(), &format!("{prefix}/{TOMBSTONES}"))?,
+            });
+        }
 
         let storage = Self {
             dictionary,
             subject: Some(subjects),
             predicate: Some(predicates),
             object: Some(objects),
+            deltas,
+            generation,
+            store,
+            pools: Mutex::default(),
         };
 
         Ok(storage)
     }
 
-    fn read_rdf<R: Read>(read: R, format: RdfFormat) -> impl Iterator<Item = [String; 3]> {
+    /// Rejects stores written before the dictionary moved out of the
+    /// attributes of the root group and the indexes gained their graph
+    /// column, which cannot be read as the current layout.
+    fn check_version(store: Arc<S>, base: &str) -> Result<(), ZarrDfError> {
+        let unsupported = |reason: &str| {
+            Err(ZarrDfError::UnsupportedVersion(format!(
+                "{reason}, rebuild the store from its RDF source"
+            )))
+        };
+        match Group::open(store.clone(), "/") {
+            Ok(group) if group.attributes().contains_key(&Index::Subject.to_string()) => {
+                return unsupported("terms are stored in the attributes of the root group");
+            }
+            Ok(_) | Err(GroupCreateError::MissingMetadata) => (),
+            Err(error) => return Err(error.into()),
+        }
+        match Array::open(store, &format!("{base}/{}", Index::Subject)) {
+            Ok(array) if !array.attributes().contains_key(COLUMNS_ATTRIBUTE) => {
+                unsupported("indexes have no graph column")
+            }
+            Ok(_) | Err(ArrayCreateError::MissingMetadata) => Ok(()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Number of compactions of the store, recorded in the root group.
+    fn read_generation(store: Arc<S>) -> Result<u64, ZarrDfError> {
+        // Groups of compactions past the last one are leftovers of a failed
+        // compaction
+        match Group::open(store, "/") {
+            Ok(group) => Ok(group
+                .attributes()
+                .get(GENERATION_ATTRIBUTE)
+                .and_then(|generation| generation.as_u64())
+                .unwrap_or(0)),
+            Err(GroupCreateError::MissingMetadata) => Ok(0),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Number of committed deltas of the group at `base`.
+    fn read_committed(store: Arc<S>, base: &str) -> Result<u64, ZarrDfError> {
+        // Deltas past the committed ones are leftovers of a failed change
+        match Group::open(store, &format!("{base}/{DELTAS}")) {
+            Ok(group) => group
+                .attributes()
+                .get(COMMITTED_ATTRIBUTE)
+                .and_then(|committed| committed.as_u64())
+                .ok_or_else(|| ZarrDfError::Corrupted("deltas are not committed".to_string())),
+            Err(GroupCreateError::MissingMetadata) => Ok(0),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Writes every section of the dictionary as a byte array holding its
+    /// fcsd serialization, under the [`DICTIONARY`] group of the group at
+    /// `prefix`.
+    pub(crate) fn write_dictionary(
+        dictionary: &Dictionary,
+        prefix: &str,
+        store: Arc<S>,
+        config: &Config,
+    ) -> Result<(), ZarrDfError> {
+        let sections = [
+            (SHARED.to_string(), dictionary.shared_section()),
+            (Index::Subject.to_string(), dictionary.subject_section()),
+            (Index::Predicate.to_string(), dictionary.predicate_section()),
+            (Index::Object.to_string(), dictionary.object_section()),
+            (GRAPH.to_string(), dictionary.graph_section()),
+        ];
+        Self::write_sections(sections, prefix, store, config)
+    }
+
+    /// Writes the sections of the terms added by an append under the
+    /// [`DICTIONARY`] group of the delta at `prefix`.
+    pub(crate) fn write_delta(
+        delta: &Delta,
+        prefix: &str,
+        store: Arc<S>,
+        config: &Config,
+    ) -> Result<(), ZarrDfError> {
+        let sections = [
+            (Index::Subject.to_string(), &delta.subjects),
+            (Index::Predicate.to_string(), &delta.predicates),
+            (Index::Object.to_string(), &delta.objects),
+            (GRAPH.to_string(), &delta.graphs),
+        ];
+        Self::write_sections(sections, prefix, store, config)
+    }
+
+    /// Writes named sections under the [`DICTIONARY`] group of the group at
+    /// `prefix`.
+    fn write_sections<'a>(
+        sections: impl IntoIterator<Item = (String, &'a Section)>,
+        prefix: &str,
+        store: Arc<S>,
+        config: &Config,
+    ) -> Result<(), ZarrDfError> {
+        GroupBuilder::new()
+            .build(store.clone(), &format!("{prefix}/{DICTIONARY}"))?
+            .store_metadata()?;
+
+        for (name, section) in sections {
+            let bytes = section.to_bytes()?;
+            let array = ArrayBuilder::new(
+                vec![bytes.len() as u64],
+                DataType::UInt8,
+                vec![DICTIONARY_CHUNK_SIZE].try_into()?,
+                FillValue::from(0u8),
+            )
+            .bytes_to_bytes_codecs(config.compression.codecs(1)?)
+            .build(store.clone(), &format!("{prefix}/{DICTIONARY}/{name}"))?;
+            array.store_metadata()?;
+            if !bytes.is_empty() {
+                array.store_array_subset_elements(&array.subset_all(), &bytes)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the dictionary written by [`write_dictionary`](Storage::write_dictionary).
+    ///
+    /// Sections are read back as they were serialized, so opening a store
+    /// does not parse nor sort any term.
+    fn read_dictionary(store: Arc<S>, prefix: &str) -> Result<Dictionary, ZarrDfError> {
+        let read_section = |name: String| Self::read_section(store.clone(), prefix, &name);
+
+        Ok(Dictionary::from_sections(
+            // Stores without a shared section use the separate layout
+            read_section(SHARED.to_string())?.unwrap_or_default(),
+            read_section(Index::Subject.to_string())?.ok_or(ZarrDfError::SubjectsNotInMetadata)?,
+            read_section(Index::Predicate.to_string())?
+                .ok_or(ZarrDfError::PredicatesNotInMetadata)?,
+            read_section(Index::Object.to_string())?.ok_or(ZarrDfError::ObjectsNotInMetadata)?,
+            read_section(GRAPH.to_string())?.ok_or(ZarrDfError::GraphsNotInMetadata)?,
+        ))
+    }
+
+    /// Reads the sections written by [`write_delta`](Storage::write_delta),
+    /// returning `None` if there is no delta at `prefix`.
+    fn read_delta(store: Arc<S>, prefix: &str) -> Result<Option<Delta>, ZarrDfError> {
+        let read_section = |name: String| Self::read_section(store.clone(), prefix, &name);
+        let Some(subjects) = read_section(Index::Subject.to_string())? else {
+            return Ok(None);
+        };
+        Ok(Some(Delta {
+            subjects,
+            predicates: read_section(Index::Predicate.to_string())?
+                .ok_or(ZarrDfError::PredicatesNotInMetadata)?,
+            objects: read_section(Index::Object.to_string())?
+                .ok_or(ZarrDfError::ObjectsNotInMetadata)?,
+            graphs: read_section(GRAPH.to_string())?.ok_or(ZarrDfError::GraphsNotInMetadata)?,
+        }))
+    }
+
+    /// Opens the three indexes of the group at `prefix`, returning `None` if
+    /// they were never written.
+    fn open_indexes(store: Arc<S>, prefix: &str) -> Result<Option<[Array<S>; 3]>, ZarrDfError> {
+        let open = |index: Index| Array::open(store.clone(), &format!("{prefix}/{index}"));
+        match open(Index::Subject) {
+            Ok(subjects) => Ok(Some([
+                subjects,
+                open(Index::Predicate)?,
+                open(Index::Object)?,
+            ])),
+            Err(ArrayCreateError::MissingMetadata) => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Reads a section of the [`DICTIONARY`] group of the group at `prefix`,
+    /// returning `None` if it was never written.
+    fn read_section(
+        store: Arc<S>,
+        prefix: &str,
+        name: &str,
+    ) -> Result<Option<Section>, ZarrDfError> {
+        let array = match Array::open(store, &format!("{prefix}/{DICTIONARY}/{name}")) {
+            Ok(array) => array,
+            Err(ArrayCreateError::MissingMetadata) => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+        let bytes = array.retrieve_array_subset_elements::<u8>(&array.subset_all())?;
+        Section::from_bytes(&bytes).map(Some)
+    }
+
+    /// Returns the array backing `index`.
+    pub(crate) fn array(&self, index: &Index) -> Result<&Array<S>, ZarrDfError> {
+        match index {
+            Index::Subject => self.subject.as_ref(),
+            Index::Predicate => self.predicate.as_ref(),
+            Index::Object => self.object.as_ref(),
+        }
+        .ok_or(ZarrDfError::InvalidQuery)
+    }
+
+    /// Returns the pool of `threads` threads, one per CPU if zero, building
+    /// it on first use so that repeated scans do not spawn threads anew.
+    pub(crate) fn thread_pool(&self, threads: usize) -> Result<Arc<ThreadPool>, ZarrDfError> {
+        let mut pools = self.pools.lock().expect("no thread panics holding it");
+        if let Some(pool) = pools.get(&threads) {
+            return Ok(pool.clone());
+        }
+        let pool = Arc::new(ThreadPoolBuilder::new().num_threads(threads).build()?);
+        pools.insert(threads, pool.clone());
+        Ok(pool)
+    }
+
+    /// Returns a builder set to the configuration the store was written
+    /// with, as recorded in the codecs of its base subject array, so that
+    /// the arrays written afterwards are encoded like the others. Settings
+    /// that are not stored, like the memory budget, keep their defaults.
+    pub fn config_builder(&self) -> Result<ConfigBuilder, ZarrDfError> {
+        let mut config = ConfigBuilder::new();
+        if self.dictionary.shared_len() > 0 {
+            config.dictionary_layout(DictionaryLayout::Shared);
+        }
+        let metadata = serde_json::to_value(self.array(&Index::Subject)?.metadata())
+            .map_err(|error| ZarrDfError::Corrupted(error.to_string()))?;
+        config.array_metadata(&metadata);
+        Ok(config)
+    }
+
+    /// Configuration the store was written with, see
+    /// [`config_builder`](Storage::config_builder).
+    pub(crate) fn config(&self) -> Result<Config, ZarrDfError> {
+        self.config_builder().map(|config| config.build())
+    }
+
+    /// Returns the array backing `index` followed by the arrays of every
+    /// delta, in the order their changes apply to its quads.
+    pub(crate) fn layers(&self, index: &Index) -> Result<Vec<(Change, &Array<S>)>, ZarrDfError> {
+        let position = index.positions()[0];
+        let deltas = self.deltas.iter().flat_map(|delta| {
+            let added = delta
+                .added
+                .as_ref()
+                .map(|arrays| (Change::Added, &arrays[position]));
+            let removed = delta
+                .removed
+                .as_ref()
+                .map(|arrays| (Change::Removed, &arrays[position]));
+            added.into_iter().chain(removed)
+        });
+        Ok(iter::once((Change::Added, self.array(index)?))
+            .chain(deltas)
+            .collect())
+    }
+
+    fn read_rdf<R: Read>(read: R, format: RdfFormat) -> impl Iterator<Item = [String; 4]> {
         RdfParser::from_format(format.into())
             .for_reader(read)
             .flatten()
@@ -148,71 +512,453 @@ impl<S: ReadableWritableStorageTraits + 'static> Storage<S> {
                 let subject = serialize_term(quad.subject);
                 let predicate = serialize_term(quad.predicate);
                 let object = serialize_term(quad.object);
+                let graph = serialize_graph_name(quad.graph_name);
 
                 if subject.is_empty() || predicate.is_empty() || object.is_empty() {
-                    return None;
+                    None
                 } else {
-                    Some([subject, predicate, object])
+                    Some([subject, predicate, object, graph])
+                }
+            })
+    }
+
+    pub(crate) fn read_rdf_path(
+        rdf_path: &str,
+        config: &Config,
+    ) -> Result<impl Iterator<Item = [String; 4]>, ZarrDfError> {
+        let input_file = File::open(rdf_path)?;
+        let rdf_format = match config.rdf_format {
+            Some(rdf_format) => rdf_format,
+            None => RdfFormat::from_path(rdf_path)?,
+        };
+        Ok(Self::read_rdf(input_file, rdf_format))
+    }
+
+    pub(crate) fn build_dictionary(
+        quads: impl Iterator<Item = Result<[String; 4], ZarrDfError>>,
+        config: &Config,
+    ) -> Result<Dictionary, ZarrDfError> {
+        let memory_budget = config.memory_budget / 4;
+        let mut subjects = ExternalSorter::new(memory_budget, config);
+        let mut predicates = ExternalSorter::new(memory_budget, config);
+        let mut objects = ExternalSorter::new(memory_budget, config);
+        let mut graphs = ExternalSorter::new(memory_budget, config);
+
+        for quad in quads {
+            let [subject, predicate, object, graph] = quad?;
+            subjects.push(subject)?;
+            predicates.push(predicate)?;
+            objects.push(object)?;
+            if !graph.is_empty() {
+                graphs.push(graph)?;
+            }
+        }
+
+        let subjects = subjects.finish()?;
+        let predicates = predicates.finish()?;
+        let objects = objects.finish()?;
+        let graphs = graphs.finish()?;
+
+        let dictionary = match config.dictionary_layout {
+            DictionaryLayout::Separate => Dictionary::new(
+                [],
+                subjects.iter()?,
+                predicates.iter()?,
+                objects.iter()?,
+                graphs.iter()?,
+            )?,
+            // Both sorted runs are merged once per section, so that no section
+            // has to be held in memory before being front-coded
+            DictionaryLayout::Shared => Dictionary::new(
+                Self::merge_terms(&subjects, &objects, EitherOrBoth::is_both)?,
+                Self::merge_terms(&subjects, &objects, EitherOrBoth::is_left)?,
+                predicates.iter()?,
+                Self::merge_terms(&subjects, &objects, EitherOrBoth::is_right)?,
+                graphs.iter()?,
+            )?,
+        };
+
+        Ok(dictionary)
+    }
+
+    /// Merges the sorted subjects and objects, keeping the terms for which
+    /// `keep` holds.
+    fn merge_terms<'a>(
+        subjects: &'a SortedRuns<String>,
+        objects: &'a SortedRuns<String>,
+        keep: fn(&EitherOrBoth<String>) -> bool,
+    ) -> Result<impl Iterator<Item = Result<String, ZarrDfError>> + 'a, ZarrDfError> {
+        let merged = subjects
+            .iter()?
+            .merge_join_by(objects.iter()?, |subject, object| {
+                match (subject, object) {
+                    (Ok(subject), Ok(object)) => subject.cmp(object),
+                    // Errors are yielded as soon as they are found
+                    (Err(_), _) => Ordering::Less,
+                    (_, Err(_)) => Ordering::Greater,
                 }
             })
+            .filter_map(move |terms| {
+                let terms = match terms {
+                    EitherOrBoth::Both(subject, object) => {
+                        subject.and_then(|subject| Ok(EitherOrBoth::Both(subject, object?)))
+                    }
+                    EitherOrBoth::Left(subject) => subject.map(EitherOrBoth::Left),
+                    EitherOrBoth::Right(object) => object.map(EitherOrBoth::Right),
+                };
+                match terms {
+                    Ok(terms) => keep(&terms).then(|| Ok(terms.reduce(|term, _| term))),
+                    Err(error) => Some(Err(error)),
+                }
+            });
+        Ok(merged)
     }
 
+    /// Encodes every quad through the dictionary, skipping the ones with
+    /// terms it does not hold.
+    pub(crate) fn encode<'a>(
+        quads: impl Iterator<Item = Result<[String; 4], ZarrDfError>> + 'a,
+        dictionary: &'a Dictionary,
+    ) -> impl Iterator<Item = Result<Quad, ZarrDfError>> + 'a {
+        quads.filter_map(|quad| {
+            let [s, p, o, g] = match quad {
+                Ok(quad) => quad,
+                Err(error) => return Some(Err(error)),
+            };
+            let quad = [
+                dictionary.get_subject_idx(&s)?.get(),
+                dictionary.get_predicate_idx(&p)?.get(),
+                dictionary.get_object_idx(&o)?.get(),
+                dictionary.get_graph_idx(&g)?.get(),
+            ];
+            Some(Ok(quad))
+        })
+    }
+
+    /// Sorts every quad by the key of each index, see [`Index::sort_key`].
+    pub(crate) fn sort_quads(
+        quads: impl Iterator<Item = Result<Quad, ZarrDfError>>,
+        config: &Config,
+    ) -> Result<[SortedRuns<Quad>; 3], ZarrDfError> {
+        let memory_budget = config.memory_budget / 3;
+        let mut subjects = ExternalSorter::new(memory_budget, config);
+        let mut predicates = ExternalSorter::new(memory_budget, config);
+        let mut objects = ExternalSorter::new(memory_budget, config);
+
+        for quad in quads {
+            let quad = quad?;
+            subjects.push(Index::Subject.sort_key(&quad))?;
+            predicates.push(Index::Predicate.sort_key(&quad))?;
+            objects.push(Index::Object.sort_key(&quad))?;
+        }
+
+        Ok([subjects.finish()?, predicates.finish()?, objects.finish()?])
+    }
+
+    /// Writes the three indexes of the quads sorted by [`sort_quads`](Storage::sort_quads)
+    /// under the group at `prefix`, with one chunk per identifier of
+    /// `dictionary`, or only rows for the identifiers with quads when
+    /// `sparse`, as for deltas, see [`write_index`](Storage::write_index).
+    pub(crate) fn write_indexes(
+        sorted: &[SortedRuns<Quad>; 3],
+        prefix: &str,
+        store: Arc<S>,
+        dictionary: &Dictionary,
+        config: &Config,
+        sparse: bool,
+    ) -> Result<[Array<S>; 3], ZarrDfError> {
+        let [subjects, predicates, objects] = sorted;
+        let [subject_sizes, predicate_sizes, object_sizes] =
+            Self::chunk_sizes(subjects, dictionary)?;
+
+        // The three indexes are written concurrently, each encoding its
+        // batches in parallel on the same pool
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(config.threads)
+            .build()?;
+        let write_index = |quads: &SortedRuns<Quad>, sizes, index| {
+            Self::write_index(
+                quads.iter()?,
+                sizes,
+                store.clone(),
+                prefix,
+                index,
+                dictionary,
+                config,
+                sparse,
+            )
+        };
+        let (subject, (predicate, object)) = pool.install(|| {
+            rayon::join(
+                || write_index(subjects, subject_sizes, Index::Subject),
+                || {
+                    rayon::join(
+                        || write_index(predicates, predicate_sizes, Index::Predicate),
+                        || write_index(objects, object_sizes, Index::Object),
+                    )
+                },
+            )
+        });
+        Ok([subject?, predicate?, object?])
+    }
+
+    /// Counts how many distinct quads each subject, predicate and object
+    /// takes part in, which gives the chunk sizes of every index.
+    fn chunk_sizes(
+        subjects: &SortedRuns<Quad>,
+        dictionary: &Dictionary,
+    ) -> Result<[Vec<u64>; 3], ZarrDfError> {
+        let mut chunk_sizes = [
+            vec![0; dictionary.subjects_len()],
+            vec![0; dictionary.predicates_len()],
+            vec![0; dictionary.objects_len()],
+        ];
+        for quad in subjects.iter()? {
+            for (sizes, component) in chunk_sizes.iter_mut().zip(quad?) {
+                sizes[component as usize] += 1;
+            }
+        }
+        Ok(chunk_sizes)
+    }
+
+    /// Writes an index with one chunk per dictionary identifier, so that the
+    /// chunk number of a term is its identifier. `quads` must be sorted by
+    /// [`Index::sort_key`] and `chunk_sizes` must hold the number of quads of
+    /// every identifier.
+    ///
+    /// A `sparse` index instead holds the rows of the identifiers with quads
+    /// back to back, listed in its [`KEYS_ATTRIBUTE`] along with their
+    /// [`OFFSETS_ATTRIBUTE`], like a sharded one only knows the offsets.
+    #[allow(clippy::too_many_arguments)]
     fn write_index(
-        triples: impl Iterator<Item = Triple>,
+        quads: impl Iterator<Item = Result<Quad, ZarrDfError>>,
+        chunk_sizes: Vec<u64>,
         store: Arc<S>,
+        prefix: &str,
         index: Index,
+        dictionary: &Dictionary,
+        config: &Config,
+        sparse: bool,
     ) -> Result<Array<S>, ZarrDfError> {
-        let chunked_triples = triples.chunk_by(|triple| index.triple_to_component(triple));
-
-        let mut chunk_sizes = Vec::new();
-        let mut triples = Vec::new();
-        for (_, chunk) in chunked_triples.into_iter() {
-            let chunk = chunk.map(|[a, b, _]| [a, b]).flatten().collect::<Vec<_>>();
-            chunk_sizes.push(chunk.len() as u64 / 2);
-            triples.push(chunk);
-        }
-
-        let config = Config {
-            shape: vec![chunk_sizes.iter().sum(), 2],
-            data_type: DataType::UInt64,
-            chunk_grid: ChunkGrid::new(RectangularChunkGrid::new(&[
-                chunk_sizes.try_into()?, // x dimension
-                2.try_into()?,           // y dimension
-            ])),
-            fill_value: FillValue::from(0u64),
-            index,
+        // Every column shares the data type of the array, so the widest
+        // column decides it
+        let lens = dictionary.lens();
+        let [_, columns @ ..] = index.positions();
+        let data_type = data_type(
+            columns
+                .iter()
+                .map(|&column| lens[column])
+                .max()
+                .unwrap_or(0),
+        );
+
+        let quads_len = chunk_sizes.iter().sum();
+        // Deltas only keep the identifiers they hold quads of, so that their
+        // metadata grows with the delta rather than with the dictionary
+        let keys: Option<Vec<u64>> = sparse.then(|| {
+            (0..)
+                .zip(&chunk_sizes)
+                .filter(|&(_, &size)| size > 0)
+                .map(|(id, _)| id)
+                .collect()
+        });
+        let chunk_sizes: Vec<u64> = match keys {
+            Some(_) => chunk_sizes.into_iter().filter(|&size| size > 0).collect(),
+            None => chunk_sizes,
+        };
+        let chunk_rows = match config.sharding {
+            Some(sharding) => Some(sharding.shard_rows()),
+            None => sparse.then_some(CHUNK_SIZE as u64),
+        };
+        let array_config = match chunk_rows {
+            // Shards, like the chunks of deltas, hold consecutive rows
+            // whatever their term, which is found from the offsets instead
+            Some(chunk_rows) => {
+                let offsets: Vec<u64> = iter::once(0)
+                    .chain(chunk_sizes.iter().scan(0, |offset, &size| {
+                        *offset += size;
+                        Some(*offset)
+                    }))
+                    .collect();
+                ArrayConfig {
+                    shape: vec![*offsets.last().unwrap_or(&0), COLUMNS as u64],
+                    chunk_grid: ChunkGrid::new(RegularChunkGrid::new(ChunkShape::try_from([
+                        chunk_rows,
+                        COLUMNS as u64,
+                    ])?)),
+                    fill_value: fill_value(&data_type),
+                    data_type,
+                    index,
+                    path: format!("{prefix}/{index}"),
+                    offsets: Some(offsets),
+                    keys,
+                    chunk_rows: Some(chunk_rows),
+                    quads: quads_len,
+                }
+            }
+            None => {
+                // Chunks cannot be empty, so terms that never occur in this
+                // position get a single row holding the fill value, which is
+                // never stored
+                let grid_sizes: Vec<_> = chunk_sizes.iter().map(|&size| size.max(1)).collect();
+                ArrayConfig {
+                    shape: vec![grid_sizes.iter().sum(), COLUMNS as u64],
+                    chunk_grid: ChunkGrid::new(RectangularChunkGrid::new(&[
+                        grid_sizes.try_into()?,       // x dimension
+                        (COLUMNS as u64).try_into()?, // y dimension
+                    ])),
+                    // Identifiers start at zero, so a zero fill value would
+                    // make zarrs drop every chunk whose rows only reference
+                    // the first terms
+                    fill_value: fill_value(&data_type),
+                    data_type,
+                    index,
+                    path: format!("{prefix}/{index}"),
+                    offsets: None,
+                    keys: None,
+                    chunk_rows: None,
+                    quads: quads_len,
+                }
+            }
         };
 
-        Self::serialize(triples.into_iter(), store, config)
+        let placeholder = chunk_rows.is_none();
+        process_results(quads, |mut quads| {
+            let chunks = chunk_sizes.iter().map(|&size| match size {
+                0 if placeholder => vec![EMPTY; COLUMNS],
+                _ => quads
+                    .by_ref()
+                    .take(size as usize)
+                    .flat_map(|[_, a, b, graph]| [a, b, graph])
+                    .collect(),
+            });
+            Self::serialize(chunks, store, array_config, config)
+        })?
     }
 
     fn serialize(
-        triples: impl Iterator<Item = Vec<u64>>,
+        chunks: impl Iterator<Item = Vec<u64>>,
         store: Arc<S>,
-        config: Config,
+        array_config: ArrayConfig,
+        config: &Config,
     ) -> Result<Array<S>, ZarrDfError> {
-        let array = ArrayBuilder::new(
-            config.shape,
-            config.data_type,
-            config.chunk_grid,
-            config.fill_value,
-        )
-        .bytes_to_bytes_codecs(vec![Arc::new(GzipCodec::new(5)?)])
-        .dimension_names(Some(config.index.dimension_names()))
-        .build(store.clone(), &format!("/{}", config.index))?;
+        let element_size = array_config.data_type.fixed_size().unwrap_or(8);
+        let mut attributes = serde_json::Map::from_iter([
+            (
+                COLUMNS_ATTRIBUTE.to_string(),
+                array_config.index.columns().collect::<Vec<_>>().into(),
+            ),
+            (QUADS_ATTRIBUTE.to_string(), array_config.quads.into()),
+            // Quads are written in the order of Index::sort_key
+            (SORTED_ATTRIBUTE.to_string(), true.into()),
+        ]);
+        if let Some(offsets) = array_config.offsets {
+            attributes.insert(OFFSETS_ATTRIBUTE.to_string(), offsets.into());
+        }
+        if let Some(keys) = array_config.keys {
+            attributes.insert(KEYS_ATTRIBUTE.to_string(), keys.into());
+        }
+        let mut builder = ArrayBuilder::new(
+            array_config.shape,
+            array_config.data_type,
+            array_config.chunk_grid,
+            array_config.fill_value,
+        );
+        match config.sharding {
+            // Filters and compression apply to every inner chunk, which can
+            // then be decoded on its own
+            Some(sharding) => builder.array_to_bytes_codec(
+                ShardingCodecBuilder::new(ChunkShape::try_from([
+                    sharding.chunk_rows,
+                    COLUMNS as u64,
+                ])?)
+                .array_to_array_codecs(config.filters())
+                .bytes_to_bytes_codecs(config.compression.codecs(element_size)?)
+                .build_arc(),
+            ),
+            None => builder
+                .array_to_array_codecs(config.filters())
+                .bytes_to_bytes_codecs(config.compression.codecs(element_size)?),
+        };
+        let array = builder
+            .dimension_names(Some(array_config.index.dimension_names()))
+            .attributes(attributes)
+            .build(store.clone(), &array_config.path)?;
 
         array.store_metadata()?;
 
-        // TODO: Parallelize
-        for (i, chunk) in triples.chunks(CHUNK_SIZE).into_iter().enumerate() {
-            let chunk: Vec<u64> = chunk.flatten().collect();
-            let size = chunk.len() / 2;
-            let start = ArrayIndices::from([(i * CHUNK_SIZE) as u64, 0]);
-            let end = ArrayIndices::from([size as u64, 2]);
-            let subset = ArraySubset::new_with_start_shape(start, end)?;
-            array.store_array_subset_elements(&subset, &chunk[..])?;
+        // Whole chunks are accumulated until a batch holds at least CHUNK_SIZE
+        // rows, so that no chunk is ever written in more than one subset.
+        // Shards, and the chunks of deltas, are written whole for the same
+        // reason, so the rows past the last complete one wait for the next
+        // batch. Batches never share a chunk, so as many as there are threads
+        // are encoded and stored in parallel while the memory used stays
+        // bounded.
+        let shard_rows = array_config.chunk_rows.map(|rows| rows as usize);
+        let batch_rows = shard_rows.map_or(CHUNK_SIZE, |shard_rows| shard_rows.max(CHUNK_SIZE));
+        let mut start = 0;
+        let mut batch = Vec::new();
+        let mut batches = Vec::new();
+        for chunk in chunks {
+            batch.extend(chunk);
+            let rows = batch.len() / COLUMNS;
+            if rows >= batch_rows {
+                let rows = shard_rows.map_or(rows, |shard_rows| rows - rows % shard_rows);
+                let rest = batch.split_off(rows * COLUMNS);
+                batches.push((start, mem::replace(&mut batch, rest)));
+                start += rows as u64;
+                if batches.len() >= rayon::current_num_threads() {
+                    Self::store_batches(&array, mem::take(&mut batches))?;
+                }
+            }
+        }
+        if !batch.is_empty() {
+            batches.push((start, batch));
         }
+        Self::store_batches(&array, batches)?;
 
         Ok(array)
     }
+
+    /// Stores batches of rows, each along with its first row, in parallel.
+    fn store_batches(array: &Array<S>, batches: Vec<(u64, Vec<u64>)>) -> Result<(), ZarrDfError> {
+        batches
+            .into_par_iter()
+            .try_for_each(|(start, batch)| Self::store_batch(array, start, &batch))
+    }
+
+    /// Stores the rows of `batch` starting at row `start`.
+    ///
+    /// Identifiers are narrowed to the data type of the array, which holds
+    /// them all, and [`EMPTY`] becomes its maximum value.
+    fn store_batch(array: &Array<S>, start: u64, batch: &[u64]) -> Result<(), ZarrDfError> {
+        let size = (batch.len() / COLUMNS) as u64;
+        let start_indices = ArrayIndices::from([start, 0]);
+        let shape = ArrayIndices::from([size, COLUMNS as u64]);
+        let subset = ArraySubset::new_with_start_shape(start_indices, shape)?;
+        match array.data_type() {
+            DataType::UInt8 => {
+                array.store_array_subset_elements(&subset, &narrow(batch, u8::MAX)?)?
+            }
+            DataType::UInt16 => {
+                array.store_array_subset_elements(&subset, &narrow(batch, u16::MAX)?)?
+            }
+            DataType::UInt32 => {
+                array.store_array_subset_elements(&subset, &narrow(batch, u32::MAX)?)?
+            }
+            _ => array.store_array_subset_elements(&subset, batch)?,
+        }
+        Ok(())
+    }
+}
+
+/// Narrows identifiers to a smaller integer type, where [`EMPTY`] becomes
+/// `empty`.
+fn narrow<T: TryFrom<u64> + Copy>(ids: &[u64], empty: T) -> Result<Vec<T>, ZarrDfError> {
+    ids.iter()
+        .map(|&id| match id {
+            EMPTY => Ok(empty),
+            id => T::try_from(id).map_err(|_| ZarrDfError::TermIdOverflow(id)),
+        })
+        .collect()
 }
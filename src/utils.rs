@@ -1,35 +1,45 @@
-use fcsd::Set;
+use std::str::FromStr;
+
+use oxrdf::GraphName;
 use oxrdf::Term as OxTerm;
-use serde_json::Value;
 
-pub fn rdf_to_value(terms: Set) -> Value {
-    terms
-        .iter()
-        .map(|(_, term)| std::str::from_utf8(&term).unwrap().to_string())
-        .collect::<Vec<_>>()
-        .into()
+use crate::error::ZarrDfError;
+
+/// Serializes a term in N-Triples syntax, which is how terms are stored in
+/// the [`Dictionary`](crate::dictionary::Dictionary).
+pub fn serialize_term(term: impl Into<OxTerm>) -> String {
+    term.into().to_string()
 }
 
-pub fn value_to_rdf(value: &Value) -> Vec<String> {
-    let mut terms: Vec<_> = value
-        .as_array()
-        .unwrap()
-        .iter()
-        .map(|term| term.as_str().unwrap().to_string())
-        .collect();
-    terms.sort();
-    terms
+/// Parses a term serialized by [`serialize_term`].
+pub fn deserialize_term(term: &[u8]) -> Result<OxTerm, ZarrDfError> {
+    Ok(OxTerm::from_str(std::str::from_utf8(term)?)?)
 }
 
-pub fn serialize_term(term: impl Into<OxTerm>) -> String {
-    match term.into() {
-        OxTerm::BlankNode(id) => format!("_:{id}"),
-        OxTerm::NamedNode(iri) => iri.to_string(),
-        OxTerm::Literal(literal) => match literal.destruct() {
-            (value, None, None) => value,
-            (value, Some(datatype), None) => format!("\"{value}\"^^{datatype}"),
-            (value, None, Some(language)) => format!("\"{value}\"@{language}"),
-            _ => unreachable!(),
-        },
+/// Serializes a graph name like [`serialize_term`], using the empty string for
+/// the default graph.
+pub fn serialize_graph_name(graph_name: GraphName) -> String {
+    match graph_name {
+        GraphName::NamedNode(iri) => serialize_term(iri),
+        GraphName::BlankNode(id) => serialize_term(id),
+        GraphName::DefaultGraph => String::new(),
+    }
+}
+
+/// Replaces every row of `rows` but the first with its difference to the
+/// previous row, column by column, keeping the bits of `mask`.
+///
+/// Differences wrap around, so any sequence of rows can be encoded, but they
+/// are only small when the rows are sorted.
+pub fn delta_encode(rows: &mut [u64], columns: usize, mask: u64) {
+    for i in (columns..rows.len()).rev() {
+        rows[i] = rows[i].wrapping_sub(rows[i - columns]) & mask;
+    }
+}
+
+/// Undoes [`delta_encode`].
+pub fn delta_decode(rows: &mut [u64], columns: usize, mask: u64) {
+    for i in columns..rows.len() {
+        rows[i] = rows[i].wrapping_add(rows[i - columns]) & mask;
     }
 }
@@ -0,0 +1,76 @@
+use zarrs::storage::ReadableWritableStorageTraits;
+
+use crate::dictionary::TermId;
+use crate::error::ZarrDfError;
+use crate::index::Index;
+use crate::index::COLUMNS;
+use crate::storage::Storage;
+
+impl<S: ReadableWritableStorageTraits + 'static> Storage<S> {
+    /// Checks that the store is consistent, reporting the first problem found
+    /// as [`ZarrDfError::Corrupted`].
+    ///
+    /// Every term of the dictionary must decode, every index must have one
+    /// chunk per identifier with rows strictly sorted and referencing known
+    /// terms, and the three indexes must hold the same number of quads.
+    pub fn verify(&self) -> Result<(), ZarrDfError> {
+        let dictionary = &self.dictionary;
+        for id in 0..dictionary.subjects_len() {
+            dictionary.subject_term(TermId::from_index(id))?;
+        }
+        for id in 0..dictionary.predicates_len() {
+            dictionary.predicate_term(TermId::from_index(id))?;
+        }
+        for id in 0..dictionary.objects_len() {
+            dictionary.object_term(TermId::from_index(id))?;
+        }
+        for id in 0..dictionary.graphs_len() {
+            dictionary.graph_term(TermId::from_index(id))?;
+        }
+
+        let subjects = self.verify_index(&Index::Subject)?;
+        let predicates = self.verify_index(&Index::Predicate)?;
+        let objects = self.verify_index(&Index::Object)?;
+        if subjects != predicates || subjects != objects {
+            return Err(ZarrDfError::Corrupted(format!(
+                "indexes hold {subjects}, {predicates} and {objects} quads"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Verifies the chunks of `index`, returning the number of quads in it.
+    fn verify_index(&self, index: &Index) -> Result<usize, ZarrDfError> {
+        let lens = self.dictionary.lens().map(|len| len as u64);
+        let num_chunks = self.num_chunks(*index)?;
+        let expected = lens[index.positions()[0]];
+        if num_chunks != expected {
+            return Err(ZarrDfError::Corrupted(format!(
+                "{index} index has {num_chunks} chunks instead of {expected}"
+            )));
+        }
+
+        let mut count = 0;
+        for key in 0..num_chunks {
+            let chunk = self.chunk_rows(*index, key)?;
+            let mut last = None;
+            for row in chunk.chunks(COLUMNS) {
+                let quad = index.quad_from_row(key, row);
+                if quad.iter().zip(lens).any(|(&id, len)| id >= len) {
+                    return Err(ZarrDfError::Corrupted(format!(
+                        "{index} index references unknown terms in {quad:?}"
+                    )));
+                }
+                let sort_key = index.sort_key(&quad);
+                if last.is_some_and(|last| last >= sort_key) {
+                    return Err(ZarrDfError::Corrupted(format!(
+                        "{index} index is not sorted at {quad:?}"
+                    )));
+                }
+                last = Some(sort_key);
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+}
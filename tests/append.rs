@@ -0,0 +1,197 @@
+use oxrdf::NamedNode;
+use zarrdf::config::Compression;
+use zarrdf::config::ConfigBuilder;
+use zarrdf::config::DictionaryLayout;
+use zarrdf::config::Sharding;
+use zarrdf::query::BasicGraphPattern;
+use zarrdf::query::TermPattern;
+use zarrdf::storage::Storage;
+use zarrs::filesystem::FilesystemStore;
+
+mod common;
+use common::open;
+use common::quads;
+
+const BASE: &str = "\
+<http://example.org/a> <http://example.org/name> \"Alice\" .
+<http://example.org/a> <http://example.org/knows> <http://example.org/b> .
+<http://example.org/b> <http://example.org/name> \"Bob\" .
+";
+
+// Repeats a quad of the base, adds quads to a known subject and introduces
+// new terms in every position
+const APPENDED: &str = "\
+<http://example.org/b> <http://example.org/name> \"Bob\" .
+<http://example.org/b> <http://example.org/knows> <http://example.org/a> .
+<http://example.org/b> <http://example.org/age> \"42\" .
+<http://example.org/c> <http://example.org/name> \"Carol\" <http://example.org/g> .
+";
+
+#[test]
+fn test_append_and_compact() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let base = dir.path().join("base.nq");
+    let appended = dir.path().join("appended.nq");
+    let union = dir.path().join("union.nq");
+    std::fs::write(&base, BASE)?;
+    std::fs::write(&appended, APPENDED)?;
+    std::fs::write(&union, format!("{BASE}{APPENDED}"))?;
+
+    let store = FilesystemStore::new(dir.path().join("expected.zarr"))?;
+    let expected = quads(&Storage::from_rdf_and_store(
+        union.to_str().unwrap(),
+        store,
+    )?);
+    assert_eq!(expected.len(), 6);
+
+    let config = ConfigBuilder::new()
+        .dictionary_layout(DictionaryLayout::Shared)
+        .build();
+    let zarr = dir.path().join("append.zarr");
+    let store = FilesystemStore::new(&zarr)?;
+    let mut storage =
+        Storage::from_rdf_and_store_with_config(base.to_str().unwrap(), store, &config)?;
+    storage.append(appended.to_str().unwrap())?;
+    assert_eq!(quads(&storage), expected);
+    assert_eq!(quads(&open(&zarr)), expected);
+    open(&zarr).verify()?;
+    assert_eq!(open(&zarr).count_matching(None, None, None)?, 6);
+
+    // The delta only lists the subjects it has quads of, b and c
+    let metadata = std::fs::read_to_string(zarr.join("deltas/0/subject/zarr.json"))?;
+    let metadata: serde_json::Value = serde_json::from_str(&metadata)?;
+    assert_eq!(
+        metadata["attributes"]["keys"].as_array().map(Vec::len),
+        Some(2)
+    );
+    assert_eq!(
+        metadata["attributes"]["offsets"],
+        serde_json::json!([0, 2, 3])
+    );
+
+    // The chunk of a known subject merges its base and appended quads,
+    // sorted by identifier as appended terms are numbered last
+    let b = NamedNode::new("http://example.org/b")?.into();
+    let triples: Vec<_> = storage
+        .triples_matching_terms(Some(&b), None, None)
+        .map(|triple| triple.unwrap().to_string())
+        .collect();
+    assert_eq!(
+        triples,
+        [
+            "<http://example.org/b> <http://example.org/knows> <http://example.org/a>",
+            "<http://example.org/b> <http://example.org/name> \"Bob\"",
+            "<http://example.org/b> <http://example.org/age> \"42\"",
+        ]
+    );
+
+    // Appending quads the store already holds writes nothing
+    storage.append(appended.to_str().unwrap())?;
+    assert!(zarr.join("deltas/0").exists());
+    assert!(!zarr.join("deltas/1").exists());
+
+    storage.compact()?;
+    assert!(!zarr.join("compacted/1/deltas").exists());
+    assert_eq!(quads(&storage), expected);
+    assert_eq!(storage.dictionary.shared_len(), 2);
+    let storage = open(&zarr);
+    storage.verify()?;
+    assert_eq!(quads(&storage), expected);
+    Ok(())
+}
+
+#[test]
+fn test_compact_keeps_config() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let base = dir.path().join("base.nq");
+    let appended = dir.path().join("appended.nq");
+    std::fs::write(&base, BASE)?;
+    std::fs::write(&appended, APPENDED)?;
+
+    let config = ConfigBuilder::new()
+        .compression(Compression::Zstd(7))
+        .transpose(true)
+        .delta(true)
+        .sharding(Some(Sharding {
+            chunk_rows: 4,
+            chunks_per_shard: 2,
+        }))
+        .build();
+    let zarr = dir.path().join("compact.zarr");
+    let store = FilesystemStore::new(&zarr)?;
+    let mut storage =
+        Storage::from_rdf_and_store_with_config(base.to_str().unwrap(), store, &config)?;
+    storage.append(appended.to_str().unwrap())?;
+    let expected = quads(&storage);
+
+    // The delta is encoded like the base arrays
+    let metadata = std::fs::read_to_string(zarr.join("deltas/0/subject/zarr.json"))?;
+    let codecs = serde_json::from_str::<serde_json::Value>(&metadata)?["codecs"].to_string();
+    for codec in ["sharding_indexed", "zarrdf.delta", "transpose", "zstd"] {
+        assert!(codecs.contains(codec), "{codec} missing from {codecs}");
+    }
+
+    // A compaction that failed halfway left an unreadable array behind,
+    // which the store ignores and the next compaction replaces
+    let leftover = zarr.join("compacted/1/subject");
+    std::fs::create_dir_all(&leftover)?;
+    std::fs::write(leftover.join("zarr.json"), "{")?;
+    assert_eq!(quads(&open(&zarr)), expected);
+
+    storage.compact()?;
+    let metadata = std::fs::read_to_string(leftover.join("zarr.json"))?;
+    let metadata: serde_json::Value = serde_json::from_str(&metadata)?;
+    let shard = &metadata["codecs"][0];
+    assert_eq!(shard["name"], "sharding_indexed");
+    assert_eq!(shard["configuration"]["chunk_shape"][0], 4);
+    assert_eq!(metadata["chunk_grid"]["configuration"]["chunk_shape"][0], 8);
+    let inner = shard["configuration"]["codecs"].to_string();
+    for codec in ["zarrdf.delta", "transpose", "zstd"] {
+        assert!(inner.contains(codec), "{codec} missing from {inner}");
+    }
+    let storage = open(&zarr);
+    storage.verify()?;
+    assert_eq!(quads(&storage), expected);
+
+    // Every compaction moves the store to a new group, and erases the
+    // generation before the previous one
+    let mut storage = storage;
+    storage.delete_matching(None, None, None, None)?;
+    storage.compact()?;
+    assert!(!zarr.join("subject").exists());
+    assert!(zarr.join("compacted/1").exists());
+    assert!(quads(&open(&zarr)).is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_compact_keeps_open_storages() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let base = dir.path().join("base.nq");
+    let appended = dir.path().join("appended.nq");
+    std::fs::write(&base, BASE)?;
+    std::fs::write(&appended, APPENDED)?;
+    let zarr = dir.path().join("open.zarr");
+    let store = FilesystemStore::new(&zarr)?;
+    let mut storage = Storage::from_rdf_and_store(base.to_str().unwrap(), store)?;
+    let before = quads(&storage);
+
+    // A storage opened before a compaction still reads the previous
+    // generation, until it refreshes
+    let mut reader = open(&zarr);
+    assert!(!reader.refresh()?);
+    storage.append(appended.to_str().unwrap())?;
+    storage.compact()?;
+    assert_eq!(quads(&reader), before);
+    assert!(reader.refresh()?);
+    assert!(!reader.refresh()?);
+    assert_eq!(quads(&reader), quads(&storage));
+
+    // Changes committed by another storage are refreshed too
+    let bob = NamedNode::new("http://example.org/b")?.into();
+    storage.delete_matching(Some(&bob), None, None, None)?;
+    assert!(reader.refresh()?);
+    assert_eq!(quads(&reader), quads(&storage));
+    reader.verify()?;
+    Ok(())
+}
@@ -0,0 +1,184 @@
+use std::collections::BTreeSet;
+use std::fs;
+
+use oxrdf::Literal;
+use oxrdf::NamedNode;
+use oxrdf::Variable;
+use zarrdf::bgp::GraphPattern;
+use zarrdf::bgp::TriplePattern;
+use zarrdf::config::ConfigBuilder;
+use zarrdf::config::DictionaryLayout;
+use zarrdf::storage::Storage;
+use zarrs::filesystem::FilesystemStore;
+
+const RDF: &str = "resources/input.ttl";
+
+fn ex(name: &str) -> NamedNode {
+    NamedNode::new_unchecked(format!("http://example.org/{name}"))
+}
+
+fn var(name: &str) -> Variable {
+    Variable::new_unchecked(name)
+}
+
+/// Evaluates the patterns and returns the value of `variables` in each
+/// solution, in N-Triples syntax.
+fn solutions<S>(
+    storage: &Storage<S>,
+    patterns: &[TriplePattern],
+    variables: &[&str],
+) -> Result<BTreeSet<Vec<String>>, Box<dyn std::error::Error>>
+where
+    S: zarrs::storage::ReadableWritableStorageTraits + 'static,
+{
+    let mut result = BTreeSet::new();
+    for solution in storage.solutions(patterns) {
+        let solution = solution?;
+        let values = variables
+            .iter()
+            .map(|variable| solution.get(variable).unwrap().to_string())
+            .collect();
+        result.insert(values);
+    }
+    Ok(result)
+}
+
+#[test]
+fn test_subject_object_join() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    for (layout, output) in [
+        (DictionaryLayout::Separate, "bgp_separate.zarr"),
+        (DictionaryLayout::Shared, "bgp_shared.zarr"),
+    ] {
+        let config = ConfigBuilder::new().dictionary_layout(layout).build();
+        let store = FilesystemStore::new(dir.path().join(output))?;
+        let storage = Storage::from_rdf_and_store_with_config(RDF, store, &config)?;
+
+        let patterns = [
+            TriplePattern::new(var("s"), ex("enrolledIn"), var("c")),
+            TriplePattern::new(var("c"), ex("name"), var("n")),
+        ];
+        let expected = BTreeSet::from([vec![
+            "<http://example.org/a>".to_string(),
+            "<http://example.org/cs101>".to_string(),
+            "\"Computer Science\"".to_string(),
+        ]]);
+        assert_eq!(solutions(&storage, &patterns, &["s", "c", "n"])?, expected);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_merge_join() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let rdf = dir.path().join("input.nt");
+    let mut triples = String::new();
+    for (subject, class) in [
+        ("a", "Student"),
+        ("b", "Student"),
+        ("c", "Student"),
+        ("a", "Person"),
+        ("c", "Person"),
+        ("d", "Person"),
+    ] {
+        triples.push_str(&format!("{} {} {} .\n", ex(subject), ex("type"), ex(class)));
+    }
+    fs::write(&rdf, triples)?;
+    let store = FilesystemStore::new(dir.path().join("output.zarr"))?;
+    let storage = Storage::from_rdf_and_store(rdf.to_str().unwrap(), store)?;
+
+    // Both patterns come sorted by ?x from the predicate index
+    let patterns = [
+        TriplePattern::new(var("x"), ex("type"), ex("Student")),
+        TriplePattern::new(var("x"), ex("type"), ex("Person")),
+    ];
+    let expected = BTreeSet::from([
+        vec!["<http://example.org/a>".to_string()],
+        vec!["<http://example.org/c>".to_string()],
+    ]);
+    assert_eq!(solutions(&storage, &patterns, &["x"])?, expected);
+
+    // The object index is sorted by subject too
+    let patterns = [
+        TriplePattern::new(var("x"), ex("type"), ex("Student")),
+        TriplePattern::new(var("x"), var("p"), ex("Person")),
+    ];
+    let expected = BTreeSet::from([
+        vec!["<http://example.org/a>".to_string(), ex("type").to_string()],
+        vec!["<http://example.org/c>".to_string(), ex("type").to_string()],
+    ]);
+    assert_eq!(solutions(&storage, &patterns, &["x", "p"])?, expected);
+
+    // Every pair of classes sharing a member, including a class with itself
+    let patterns = [
+        TriplePattern::new(var("x"), ex("type"), var("c1")),
+        TriplePattern::new(var("x"), ex("type"), var("c2")),
+    ];
+    assert_eq!(storage.solutions(&patterns).count(), 2 * 4 + 1 + 1);
+    Ok(())
+}
+
+#[test]
+fn test_unmatched_patterns() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let store = FilesystemStore::new(dir.path().join("bgp_unmatched.zarr"))?;
+    let storage = Storage::from_rdf_and_store(RDF, store)?;
+
+    // A constant that is not in the dictionary
+    let patterns = [
+        TriplePattern::new(var("s"), ex("name"), var("n")),
+        TriplePattern::new(var("s"), ex("unknown"), var("o")),
+    ];
+    assert_eq!(storage.solutions(&patterns).count(), 0);
+
+    // A literal can never be a subject
+    let patterns = [
+        TriplePattern::new(var("s"), ex("name"), var("n")),
+        TriplePattern::new(var("n"), var("p"), var("o")),
+    ];
+    assert_eq!(storage.solutions(&patterns).count(), 0);
+
+    // The empty pattern has a single solution binding nothing
+    let empty = storage.solutions(&[]).collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(empty.len(), 1);
+    assert_eq!(empty[0].iter().count(), 0);
+
+    let patterns = [TriplePattern::new(
+        var("s"),
+        ex("name"),
+        Literal::new_simple_literal("Bob"),
+    )];
+    let expected = BTreeSet::from([vec!["<http://example.org/b>".to_string()]]);
+    assert_eq!(solutions(&storage, &patterns, &["s"])?, expected);
+    Ok(())
+}
+
+#[test]
+fn test_selective_pattern_last() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let rdf = dir.path().join("input.nt");
+    let mut triples = String::new();
+    for i in 0..20 {
+        let subject = ex(&format!("s{}", i % 4));
+        triples.push_str(&format!("{subject} {} {} .\n", ex("p"), ex(&i.to_string())));
+    }
+    triples.push_str(&format!("{} {} {} .\n", ex("s1"), ex("q"), ex("rare")));
+    fs::write(&rdf, triples)?;
+    let store = FilesystemStore::new(dir.path().join("output.zarr"))?;
+    let storage = Storage::from_rdf_and_store(rdf.to_str().unwrap(), store)?;
+
+    // The last pattern has the smallest chunk, so it is evaluated first
+    let patterns = [
+        TriplePattern::new(var("x"), ex("p"), var("o")),
+        TriplePattern::new(var("x"), var("r"), var("y")),
+        TriplePattern::new(var("x"), ex("q"), ex("rare")),
+    ];
+    let solutions = storage
+        .solutions(&patterns)
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(solutions.len(), 5 * 6);
+    assert!(solutions
+        .iter()
+        .all(|solution| solution.get("x") == Some(&ex("s1").into())));
+    Ok(())
+}
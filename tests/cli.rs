@@ -0,0 +1,105 @@
+use std::process::Command;
+use std::process::Output;
+
+const RDF: &str = "resources/input.trig";
+
+fn zarrdf(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_zarrdf"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_cli() {
+    let dir = tempfile::tempdir().unwrap();
+    let zarr = dir.path().join("cli.zarr");
+    let zarr = zarr.to_str().unwrap();
+    let output = zarrdf(&["convert", RDF, zarr, "--shared-dictionary"]);
+    assert!(output.status.success());
+
+    let output = zarrdf(&["verify", zarr]);
+    assert!(output.status.success());
+
+    let output = zarrdf(&[
+        "query",
+        zarr,
+        "--subject",
+        "<http://example.org/a>",
+        "--predicate",
+        "<http://example.org/enrolledIn>",
+    ]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "<http://example.org/a> <http://example.org/enrolledIn> <http://example.org/cs101> .\n"
+    );
+
+    let output = zarrdf(&["query", zarr, "--graph", "", "--limit", "1"]);
+    assert_eq!(String::from_utf8(output.stdout).unwrap().lines().count(), 1);
+
+    let output = zarrdf(&["stats", zarr]);
+    let stats = String::from_utf8(output.stdout).unwrap();
+    assert!(stats.contains("quads: 5"));
+    assert!(stats.contains("triples: 4"));
+
+    let output = zarrdf(&["dump", zarr]);
+    assert_eq!(String::from_utf8(output.stdout).unwrap().lines().count(), 5);
+
+    let args = ["--format", "trig", "--memory-budget", "1", "--threads", "1"];
+    let output = zarrdf(&[&["dump", zarr][..], &args].concat());
+    assert!(output.status.success());
+    let trig = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(trig.matches("provenance1 {").count(), 1);
+}
+
+#[test]
+fn test_cli_stats_after_changes() {
+    let dir = tempfile::tempdir().unwrap();
+    let zarr = dir.path().join("changes.zarr");
+    let zarr = zarr.to_str().unwrap();
+    let appended = dir.path().join("appended.nt");
+    std::fs::write(
+        &appended,
+        "<http://example.org/z> <http://example.org/new> \"new\" .\n",
+    )
+    .unwrap();
+    assert!(zarrdf(&["convert", RDF, zarr]).status.success());
+
+    // Terms missing from a delta and removed quads are not counted
+    let output = zarrdf(&["append", appended.to_str().unwrap(), zarr]);
+    assert!(output.status.success());
+    let stats = String::from_utf8(zarrdf(&["stats", zarr]).stdout).unwrap();
+    assert!(stats.contains("quads: 6"));
+    assert!(stats.contains("triples: 5"));
+
+    let predicate = "<http://example.org/enrolledIn>";
+    let output = zarrdf(&["delete", zarr, "--predicate", predicate]);
+    assert!(output.status.success());
+    let stats = String::from_utf8(zarrdf(&["stats", zarr]).stdout).unwrap();
+    assert!(stats.contains("quads: 4"));
+    assert!(stats.contains("triples: 4"));
+}
+
+#[test]
+fn test_cli_exit_codes() {
+    let dir = tempfile::tempdir().unwrap();
+    let unknown = dir.path().join("unknown.zarr");
+    let unknown = unknown.to_str().unwrap();
+    let missing = dir.path().join("missing.zarr");
+    let missing = missing.to_str().unwrap();
+    let output = zarrdf(&["convert", "resources/input.unknown", unknown]);
+    assert_eq!(output.status.code(), Some(3));
+
+    let output = zarrdf(&["convert", "Cargo.toml", unknown]);
+    assert_eq!(output.status.code(), Some(4));
+
+    let output = zarrdf(&["query", missing]);
+    assert_eq!(output.status.code(), Some(5));
+
+    let output = zarrdf(&["query", missing, "--subject", "not a term"]);
+    assert_eq!(output.status.code(), Some(4));
+
+    let output = zarrdf(&["stats"]);
+    assert_eq!(output.status.code(), Some(2));
+}
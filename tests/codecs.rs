@@ -0,0 +1,142 @@
+use std::fs;
+
+use zarrdf::config::BloscCompressor;
+use zarrdf::config::Compression;
+use zarrdf::config::ConfigBuilder;
+use zarrdf::config::Sharding;
+use zarrdf::query::BasicGraphPattern;
+use zarrdf::query::TermId;
+use zarrdf::storage::Storage;
+use zarrs::filesystem::FilesystemStore;
+
+const RDF: &str = "resources/input.trig";
+
+#[test]
+fn test_codecs() -> Result<(), zarrdf::error::ZarrDfError> {
+    let dir = tempfile::tempdir()?;
+    let store = FilesystemStore::new(dir.path().join("codecs_default.zarr"))?;
+    let expected =
+        Storage::from_rdf_and_store(RDF, store)?.quads_matching(None, None, None, None)?;
+
+    let compressions = [
+        Compression::None,
+        Compression::Gzip(9),
+        Compression::Zstd(3),
+        Compression::Blosc(BloscCompressor::LZ4, 5),
+    ];
+    for (i, compression) in compressions.into_iter().enumerate() {
+        for (transpose, delta) in [(false, false), (true, false), (false, true), (true, true)] {
+            let config = ConfigBuilder::new()
+                .compression(compression)
+                .transpose(transpose)
+                .delta(delta)
+                .build();
+            let output = dir
+                .path()
+                .join(format!("codecs_{i}_{transpose}_{delta}.zarr"));
+            Storage::from_rdf_and_store_with_config(RDF, FilesystemStore::new(&output)?, &config)?;
+
+            let storage = Storage::from_zarr(FilesystemStore::new(&output)?)?;
+            storage.verify()?;
+            assert_eq!(storage.quads_matching(None, None, None, None)?, expected);
+
+            // Differences are undone by a codec of the chain, not by ZarrDF
+            let metadata = fs::read_to_string(output.join("subject/zarr.json"))?;
+            assert_eq!(metadata.contains("\"zarrdf.delta\""), delta);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_parse_compression() {
+    assert_eq!("none".parse::<Compression>().ok(), Some(Compression::None));
+    assert_eq!(
+        "gzip".parse::<Compression>().ok(),
+        Some(Compression::Gzip(5))
+    );
+    assert_eq!(
+        "zstd:-1".parse::<Compression>().ok(),
+        Some(Compression::Zstd(-1))
+    );
+    assert_eq!(
+        "blosc:zstd:9".parse::<Compression>().ok(),
+        Some(Compression::Blosc(BloscCompressor::Zstd, 9))
+    );
+    assert!("gzip:fast".parse::<Compression>().is_err());
+    assert!("lzma".parse::<Compression>().is_err());
+}
+
+#[test]
+fn test_sharding() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let rdf = dir.path().join("input.nt");
+    let content: String = (0..100)
+        .map(|i| {
+            format!(
+                "<http://example.org/s{}> <http://example.org/p{}> \"{i}\" .\n",
+                i % 13,
+                i % 3
+            )
+        })
+        .collect();
+    fs::write(&rdf, content)?;
+    let rdf = rdf.to_str().unwrap();
+    let store = FilesystemStore::new(dir.path().join("chunks.zarr"))?;
+    let expected = Storage::from_rdf_and_store(rdf, store)?;
+
+    // Shards of 8 rows, split in inner chunks of 4
+    let sharding = Sharding {
+        chunk_rows: 4,
+        chunks_per_shard: 2,
+    };
+    for (transpose, delta) in [(false, false), (true, true)] {
+        let config = ConfigBuilder::new()
+            .sharding(Some(sharding))
+            .transpose(transpose)
+            .delta(delta)
+            .build();
+        let output = dir.path().join(format!("sharded_{transpose}_{delta}.zarr"));
+        Storage::from_rdf_and_store_with_config(rdf, FilesystemStore::new(&output)?, &config)?;
+
+        let storage = Storage::from_zarr(FilesystemStore::new(&output)?)?;
+        storage.verify()?;
+        assert_eq!(
+            storage.quads_matching(None, None, None, None)?,
+            expected.quads_matching(None, None, None, None)?
+        );
+        for id in 0..storage.dictionary.subjects_len() as u64 {
+            let subject = Some(TermId::new(id));
+            assert_eq!(
+                storage.triples_matching(subject, None, None)?,
+                expected.triples_matching(subject, None, None)?
+            );
+        }
+        assert_eq!(storage.statistics()?, expected.statistics()?);
+
+        // 100 quads in shards of 8 rows
+        let shards = fs::read_dir(output.join("subject").join("c"))?.count();
+        assert_eq!(shards, 13);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_parse_sharding() {
+    assert_eq!(
+        "256".parse::<Sharding>().ok(),
+        Some(Sharding {
+            chunk_rows: 256,
+            chunks_per_shard: 1024
+        })
+    );
+    assert_eq!(
+        "16:4".parse::<Sharding>().ok(),
+        Some(Sharding {
+            chunk_rows: 16,
+            chunks_per_shard: 4
+        })
+    );
+    assert!("0".parse::<Sharding>().is_err());
+    assert!("16:".parse::<Sharding>().is_err());
+}
@@ -0,0 +1,20 @@
+use std::path::Path;
+
+use zarrdf::query::TermPattern;
+use zarrdf::storage::Storage;
+use zarrs::filesystem::FilesystemStore;
+
+/// Every quad of the store in N-Quads, sorted.
+pub fn quads(storage: &Storage<FilesystemStore>) -> Vec<String> {
+    let mut quads: Vec<_> = storage
+        .quads_matching_terms(None, None, None, None)
+        .map(|quad| quad.unwrap().to_string())
+        .collect();
+    quads.sort();
+    quads
+}
+
+/// Opens the store at `path` as it was last committed.
+pub fn open(path: &Path) -> Storage<FilesystemStore> {
+    Storage::from_zarr(FilesystemStore::new(path).unwrap()).unwrap()
+}
@@ -0,0 +1,132 @@
+use oxrdf::GraphName;
+use oxrdf::Literal;
+use oxrdf::NamedNode;
+use oxrdf::Quad;
+use zarrdf::config::Compression;
+use zarrdf::config::ConfigBuilder;
+use zarrdf::query::BasicGraphPattern;
+use zarrdf::storage::Storage;
+use zarrs::filesystem::FilesystemStore;
+
+mod common;
+use common::open;
+use common::quads;
+
+const BASE: &str = "\
+<http://example.org/a> <http://example.org/name> \"Alice\" .
+<http://example.org/a> <http://example.org/knows> <http://example.org/b> .
+<http://example.org/b> <http://example.org/name> \"Bob\" .
+<http://example.org/b> <http://example.org/knows> <http://example.org/a> <http://example.org/g> .
+";
+
+fn ex(name: &str) -> NamedNode {
+    NamedNode::new(format!("http://example.org/{name}")).unwrap()
+}
+
+#[test]
+fn test_delete_and_compact() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let rdf = dir.path().join("base.nq");
+    std::fs::write(&rdf, BASE)?;
+    let zarr = dir.path().join("delete.zarr");
+    let store = FilesystemStore::new(&zarr)?;
+    let mut storage = Storage::from_rdf_and_store(rdf.to_str().unwrap(), store)?;
+
+    let bob = Quad::new(
+        ex("b"),
+        ex("name"),
+        Literal::from("Bob"),
+        GraphName::DefaultGraph,
+    );
+    let missing = Quad::new(
+        ex("b"),
+        ex("name"),
+        Literal::from("Alice"),
+        GraphName::DefaultGraph,
+    );
+    assert_eq!(storage.delete([bob.clone(), missing.clone()])?, 1);
+    assert!(!quads(&storage).contains(&bob.to_string()));
+    assert_eq!(quads(&open(&zarr)), quads(&storage));
+
+    // Deleting nothing writes no delta
+    assert_eq!(storage.delete([bob.clone(), missing])?, 0);
+    assert!(!zarr.join("deltas/1").exists());
+
+    // Triples are removed from every graph unless one is given
+    let knows = ex("knows");
+    assert_eq!(storage.delete_matching(None, Some(&knows), None, None)?, 2);
+    assert_eq!(storage.count_matching(None, None, None)?, 1);
+    let storage = open(&zarr);
+    storage.verify()?;
+    assert_eq!(
+        quads(&storage),
+        ["<http://example.org/a> <http://example.org/name> \"Alice\""]
+    );
+
+    // A removed quad is stored again by a later append
+    let mut storage = storage;
+    storage.append(rdf.to_str().unwrap())?;
+    assert_eq!(quads(&storage).len(), 4);
+    assert_eq!(
+        storage.delete_matching(Some(&ex("b").into()), None, None, None)?,
+        2
+    );
+
+    // Compaction drops the terms only removed quads referenced
+    storage.compact()?;
+    assert!(!zarr.join("compacted/1/deltas").exists());
+    let storage = open(&zarr);
+    storage.verify()?;
+    assert_eq!(
+        quads(&storage),
+        [
+            "<http://example.org/a> <http://example.org/knows> <http://example.org/b>",
+            "<http://example.org/a> <http://example.org/name> \"Alice\"",
+        ]
+    );
+    assert_eq!(storage.dictionary.subjects_len(), 1);
+    assert_eq!(storage.dictionary.get_object_idx("\"Bob\""), None);
+    assert_eq!(storage.dictionary.graphs_len(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_tombstones_keep_config() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let rdf = dir.path().join("base.nq");
+    std::fs::write(&rdf, BASE)?;
+    let config = ConfigBuilder::new()
+        .compression(Compression::Zstd(3))
+        .delta(true)
+        .build();
+    let zarr = dir.path().join("tombstones.zarr");
+    let store = FilesystemStore::new(&zarr)?;
+    let mut storage =
+        Storage::from_rdf_and_store_with_config(rdf.to_str().unwrap(), store, &config)?;
+
+    // Tombstones are encoded like the arrays they are subtracted from
+    let knows = ex("knows");
+    assert_eq!(storage.delete_matching(None, Some(&knows), None, None)?, 2);
+    let bob = Quad::new(
+        ex("b"),
+        ex("name"),
+        Literal::from("Bob"),
+        GraphName::DefaultGraph,
+    );
+    assert_eq!(storage.delete([bob])?, 1);
+    for delta in 0..2 {
+        let path = zarr.join(format!("deltas/{delta}/tombstones/subject/zarr.json"));
+        let codecs = serde_json::from_str::<serde_json::Value>(&std::fs::read_to_string(path)?)?
+            ["codecs"]
+            .to_string();
+        for codec in ["zarrdf.delta", "zstd"] {
+            assert!(codecs.contains(codec), "{codec} missing from {codecs}");
+        }
+        assert!(!codecs.contains("gzip"));
+    }
+    assert_eq!(
+        quads(&open(&zarr)),
+        ["<http://example.org/a> <http://example.org/name> \"Alice\""]
+    );
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use std::sync::Arc;
+
+use zarrdf::error::ZarrDfError;
+use zarrdf::storage::Storage;
+use zarrs::filesystem::FilesystemStore;
+use zarrs::group::Group;
+use zarrs::group::GroupBuilder;
+
+const RDF: &str = "resources/input.trig";
+
+#[test]
+fn test_reopen_dictionary() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let zarr = dir.path().join("dictionary.zarr");
+    let store = FilesystemStore::new(&zarr)?;
+    let built = Storage::from_rdf_and_store(RDF, store)?.dictionary;
+
+    let store = FilesystemStore::new(&zarr)?;
+    let reopened = Storage::from_zarr(store)?.dictionary;
+
+    assert_eq!(
+        reopened.subject_section().iter().collect::<Vec<_>>(),
+        built.subject_section().iter().collect::<Vec<_>>()
+    );
+    assert_eq!(
+        reopened.predicate_section().iter().collect::<Vec<_>>(),
+        built.predicate_section().iter().collect::<Vec<_>>()
+    );
+    assert_eq!(
+        reopened.object_section().iter().collect::<Vec<_>>(),
+        built.object_section().iter().collect::<Vec<_>>()
+    );
+    assert_eq!(
+        reopened.graph_section().iter().collect::<Vec<_>>(),
+        built.graph_section().iter().collect::<Vec<_>>()
+    );
+
+    // The sets returned before sections existed are still available
+    #[allow(deprecated)]
+    let subjects = reopened.subjects().expect("the store has subjects");
+    assert_eq!(subjects.len(), reopened.subjects_len());
+
+    // Terms no longer live in the attributes of the root group
+    let group = Group::open(Arc::new(FilesystemStore::new(&zarr)?), "/")?;
+    assert!(group.attributes().is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_reject_attribute_dictionary() -> Result<(), Box<dyn std::error::Error>> {
+    // Stores of the first version kept their terms in the root attributes
+    let dir = tempfile::tempdir()?;
+    let store = Arc::new(FilesystemStore::new(dir.path())?);
+    let mut group = GroupBuilder::new().build(store, "/")?;
+    group.attributes_mut().insert(
+        "subject".to_string(),
+        serde_json::json!(["<http://example.org/a>"]),
+    );
+    group.store_metadata()?;
+
+    let result = Storage::from_zarr(FilesystemStore::new(dir.path())?);
+    assert!(matches!(result, Err(ZarrDfError::UnsupportedVersion(_))));
+    Ok(())
+}
@@ -0,0 +1,68 @@
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::Read;
+
+use oxrdfio::RdfParser;
+use zarrdf::config::ConfigBuilder;
+use zarrdf::rdf_format::RdfFormat;
+use zarrdf::storage::Storage;
+use zarrs::filesystem::FilesystemStore;
+
+fn parse<R: Read>(
+    read: R,
+    format: RdfFormat,
+) -> Result<BTreeSet<String>, Box<dyn std::error::Error>> {
+    let quads = RdfParser::from_format(format.into())
+        .for_reader(read)
+        .map(|quad| quad.map(|quad| quad.to_string()))
+        .collect::<Result<_, _>>()?;
+    Ok(quads)
+}
+
+#[test]
+fn test_export_triples() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let store = FilesystemStore::new(dir.path().join("export_triples.zarr"))?;
+    let storage = Storage::from_rdf_and_store("resources/input.ttl", store)?;
+    let expected = parse(File::open("resources/input.ttl")?, RdfFormat::Turtle)?;
+
+    for format in [RdfFormat::NTriples, RdfFormat::Turtle, RdfFormat::RdfXml] {
+        let output = storage.export(Vec::new(), format)?;
+        assert_eq!(parse(output.as_slice(), format)?, expected);
+    }
+
+    let turtle = String::from_utf8(storage.export(Vec::new(), RdfFormat::Turtle)?)?;
+    assert!(turtle.contains("@prefix ns1: <http://example.org/>"));
+    Ok(())
+}
+
+#[test]
+fn test_export_quads() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let store = FilesystemStore::new(dir.path().join("export_quads.zarr"))?;
+    let storage = Storage::from_rdf_and_store("resources/input.trig", store)?;
+    let expected = parse(File::open("resources/input.trig")?, RdfFormat::TriG)?;
+
+    for format in [RdfFormat::NQuads, RdfFormat::TriG] {
+        let output = storage.export(Vec::new(), format)?;
+        assert_eq!(parse(output.as_slice(), format)?, expected);
+    }
+
+    // Every graph is written as a single block
+    let trig = String::from_utf8(storage.export(Vec::new(), RdfFormat::TriG)?)?;
+    for graph in ["provenance1", "provenance2"] {
+        assert_eq!(trig.matches(&format!("{graph} {{")).count(), 1);
+    }
+
+    // Quads sorted by graph may spill, as set by the configuration
+    let runs = dir.path().join("runs");
+    std::fs::create_dir(&runs)?;
+    let config = ConfigBuilder::new()
+        .memory_budget(1)
+        .temp_dir(&runs)
+        .threads(1)
+        .build();
+    let output = storage.export_with_config(Vec::new(), RdfFormat::TriG, &config)?;
+    assert_eq!(String::from_utf8(output)?, trig);
+    Ok(())
+}
@@ -0,0 +1,134 @@
+use oxrdf::Literal;
+use oxrdf::NamedNode;
+use oxrdf::Subject;
+use oxrdf::Term;
+use zarrdf::fragments::parse_representation;
+use zarrdf::fragments::representation;
+use zarrdf::query::BasicGraphPattern;
+use zarrdf::rdf_format::RdfFormat;
+use zarrdf::storage::Storage;
+use zarrs::filesystem::FilesystemStore;
+
+const RDF: &str = "resources/input.ttl";
+const ENDPOINT: &str = "http://localhost/fragments";
+
+fn ex(name: &str) -> NamedNode {
+    NamedNode::new_unchecked(format!("http://example.org/{name}"))
+}
+
+#[test]
+fn test_fragment_pages() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let store = FilesystemStore::new(dir.path().join("fragments.zarr"))?;
+    let storage = Storage::from_rdf_and_store(RDF, store)?;
+    let expected = storage.triples_matching(None, None, None)?.len();
+
+    // Pages of the whole dataset cover every triple exactly once
+    let mut triples = Vec::new();
+    for page in 1.. {
+        let fragment = storage.fragment(None, None, None, page, 4)?;
+        assert!(fragment.triples.len() <= 4);
+        assert_eq!(fragment.total, expected as u64);
+        triples.extend(fragment.triples);
+        if !fragment.has_next {
+            break;
+        }
+    }
+    assert_eq!(triples.len(), expected);
+
+    let name = ex("name");
+    let fragment = storage.fragment(None, Some(&name), None, 2, 2)?;
+    assert_eq!(fragment.total, 4);
+    assert_eq!(fragment.triples.len(), 2);
+    assert!(!fragment.has_next);
+
+    let subject = Subject::from(ex("b"));
+    let object = Term::from(Literal::new_simple_literal("Robert"));
+    let fragment = storage.fragment(Some(&subject), None, Some(&object), 1, 2)?;
+    assert_eq!(fragment.total, 1);
+    assert_eq!(fragment.triples[0].predicate, name);
+
+    let fragment = storage.fragment(None, Some(&ex("unknown")), None, 1, 2)?;
+    assert_eq!(fragment.total, 0);
+    assert!(fragment.triples.is_empty());
+
+    // Beyond the last page
+    let fragment = storage.fragment(None, Some(&name), None, 5, 2)?;
+    assert!(fragment.triples.is_empty());
+    assert!(!fragment.has_next);
+    Ok(())
+}
+
+#[test]
+fn test_fragment_triples_in_several_graphs() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let store = FilesystemStore::new(dir.path().join("fragments_graphs.zarr"))?;
+    let storage = Storage::from_rdf_and_store("resources/input.trig", store)?;
+
+    // :a :enrolledIn :cs101 is in two graphs but is a single triple
+    let mut triples = Vec::new();
+    for page in 1.. {
+        let fragment = storage.fragment(None, None, None, page, 2)?;
+        assert_eq!(fragment.total, 4);
+        triples.extend(fragment.triples);
+        if !fragment.has_next {
+            break;
+        }
+    }
+    assert_eq!(triples.len(), 4);
+
+    let enrolled_in = ex("enrolledIn");
+    let fragment = storage.fragment(None, Some(&enrolled_in), None, 1, 2)?;
+    assert_eq!(fragment.total, 1);
+    assert_eq!(fragment.triples.len(), 1);
+    assert!(!fragment.has_next);
+
+    let subject = Subject::from(ex("a"));
+    let fragment = storage.fragment(Some(&subject), None, None, 1, 2)?;
+    assert_eq!(fragment.total, 3);
+    assert_eq!(fragment.triples.len(), 2);
+    assert!(fragment.has_next);
+    Ok(())
+}
+
+#[test]
+fn test_hypermedia_controls() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let store = FilesystemStore::new(dir.path().join("fragments_controls.zarr"))?;
+    let storage = Storage::from_rdf_and_store(RDF, store)?;
+    let name = ex("name");
+    let fragment = storage.fragment(None, Some(&name), None, 2, 1)?;
+
+    let url = fragment.url(ENDPOINT, 3);
+    assert_eq!(
+        url,
+        "http://localhost/fragments?predicate=http%3A%2F%2Fexample.org%2Fname&page=3"
+    );
+
+    let turtle = String::from_utf8(fragment.write(Vec::new(), RdfFormat::Turtle, ENDPOINT)?)?;
+    assert!(turtle.contains("hydra:totalItems 4"));
+    assert!(turtle.contains("hydra:next"));
+    assert!(turtle.contains("hydra:previous"));
+    assert!(turtle.contains("hydra:ExplicitRepresentation"));
+
+    // Controls move to a graph of their own in TriG
+    let trig = String::from_utf8(fragment.write(Vec::new(), RdfFormat::TriG, ENDPOINT)?)?;
+    assert!(trig.contains("page=2#metadata> {"));
+    Ok(())
+}
+
+#[test]
+fn test_explicit_representation() -> Result<(), Box<dyn std::error::Error>> {
+    for term in [
+        Term::from(ex("a")),
+        Literal::new_simple_literal("say \"hi\"").into(),
+        Literal::new_language_tagged_literal("Bob", "en")?.into(),
+        Literal::from(42).into(),
+    ] {
+        assert_eq!(parse_representation(&representation(&term))?, Some(term));
+    }
+    assert_eq!(parse_representation("?s")?, None);
+    assert_eq!(parse_representation("")?, None);
+    assert!(parse_representation("\"x\"@").is_err());
+    Ok(())
+}
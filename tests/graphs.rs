@@ -0,0 +1,64 @@
+use zarrdf::query::BasicGraphPattern;
+use zarrdf::query::DEFAULT_GRAPH;
+use zarrdf::storage::Storage;
+use zarrs::filesystem::FilesystemStore;
+
+const RDF: &str = "resources/input.trig";
+const SUBJECT: &str = "<http://example.org/a>";
+const PREDICATE: &str = "<http://example.org/enrolledIn>";
+const GRAPH: &str = "<http://example.org/provenance1>";
+
+#[test]
+fn test_quads() -> Result<(), zarrdf::error::ZarrDfError> {
+    let dir = tempfile::tempdir()?;
+    let store = FilesystemStore::new(dir.path().join("graphs_quads.zarr"))?;
+    let storage = Storage::from_rdf_and_store(RDF, store)?;
+
+    let quads = storage.quads_matching(None, None, None, None)?;
+    assert_eq!(quads.len(), 5);
+
+    let default_graph = storage.quads_matching(None, None, None, Some(DEFAULT_GRAPH))?;
+    assert_eq!(default_graph.len(), 1);
+
+    let graph = storage.dictionary.get_graph_idx(GRAPH);
+    let named_graph = storage.quads_matching(None, None, None, graph)?;
+    assert_eq!(named_graph.len(), 2);
+
+    let subject = storage.dictionary.get_subject_idx(SUBJECT);
+    let predicate = storage.dictionary.get_predicate_idx(PREDICATE);
+    let quads = storage.quads_matching(subject, predicate, None, None)?;
+    assert_eq!(quads.len(), 2);
+    let quads = storage.quads_matching(None, predicate, None, graph)?;
+    assert_eq!(quads.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_triples_merge_graphs() -> Result<(), zarrdf::error::ZarrDfError> {
+    let dir = tempfile::tempdir()?;
+    let store = FilesystemStore::new(dir.path().join("graphs_triples.zarr"))?;
+    let storage = Storage::from_rdf_and_store(RDF, store)?;
+
+    let triples = storage.triples_matching(None, None, None)?;
+    assert_eq!(triples.len(), 4);
+
+    let predicate = storage.dictionary.get_predicate_idx(PREDICATE);
+    let triples = storage.triples_matching(None, predicate, None)?;
+    assert_eq!(triples.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_reopen_graphs() -> Result<(), zarrdf::error::ZarrDfError> {
+    let dir = tempfile::tempdir()?;
+    let zarr = dir.path().join("graphs_reopen.zarr");
+    let store = FilesystemStore::new(&zarr)?;
+    Storage::from_rdf_and_store(RDF, store)?;
+
+    let store = FilesystemStore::new(&zarr)?;
+    let storage = Storage::from_zarr(store)?;
+    assert_eq!(storage.dictionary.graphs_len(), 3);
+    let graph = storage.dictionary.get_graph_idx(GRAPH);
+    assert_eq!(storage.quads_matching(None, None, None, graph)?.len(), 2);
+    Ok(())
+}
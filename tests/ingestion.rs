@@ -0,0 +1,84 @@
+use zarrdf::config::ConfigBuilder;
+use zarrdf::config::Sharding;
+use zarrdf::query::BasicGraphPattern;
+use zarrdf::storage::Storage;
+use zarrs::filesystem::FilesystemStore;
+
+const RDF: &str = "resources/input.ttl";
+
+#[test]
+fn test_spilled_ingestion() -> Result<(), zarrdf::error::ZarrDfError> {
+    let dir = tempfile::tempdir()?;
+    let store = FilesystemStore::new(dir.path().join("ingestion_in_memory.zarr"))?;
+    let in_memory = Storage::from_rdf_and_store(RDF, store)?;
+
+    // A budget this small spills every term and triple to its own run
+    let config = ConfigBuilder::new().memory_budget(1).build();
+    let store = FilesystemStore::new(dir.path().join("ingestion_spilled.zarr"))?;
+    let spilled = Storage::from_rdf_and_store_with_config(RDF, store, &config)?;
+
+    let expected = in_memory.triples_matching(None, None, None)?;
+    let triples = spilled.triples_matching(None, None, None)?;
+    assert_eq!(triples.len(), 6);
+    assert_eq!(triples, expected);
+
+    // Merging two runs at a time takes several levels
+    let runs = dir.path().join("runs");
+    std::fs::create_dir(&runs)?;
+    let config = ConfigBuilder::new()
+        .memory_budget(1)
+        .merge_fan_in(2)
+        .temp_dir(&runs)
+        .build();
+    let store = FilesystemStore::new(dir.path().join("ingestion_merged.zarr"))?;
+    let merged = Storage::from_rdf_and_store_with_config(RDF, store, &config)?;
+    assert_eq!(merged.triples_matching(None, None, None)?, expected);
+    assert_eq!(
+        merged.dictionary.objects_len(),
+        in_memory.dictionary.objects_len()
+    );
+    assert_eq!(std::fs::read_dir(&runs)?.count(), 0);
+    Ok(())
+}
+
+#[test]
+fn test_parallel_ingestion() -> Result<(), Box<dyn std::error::Error>> {
+    // Enough rows for several batches of every index
+    let dir = tempfile::tempdir()?;
+    let rdf = dir.path().join("input.nt");
+    let content: String = (0..25000)
+        .map(|i| {
+            format!(
+                "<http://example.org/s{}> <http://example.org/p{}> \"{}\" .\n",
+                i / 50,
+                i % 5,
+                i % 50
+            )
+        })
+        .collect();
+    std::fs::write(&rdf, content)?;
+    let rdf = rdf.to_str().unwrap();
+
+    let config = ConfigBuilder::new().threads(1).build();
+    let store = FilesystemStore::new(dir.path().join("sequential.zarr"))?;
+    let sequential = Storage::from_rdf_and_store_with_config(rdf, store, &config)?;
+    let expected = sequential.quads_matching(None, None, None, None)?;
+    assert_eq!(expected.len(), 25000);
+
+    let sharding = Sharding {
+        chunk_rows: 256,
+        chunks_per_shard: 4,
+    };
+    for sharding in [None, Some(sharding)] {
+        let config = ConfigBuilder::new().threads(4).sharding(sharding).build();
+        let output = dir
+            .path()
+            .join(format!("parallel_{}.zarr", sharding.is_some()));
+        Storage::from_rdf_and_store_with_config(rdf, FilesystemStore::new(&output)?, &config)?;
+
+        let parallel = Storage::from_zarr(FilesystemStore::new(&output)?)?;
+        parallel.verify()?;
+        assert_eq!(parallel.quads_matching(None, None, None, None)?, expected);
+    }
+    Ok(())
+}
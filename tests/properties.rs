@@ -0,0 +1,98 @@
+use std::fs;
+
+use proptest::prelude::*;
+use zarrdf::config::ConfigBuilder;
+use zarrdf::config::DictionaryLayout;
+use zarrdf::query::BasicGraphPattern;
+use zarrdf::query::TermId;
+use zarrdf::storage::Storage;
+use zarrs::filesystem::FilesystemStore;
+
+fn subject(id: u8) -> String {
+    format!("<http://example.org/s{id}>")
+}
+
+fn predicate(id: u8) -> String {
+    format!("<http://example.org/p{id}>")
+}
+
+fn object(id: u8) -> String {
+    match id % 2 {
+        0 => subject(id),
+        _ => format!("\"o{id}\"@en"),
+    }
+}
+
+fn triples() -> impl Strategy<Value = Vec<(u8, u8, u8)>> {
+    prop::collection::vec((0..6u8, 0..3u8, 0..8u8), 1..40)
+}
+
+fn check_all_patterns(
+    triples: Vec<(u8, u8, u8)>,
+    dictionary_layout: DictionaryLayout,
+) -> Result<(), TestCaseError> {
+    let dir = tempfile::tempdir().unwrap();
+    let rdf = dir.path().join("input.nt");
+    let rdf_content: String = triples
+        .iter()
+        .map(|&(s, p, o)| format!("{} {} {} .\n", subject(s), predicate(p), object(o)))
+        .collect();
+    fs::write(&rdf, rdf_content).unwrap();
+
+    let store = FilesystemStore::new(dir.path().join("output.zarr")).unwrap();
+    let config = ConfigBuilder::new()
+        .dictionary_layout(dictionary_layout)
+        .build();
+    let storage =
+        Storage::from_rdf_and_store_with_config(rdf.to_str().unwrap(), store, &config).unwrap();
+    let dictionary = &storage.dictionary;
+
+    let mut encoded: Vec<_> = triples
+        .iter()
+        .map(|&(s, p, o)| {
+            [
+                dictionary.get_subject_idx(&subject(s)).unwrap(),
+                dictionary.get_predicate_idx(&predicate(p)).unwrap(),
+                dictionary.get_object_idx(&object(o)).unwrap(),
+            ]
+        })
+        .collect();
+    encoded.sort();
+    encoded.dedup();
+
+    let subjects = (0..dictionary.subjects_len() as u64).map(|id| Some(TermId::new(id)));
+    let predicates = (0..dictionary.predicates_len() as u64).map(|id| Some(TermId::new(id)));
+    let objects = (0..dictionary.objects_len() as u64).map(|id| Some(TermId::new(id)));
+
+    for s in subjects.chain([None]) {
+        for p in predicates.clone().chain([None]) {
+            for o in objects.clone().chain([None]) {
+                let mut expected: Vec<_> = encoded
+                    .iter()
+                    .filter(|[ts, tp, to]| {
+                        s.is_none_or(|s| s == *ts)
+                            && p.is_none_or(|p| p == *tp)
+                            && o.is_none_or(|o| o == *to)
+                    })
+                    .cloned()
+                    .collect();
+                let mut triples = storage.triples_matching(s, p, o).unwrap();
+                expected.sort();
+                triples.sort();
+                prop_assert_eq!(triples, expected, "pattern {:?}", (s, p, o));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(16))]
+
+    #[test]
+    fn test_triples_matching_agrees_with_scan(triples in triples()) {
+        check_all_patterns(triples.clone(), DictionaryLayout::Separate)?;
+        check_all_patterns(triples, DictionaryLayout::Shared)?;
+    }
+}
@@ -0,0 +1,70 @@
+use std::ops::ControlFlow;
+
+use zarrdf::config::ConfigBuilder;
+use zarrdf::query::BasicGraphPattern;
+use zarrdf::scan::ScanOrder;
+use zarrdf::scan::PREFETCH;
+use zarrdf::storage::Storage;
+use zarrs::filesystem::FilesystemStore;
+
+#[test]
+fn test_scan() -> Result<(), Box<dyn std::error::Error>> {
+    // Many more chunks than are read ahead
+    let dir = tempfile::tempdir()?;
+    let rdf = dir.path().join("input.nt");
+    let subjects = 4 * PREFETCH;
+    let content: String = (0..subjects * 5)
+        .map(|i| {
+            format!(
+                "<http://example.org/s{}> <http://example.org/p{}> \"{}\" .\n",
+                i / 5,
+                i % 3,
+                i % 5
+            )
+        })
+        .collect();
+    std::fs::write(&rdf, content)?;
+    let store = FilesystemStore::new(dir.path().join("output.zarr"))?;
+    let storage = Storage::from_rdf_and_store(rdf.to_str().unwrap(), store)?;
+    let expected = storage.quads_matching(None, None, None, None)?;
+
+    let mut quads = Vec::new();
+    let stopped = storage.scan(ScanOrder::Ordered, |quad| {
+        quads.push(quad);
+        ControlFlow::<()>::Continue(())
+    })?;
+    assert_eq!(stopped, None);
+    assert_eq!(quads, expected);
+
+    let mut quads = Vec::new();
+    storage.scan(ScanOrder::Unordered, |quad| {
+        quads.push(quad);
+        ControlFlow::<()>::Continue(())
+    })?;
+    quads.sort();
+    assert_eq!(quads, expected);
+
+    // A single reader still gets ahead of the visits
+    let config = ConfigBuilder::new().threads(1).build();
+    let mut quads = Vec::new();
+    storage.scan_with_config(ScanOrder::Ordered, &config, |quad| {
+        quads.push(quad);
+        ControlFlow::<()>::Continue(())
+    })?;
+    assert_eq!(quads, expected);
+
+    // Breaking stops the scan with its value
+    for order in [ScanOrder::Ordered, ScanOrder::Unordered] {
+        let mut visited = 0;
+        let stopped = storage.scan(order, |_| {
+            visited += 1;
+            match visited {
+                10 => ControlFlow::Break("tenth"),
+                _ => ControlFlow::Continue(()),
+            }
+        })?;
+        assert_eq!(stopped, Some("tenth"));
+        assert_eq!(visited, 10);
+    }
+    Ok(())
+}
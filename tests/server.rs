@@ -0,0 +1,218 @@
+use std::io::Read;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::net::TcpStream;
+use std::sync::mpsc;
+use std::thread;
+
+use zarrdf::server::Server;
+use zarrdf::storage::Storage;
+use zarrs::filesystem::FilesystemStore;
+
+const RDF: &str = "resources/input.ttl";
+const SELECT: &str =
+    "SELECT ?n WHERE { <http://example.org/b> <http://example.org/name> ?n } ORDER BY ?n";
+
+/// Serves the store on a free port from a background thread.
+fn serve() -> SocketAddr {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FilesystemStore::new(dir.path()).unwrap();
+        let storage = Storage::from_rdf_and_store(RDF, store).unwrap();
+        let server = Server::bind("127.0.0.1:0", storage).unwrap();
+        sender.send(server.local_addr().unwrap()).unwrap();
+        server.serve().unwrap();
+    });
+    receiver.recv().unwrap()
+}
+
+/// Sends an HTTP request and returns the status, content type and body of
+/// the response.
+fn request(
+    address: SocketAddr,
+    method: &str,
+    target: &str,
+    headers: &[(&str, &str)],
+    body: &str,
+) -> (u16, String, String) {
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(stream, "{method} {target} HTTP/1.0\r\n").unwrap();
+    for (name, value) in headers {
+        write!(stream, "{name}: {value}\r\n").unwrap();
+    }
+    write!(stream, "Content-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+    let content_type = head
+        .lines()
+        .find_map(|line| line.strip_prefix("Content-Type: "))
+        .unwrap_or_default()
+        .to_string();
+    (status, content_type, body.to_string())
+}
+
+fn encode(query: &str) -> String {
+    query
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' => (byte as char).to_string(),
+            byte => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+#[test]
+fn test_sparql_protocol() {
+    let address = serve();
+    let target = format!("/sparql?query={}", encode(SELECT));
+
+    let (status, content_type, body) = request(address, "GET", &target, &[], "");
+    assert_eq!(status, 200);
+    assert_eq!(content_type, "application/sparql-results+json");
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["head"]["vars"], serde_json::json!(["n"]));
+    assert_eq!(
+        json["results"]["bindings"][0]["n"],
+        serde_json::json!({ "type": "literal", "value": "Bob" })
+    );
+
+    let form = format!("query={}", encode(SELECT));
+    let headers = [
+        ("Content-Type", "application/x-www-form-urlencoded"),
+        ("Accept", "text/csv;q=0.5, application/sparql-results+xml"),
+    ];
+    let (status, content_type, body) = request(address, "POST", "/sparql", &headers, &form);
+    assert_eq!(status, 200);
+    assert_eq!(content_type, "application/sparql-results+xml");
+    assert!(body.contains("<binding name=\"n\"><literal>Robert</literal></binding>"));
+
+    let headers = [
+        ("Content-Type", "application/sparql-query"),
+        ("Accept", "text/csv"),
+    ];
+    let (_, _, body) = request(address, "POST", "/sparql", &headers, SELECT);
+    assert_eq!(body, "n\r\nBob\r\nRobert\r\n");
+
+    let headers = [
+        ("Content-Type", "application/sparql-query"),
+        ("Accept", "text/tab-separated-values"),
+    ];
+    let (_, _, body) = request(address, "POST", "/sparql", &headers, SELECT);
+    assert_eq!(body, "?n\n\"Bob\"\n\"Robert\"\n");
+
+    let ask = "ASK { ?s <http://example.org/enrolledIn> ?c }";
+    let headers = [("Content-Type", "application/sparql-query")];
+    let (_, _, body) = request(address, "POST", "/sparql", &headers, ask);
+    assert_eq!(body, r#"{"head":{},"boolean":true}"#);
+
+    let construct = "CONSTRUCT WHERE { ?s <http://example.org/enrolledIn> ?c }";
+    let headers = [
+        ("Content-Type", "application/sparql-query"),
+        ("Accept", "application/n-triples"),
+    ];
+    let (status, content_type, body) = request(address, "POST", "/sparql", &headers, construct);
+    assert_eq!(status, 200);
+    assert_eq!(content_type, "application/n-triples");
+    assert_eq!(
+        body,
+        "<http://example.org/a> <http://example.org/enrolledIn> <http://example.org/cs101> .\n"
+    );
+}
+
+#[test]
+fn test_store_changed_while_served() {
+    let dir = tempfile::tempdir().unwrap();
+    let zarr = dir.path().join("served.zarr");
+    let mut storage =
+        Storage::from_rdf_and_store(RDF, FilesystemStore::new(&zarr).unwrap()).unwrap();
+    let (sender, receiver) = mpsc::channel();
+    let served = zarr.clone();
+    thread::spawn(move || {
+        let storage = Storage::from_zarr(FilesystemStore::new(served).unwrap()).unwrap();
+        let server = Server::bind("127.0.0.1:0", storage).unwrap();
+        sender.send(server.local_addr().unwrap()).unwrap();
+        server.serve().unwrap();
+    });
+    let address = receiver.recv().unwrap();
+    let headers = [
+        ("Content-Type", "application/sparql-query"),
+        ("Accept", "text/csv"),
+    ];
+
+    // Every compaction renumbers the terms and erases an older generation,
+    // which the server follows
+    for (name, expected) in [
+        ("Bobby", "n\r\nBob\r\nBobby\r\nRobert\r\n"),
+        ("Rob", "n\r\nBob\r\nBobby\r\nRob\r\nRobert\r\n"),
+    ] {
+        storage
+            .sparql_update(&format!(
+                "INSERT DATA {{ <http://example.org/b> <http://example.org/name> \"{name}\" }}"
+            ))
+            .unwrap();
+        let (status, _, body) = request(address, "POST", "/sparql", &headers, SELECT);
+        assert_eq!((status, body.as_str()), (200, expected));
+        storage.compact().unwrap();
+        let (status, _, body) = request(address, "POST", "/sparql", &headers, SELECT);
+        assert_eq!((status, body.as_str()), (200, expected));
+        let (status, _, _) = request(
+            address,
+            "GET",
+            "/fragments?subject=http%3A%2F%2Fexample.org%2Fb",
+            &[],
+            "",
+        );
+        assert_eq!(status, 200);
+    }
+}
+
+#[test]
+fn test_sparql_protocol_errors() {
+    let address = serve();
+
+    let target = format!("/sparql?query={}", encode("SELECT ?s WHERE {"));
+    assert_eq!(request(address, "GET", &target, &[], "").0, 400);
+    assert_eq!(request(address, "GET", "/sparql", &[], "").0, 400);
+    assert_eq!(request(address, "GET", "/unknown", &[], "").0, 404);
+    assert_eq!(request(address, "PUT", "/sparql", &[], "").0, 405);
+
+    let headers = [("Content-Type", "text/plain")];
+    assert_eq!(request(address, "POST", "/sparql", &headers, SELECT).0, 415);
+
+    // Booleans have no CSV serialization
+    let target = format!("/sparql?query={}", encode("ASK {}"));
+    assert_eq!(
+        request(address, "GET", &target, &[("Accept", "text/csv")], "").0,
+        406
+    );
+}
+
+#[test]
+fn test_triple_pattern_fragments() {
+    let address = serve();
+
+    let target = format!(
+        "/fragments?subject={}&predicate=",
+        encode("http://example.org/b")
+    );
+    let headers = [("Host", "example.org:8080")];
+    let (status, content_type, body) = request(address, "GET", &target, &headers, "");
+    assert_eq!(status, 200);
+    assert_eq!(content_type, "text/turtle");
+    assert!(body.contains("\"Robert\""));
+    assert!(body.contains("hydra:totalItems 2"));
+    assert!(body.contains("<http://example.org:8080/fragments#dataset>"));
+
+    let headers = [("Accept", "application/trig")];
+    let (status, content_type, _) = request(address, "GET", "/fragments", &headers, "");
+    assert_eq!(status, 200);
+    assert_eq!(content_type, "application/trig");
+
+    assert_eq!(request(address, "GET", "/fragments?page=0", &[], "").0, 400);
+    let target = format!("/fragments?predicate={}", encode("\"literal\""));
+    assert_eq!(request(address, "GET", &target, &[], "").0, 400);
+}
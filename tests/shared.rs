@@ -0,0 +1,67 @@
+use zarrdf::config::ConfigBuilder;
+use zarrdf::config::DictionaryLayout;
+use zarrdf::query::BasicGraphPattern;
+use zarrdf::query::TermId;
+use zarrdf::storage::Storage;
+use zarrs::filesystem::FilesystemStore;
+
+const RDF: &str = "resources/input.ttl";
+const COURSE: &str = "<http://example.org/cs101>";
+const ENROLLED_IN: &str = "<http://example.org/enrolledIn>";
+const NAME: &str = "<http://example.org/name>";
+
+#[test]
+fn test_shared_dictionary() -> Result<(), zarrdf::error::ZarrDfError> {
+    let dir = tempfile::tempdir()?;
+    let zarr = dir.path().join("shared.zarr");
+    let config = ConfigBuilder::new()
+        .dictionary_layout(DictionaryLayout::Shared)
+        .build();
+    let store = FilesystemStore::new(&zarr)?;
+    Storage::from_rdf_and_store_with_config(RDF, store, &config)?;
+    let storage = Storage::from_zarr(FilesystemStore::new(&zarr)?)?;
+    let dictionary = &storage.dictionary;
+
+    // :cs101 is the only term that is both a subject and an object
+    assert_eq!(dictionary.shared_len(), 1);
+    assert_eq!(dictionary.get_subject_idx(COURSE), Some(TermId::new(0)));
+    assert_eq!(dictionary.get_object_idx(COURSE), Some(TermId::new(0)));
+    assert_eq!(dictionary.subjects_len(), 3);
+    assert_eq!(dictionary.objects_len(), 6);
+
+    // The sets of a role include the shared terms
+    #[allow(deprecated)]
+    let (subjects, objects) = (dictionary.subjects(), dictionary.objects());
+    assert_eq!(subjects.map(|set| set.len()), Some(3));
+    assert_eq!(objects.map(|set| set.len()), Some(6));
+
+    // Following :enrolledIn needs no lookup by term
+    let enrolled_in = dictionary.get_predicate_idx(ENROLLED_IN);
+    let name = dictionary.get_predicate_idx(NAME);
+    let [[_, _, course]] = storage.triples_matching(None, enrolled_in, None)?[..] else {
+        panic!("expected a single enrollment");
+    };
+    let course = dictionary.object_as_subject(course);
+    assert_eq!(course, Some(TermId::new(0)));
+    let names = storage.triples_matching(course, name, None)?;
+    assert_eq!(
+        dictionary.object_term(names[0][2])?.to_string(),
+        "\"Computer Science\""
+    );
+    Ok(())
+}
+
+#[test]
+fn test_separate_role_conversion() -> Result<(), zarrdf::error::ZarrDfError> {
+    let dir = tempfile::tempdir()?;
+    let store = FilesystemStore::new(dir.path().join("separate.zarr"))?;
+    let storage = Storage::from_rdf_and_store(RDF, store)?;
+    let dictionary = &storage.dictionary;
+
+    assert_eq!(dictionary.shared_len(), 0);
+    let object = dictionary.get_object_idx(COURSE).unwrap();
+    let subject = dictionary.get_subject_idx(COURSE);
+    assert_eq!(dictionary.object_as_subject(object), subject);
+    assert_eq!(dictionary.subject_as_object(subject.unwrap()), Some(object));
+    Ok(())
+}
@@ -0,0 +1,292 @@
+use std::fs;
+
+use oxrdf::vocab::xsd;
+use oxrdf::Literal;
+use oxrdf::NamedNode;
+use oxrdf::Term;
+use tempfile::TempDir;
+use zarrdf::error::ZarrDfError;
+use zarrdf::sparql::QueryResults;
+use zarrdf::storage::Storage;
+use zarrs::filesystem::FilesystemStore;
+
+const DATA: &str = r#"
+@prefix : <http://example.org/> .
+@prefix xsd: <http://www.w3.org/2001/XMLSchema#> .
+
+:alice :name "Alice" ; :age 30 ; :knows :bob, :carol ; :dept :sales .
+:bob :name "Bob" ; :age 25 ; :knows :carol ; :dept :sales .
+:carol :name "Carol"@en ; :age 35 ; :dept :it .
+:dave :name "Dave" .
+"#;
+
+fn storage(dir: &TempDir) -> Result<Storage<FilesystemStore>, Box<dyn std::error::Error>> {
+    let rdf = dir.path().join("input.ttl");
+    fs::write(&rdf, DATA)?;
+    let store = FilesystemStore::new(dir.path().join("output.zarr"))?;
+    Ok(Storage::from_rdf_and_store(rdf.to_str().unwrap(), store)?)
+}
+
+fn ex(name: &str) -> Term {
+    NamedNode::new_unchecked(format!("http://example.org/{name}")).into()
+}
+
+/// Runs a `SELECT` and returns the values of each solution in order, in
+/// N-Triples syntax, with `UNDEF` for unbound variables.
+fn select(
+    storage: &Storage<FilesystemStore>,
+    query: &str,
+) -> Result<Vec<Vec<String>>, Box<dyn std::error::Error>> {
+    let QueryResults::Solutions(variables, solutions) = storage.sparql(query)? else {
+        panic!("expected solutions");
+    };
+    Ok(solutions
+        .iter()
+        .map(|solution| {
+            variables
+                .iter()
+                .map(|variable| match solution.get(variable.as_str()) {
+                    Some(term) => term.to_string(),
+                    None => "UNDEF".to_string(),
+                })
+                .collect()
+        })
+        .collect())
+}
+
+fn rows(rows: &[&[&str]]) -> Vec<Vec<String>> {
+    rows.iter()
+        .map(|row| row.iter().map(|value| value.to_string()).collect())
+        .collect()
+}
+
+#[test]
+fn test_select() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let storage = storage(&dir)?;
+
+    let query = "PREFIX : <http://example.org/>
+        SELECT ?name WHERE { :alice :knows ?p . ?p :name ?name } ORDER BY ?name";
+    let expected = rows(&[&["\"Bob\""], &["\"Carol\"@en"]]);
+    assert_eq!(select(&storage, query)?, expected);
+
+    // Numeric comparison, and an expression in the projection
+    let query = "PREFIX : <http://example.org/>
+        SELECT ?p (?age + 1 AS ?next) WHERE { ?p :age ?age FILTER(?age >= 30) }
+        ORDER BY DESC(?age)";
+    let expected = vec![
+        vec![
+            "<http://example.org/carol>".to_string(),
+            Literal::from(36i64).to_string(),
+        ],
+        vec![
+            "<http://example.org/alice>".to_string(),
+            Literal::from(31i64).to_string(),
+        ],
+    ];
+    assert_eq!(select(&storage, query)?, expected);
+
+    let query = "PREFIX : <http://example.org/>
+        SELECT ?p WHERE { ?p :name ?n FILTER(regex(str(?n), \"^[AB]\")) } ORDER BY ?p";
+    let expected = rows(&[
+        &["<http://example.org/alice>"],
+        &["<http://example.org/bob>"],
+    ]);
+    assert_eq!(select(&storage, query)?, expected);
+
+    let query = "SELECT * WHERE { ?s <http://example.org/dept> ?d } ORDER BY ?s LIMIT 1 OFFSET 1";
+    let expected = rows(&[&["<http://example.org/bob>", "<http://example.org/sales>"]]);
+    assert_eq!(select(&storage, query)?, expected);
+
+    let query = "SELECT DISTINCT ?d WHERE { ?s <http://example.org/dept> ?d }";
+    assert_eq!(select(&storage, query)?.len(), 2);
+
+    let query = "PREFIX : <http://example.org/>
+        SELECT ?label WHERE {
+            VALUES ?p { :bob :dave }
+            { SELECT ?p ?n WHERE { ?p :name ?n } }
+            BIND(CONCAT(UCASE(?n), \"!\") AS ?label)
+        } ORDER BY ?label";
+    let expected = rows(&[&["\"BOB!\""], &["\"DAVE!\""]]);
+    assert_eq!(select(&storage, query)?, expected);
+    Ok(())
+}
+
+#[test]
+fn test_optional_union_minus() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let storage = storage(&dir)?;
+
+    let query = "PREFIX : <http://example.org/>
+        SELECT ?p ?age WHERE { ?p :name ?n OPTIONAL { ?p :age ?age } } ORDER BY ?p";
+    let expected = [
+        ("alice", "30"),
+        ("bob", "25"),
+        ("carol", "35"),
+        ("dave", "UNDEF"),
+    ]
+    .into_iter()
+    .map(|(p, age)| {
+        let age = match age {
+            "UNDEF" => age.to_string(),
+            age => Literal::new_typed_literal(age, xsd::INTEGER).to_string(),
+        };
+        vec![ex(p).to_string(), age]
+    })
+    .collect::<Vec<_>>();
+    assert_eq!(select(&storage, query)?, expected);
+
+    let query = "PREFIX : <http://example.org/>
+        SELECT ?p WHERE { { ?p :dept :it } UNION { ?p :knows :bob } } ORDER BY ?p";
+    let expected = rows(&[
+        &["<http://example.org/alice>"],
+        &["<http://example.org/carol>"],
+    ]);
+    assert_eq!(select(&storage, query)?, expected);
+
+    let query = "PREFIX : <http://example.org/>
+        SELECT ?p WHERE { ?p :name ?n MINUS { ?p :knows ?q } FILTER NOT EXISTS { ?p :dept :it } }";
+    let expected = rows(&[&["<http://example.org/dave>"]]);
+    assert_eq!(select(&storage, query)?, expected);
+    Ok(())
+}
+
+#[test]
+fn test_aggregates() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let storage = storage(&dir)?;
+
+    let query = "PREFIX : <http://example.org/>
+        SELECT ?d (COUNT(?p) AS ?n) (AVG(?age) AS ?avg) (MAX(?age) AS ?max)
+        WHERE { ?p :dept ?d ; :age ?age }
+        GROUP BY ?d HAVING (COUNT(?p) > 1)";
+    let expected = vec![vec![
+        "<http://example.org/sales>".to_string(),
+        Literal::from(2i64).to_string(),
+        Literal::new_typed_literal("27.5", xsd::DECIMAL).to_string(),
+        Literal::from(30i64).to_string(),
+    ]];
+    assert_eq!(select(&storage, query)?, expected);
+
+    // Without GROUP BY there is a single group, even with no solutions
+    let query = "SELECT (COUNT(*) AS ?n) WHERE { ?s <http://example.org/missing> ?o }";
+    let QueryResults::Solutions(_, solutions) = storage.sparql(query)? else {
+        panic!("expected solutions");
+    };
+    assert_eq!(solutions.len(), 1);
+    assert_eq!(solutions[0].get("n"), Some(&Literal::from(0i64).into()));
+
+    // Decimals are exact, unlike doubles
+    let query = "SELECT (0.1 + 0.2 AS ?sum) (1 / 3 AS ?third) (ROUND(-2.5) AS ?round) WHERE {}";
+    let expected = vec![vec![
+        Literal::new_typed_literal("0.3", xsd::DECIMAL).to_string(),
+        Literal::new_typed_literal("0.333333333333333333", xsd::DECIMAL).to_string(),
+        Literal::new_typed_literal("-2.0", xsd::DECIMAL).to_string(),
+    ]];
+    assert_eq!(select(&storage, query)?, expected);
+    let query = "ASK { FILTER(0.1 + 0.2 = 0.3 && 0.1e0 + 0.2e0 != 0.3e0) }";
+    assert_eq!(storage.sparql(query)?, QueryResults::Boolean(true));
+    Ok(())
+}
+
+#[test]
+fn test_ask_construct_describe() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let storage = storage(&dir)?;
+
+    let query = "ASK { <http://example.org/bob> <http://example.org/knows> ?p }";
+    assert_eq!(storage.sparql(query)?, QueryResults::Boolean(true));
+    let query = "ASK { <http://example.org/dave> <http://example.org/knows> ?p }";
+    assert_eq!(storage.sparql(query)?, QueryResults::Boolean(false));
+
+    let query = "PREFIX : <http://example.org/>
+        CONSTRUCT { ?q :knownBy ?p } WHERE { ?p :knows ?q }";
+    let QueryResults::Graph(triples) = storage.sparql(query)? else {
+        panic!("expected a graph");
+    };
+    assert_eq!(triples.len(), 3);
+    assert!(triples
+        .iter()
+        .all(|triple| Term::from(triple.predicate.clone()) == ex("knownBy")));
+
+    // Every solution gets its own blank node
+    let query = "PREFIX : <http://example.org/>
+        CONSTRUCT { [] :member ?p } WHERE { ?p :dept :sales }";
+    let QueryResults::Graph(triples) = storage.sparql(query)? else {
+        panic!("expected a graph");
+    };
+    assert_eq!(triples.len(), 2);
+    assert_ne!(triples[0].subject, triples[1].subject);
+
+    let query = "DESCRIBE <http://example.org/bob>";
+    let QueryResults::Graph(triples) = storage.sparql(query)? else {
+        panic!("expected a graph");
+    };
+    assert_eq!(triples.len(), 4);
+    Ok(())
+}
+
+#[test]
+fn test_graphs() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let store = FilesystemStore::new(dir.path().join("graphs.zarr"))?;
+    let storage = Storage::from_rdf_and_store("resources/input.trig", store)?;
+    let provenance1 = "<http://example.org/provenance1>";
+    let provenance2 = "<http://example.org/provenance2>";
+
+    // A graph variable ranges over the named graphs holding the triples
+    let query = "PREFIX : <http://example.org/>
+        SELECT ?g WHERE { GRAPH ?g { :a :enrolledIn :cs101 } } ORDER BY ?g";
+    let expected = rows(&[&[provenance1], &[provenance2]]);
+    assert_eq!(select(&storage, query)?, expected);
+
+    let query = "PREFIX : <http://example.org/>
+        SELECT ?name WHERE { GRAPH :provenance2 { ?s :name ?name } }";
+    let expected = rows(&[&["\"Computer Science\""]]);
+    assert_eq!(select(&storage, query)?, expected);
+
+    // Patterns outside of GRAPH match the default graph
+    let query = "PREFIX : <http://example.org/>
+        SELECT ?g ?name WHERE { GRAPH ?g { :a :birthdate ?date } :a :name ?name }";
+    let expected = rows(&[&[provenance1, "\"Alice\""]]);
+    assert_eq!(select(&storage, query)?, expected);
+
+    // FROM merges graphs into the default graph, without duplicates
+    let query = "PREFIX : <http://example.org/>
+        SELECT (COUNT(*) AS ?n) FROM :provenance1 FROM :provenance2 WHERE { ?s ?p ?o }";
+    let expected = rows(&[&[&Literal::from(3i64).to_string()]]);
+    assert_eq!(select(&storage, query)?, expected);
+
+    // FROM NAMED restricts the named graphs and leaves the default one empty
+    let query = "PREFIX : <http://example.org/>
+        SELECT DISTINCT ?g FROM NAMED :provenance2 WHERE { GRAPH ?g { ?s ?p ?o } }";
+    assert_eq!(select(&storage, query)?, rows(&[&[provenance2]]));
+    let query = "PREFIX : <http://example.org/>
+        ASK FROM NAMED :provenance2 WHERE { ?s ?p ?o }";
+    assert_eq!(storage.sparql(query)?, QueryResults::Boolean(false));
+    Ok(())
+}
+
+#[test]
+fn test_invalid_queries() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let storage = storage(&dir)?;
+
+    for query in [
+        "SELECT ?s WHERE { ?s ?p }",
+        "SELECT ?s WHERE { ?s ?p ?o",
+        "SELECT WHERE { ?s ?p ?o }",
+        "PREFIX : <http://example.org/> SELECT * WHERE { ?s ex:p ?o }",
+    ] {
+        assert!(
+            matches!(storage.sparql(query), Err(ZarrDfError::SparqlSyntax(_))),
+            "{query}"
+        );
+    }
+    let query = "SELECT * WHERE { SERVICE <http://example.org/> { ?s ?p ?o } }";
+    assert!(matches!(
+        storage.sparql(query),
+        Err(ZarrDfError::UnsupportedQuery(_))
+    ));
+    Ok(())
+}
@@ -0,0 +1,156 @@
+use oxrdf::NamedNode;
+use zarrdf::query::BasicGraphPattern;
+use zarrdf::query::TermId;
+use zarrdf::statistics::Index;
+use zarrdf::storage::Storage;
+use zarrs::filesystem::FilesystemStore;
+
+/// Checks every pattern with at most one bound component against a scan.
+fn assert_counts_agree<S>(storage: &Storage<S>) -> Result<(), Box<dyn std::error::Error>>
+where
+    S: zarrs::storage::ReadableWritableStorageTraits + 'static,
+{
+    let dictionary = &storage.dictionary;
+    let scan = |s, p, o| storage.triples_matching_iter(s, p, o).count() as u64;
+    assert_eq!(
+        storage.count_matching(None, None, None)?,
+        scan(None, None, None)
+    );
+    for id in (0..dictionary.subjects_len()).map(|id| Some(TermId::new(id as u64))) {
+        assert_eq!(
+            storage.count_matching(id, None, None)?,
+            scan(id, None, None)
+        );
+    }
+    for id in (0..dictionary.predicates_len()).map(|id| Some(TermId::new(id as u64))) {
+        assert_eq!(
+            storage.count_matching(None, id, None)?,
+            scan(None, id, None)
+        );
+    }
+    for id in (0..dictionary.objects_len()).map(|id| Some(TermId::new(id as u64))) {
+        assert_eq!(
+            storage.count_matching(None, None, id)?,
+            scan(None, None, id)
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_count_matching() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let store = FilesystemStore::new(dir.path().join("statistics.zarr"))?;
+    let storage = Storage::from_rdf_and_store("resources/input.ttl", store)?;
+    assert_counts_agree(&storage)?;
+
+    let dictionary = &storage.dictionary;
+    let a = dictionary.get_subject_idx("<http://example.org/a>");
+    let name = dictionary.get_predicate_idx("<http://example.org/name>");
+    assert_eq!(storage.count_matching(None, name, None)?, 4);
+    assert_eq!(storage.count_matching(a, name, None)?, 1);
+
+    // A triple in several graphs is counted once
+    let store = FilesystemStore::new(dir.path().join("statistics_graphs.zarr"))?;
+    let storage = Storage::from_rdf_and_store("resources/input.trig", store)?;
+    assert_counts_agree(&storage)?;
+    assert_eq!(storage.count_matching(None, None, None)?, 4);
+    Ok(())
+}
+
+#[test]
+fn test_statistics() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let store = FilesystemStore::new(dir.path().join("statistics_grid.zarr"))?;
+    let storage = Storage::from_rdf_and_store("resources/input.trig", store)?;
+    let statistics = storage.statistics()?;
+    let dictionary = &storage.dictionary;
+
+    assert_eq!(statistics.quads(), 5);
+    assert_eq!(statistics.subjects().len(), dictionary.subjects_len());
+    assert_eq!(statistics.subjects().iter().sum::<u64>(), 5);
+    assert_eq!(statistics.objects().iter().sum::<u64>(), 5);
+
+    let enrolled_in = dictionary
+        .get_predicate_idx("<http://example.org/enrolledIn>")
+        .unwrap();
+    assert_eq!(statistics.predicate(enrolled_in), 2);
+    let cs101 = dictionary
+        .get_object_idx("<http://example.org/cs101>")
+        .unwrap();
+    assert_eq!(statistics.object(cs101), 2);
+    assert_eq!(statistics.subject(TermId::new(1000)), 0);
+
+    // Appended and deleted quads are counted, placeholders are not
+    let rdf = dir.path().join("appended.nt");
+    std::fs::write(
+        &rdf,
+        "<http://example.org/c> <http://example.org/name> \"Carol\" .\n",
+    )?;
+    let store = FilesystemStore::new(dir.path().join("statistics.zarr"))?;
+    let mut storage = Storage::from_rdf_and_store("resources/input.trig", store)?;
+    storage.append(rdf.to_str().unwrap())?;
+    let enrolled_in = NamedNode::new("http://example.org/enrolledIn")?;
+    assert_eq!(
+        storage.delete_matching(None, Some(&enrolled_in), None, None)?,
+        2
+    );
+    let statistics = storage.statistics()?;
+    assert_eq!(statistics.quads(), 4);
+    for counts in [
+        statistics.subjects(),
+        statistics.predicates(),
+        statistics.objects(),
+    ] {
+        assert_eq!(counts.iter().sum::<u64>(), 4);
+    }
+    let dictionary = &storage.dictionary;
+    let enrolled_in = dictionary
+        .get_predicate_idx("<http://example.org/enrolledIn>")
+        .unwrap();
+    assert_eq!(statistics.predicate(enrolled_in), 0);
+    let carol = dictionary.get_object_idx("\"Carol\"").unwrap();
+    assert_eq!(statistics.object(carol), 1);
+    Ok(())
+}
+
+#[test]
+fn test_access_path() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let rdf = dir.path().join("input.nt");
+    let ex = |name: &str| format!("<http://example.org/{name}>");
+    let mut triples = String::new();
+    for i in 0..20 {
+        triples.push_str(&format!(
+            "{} {} {} .\n",
+            ex("s"),
+            ex("p"),
+            ex(&i.to_string())
+        ));
+    }
+    triples.push_str(&format!("{} {} {} .\n", ex("s"), ex("q"), ex("rare")));
+    triples.push_str(&format!("{} {} {} .\n", ex("t"), ex("q"), ex("rare")));
+    std::fs::write(&rdf, triples)?;
+    let store = FilesystemStore::new(dir.path().join("output.zarr"))?;
+    let storage = Storage::from_rdf_and_store(rdf.to_str().unwrap(), store)?;
+
+    let dictionary = &storage.dictionary;
+    let s = dictionary.get_subject_idx(&ex("s"));
+    let p = dictionary.get_predicate_idx(&ex("p"));
+    let q = dictionary.get_predicate_idx(&ex("q"));
+    let rare = dictionary.get_object_idx(&ex("rare"));
+    assert_eq!(storage.access_path(None, None, None), Index::Subject);
+    assert_eq!(storage.access_path(None, p, None), Index::Predicate);
+    assert_eq!(storage.access_path(s, None, rare), Index::Object);
+    assert_eq!(storage.access_path(s, p, None), Index::Predicate);
+    assert_eq!(storage.access_path(s, q, rare), Index::Predicate);
+
+    // Whichever index answers them, matches are the same
+    assert_eq!(
+        storage.triples_matching(s, None, rare)?,
+        vec![[s.unwrap(), q.unwrap(), rare.unwrap()]]
+    );
+    assert_eq!(storage.triples_matching(s, p, None)?.len(), 20);
+    assert_eq!(storage.count_matching(None, q, rare)?, 2);
+    Ok(())
+}
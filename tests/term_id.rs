@@ -0,0 +1,32 @@
+use zarrdf::error::ZarrDfError;
+use zarrdf::query::BasicGraphPattern;
+use zarrdf::query::TermId;
+use zarrdf::storage::Storage;
+use zarrs::filesystem::FilesystemStore;
+
+const RDF: &str = "resources/input.ttl";
+
+#[test]
+fn test_ids_beyond_i32() -> Result<(), ZarrDfError> {
+    let dir = tempfile::tempdir()?;
+    let store = FilesystemStore::new(dir.path().join("term_id.zarr"))?;
+    let storage = Storage::from_rdf_and_store(RDF, store)?;
+    let dictionary = &storage.dictionary;
+
+    // Identifiers that do not fit in an i32 are looked up, never wrapped
+    let id = TermId::new(i32::MAX as u64 + 1);
+    assert_eq!(u64::from(id), 1 << 31);
+    assert!(matches!(
+        storage.triples_matching(Some(id), None, None),
+        Err(ZarrDfError::ChunkNotFound(2147483648))
+    ));
+    assert!(matches!(
+        dictionary.subject_term(id),
+        Err(ZarrDfError::TermNotFound(_))
+    ));
+
+    let subject = dictionary.get_subject_idx("<http://example.org/a>");
+    assert_eq!(subject, Some(TermId::new(0)));
+    assert!(!storage.triples_matching(subject, None, None)?.is_empty());
+    Ok(())
+}
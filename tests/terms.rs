@@ -0,0 +1,62 @@
+use std::collections::BTreeSet;
+use std::fs::File;
+
+use oxrdf::GraphName;
+use oxrdf::Literal;
+use oxrdf::NamedNode;
+use oxrdf::Term;
+use oxrdfio::RdfFormat;
+use oxrdfio::RdfParser;
+use zarrdf::query::TermPattern;
+use zarrdf::storage::Storage;
+use zarrs::filesystem::FilesystemStore;
+
+#[test]
+fn test_decode_triples() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let store = FilesystemStore::new(dir.path().join("terms_triples.zarr"))?;
+    let storage = Storage::from_rdf_and_store("resources/input.ttl", store)?;
+
+    let triples = storage
+        .triples_matching_terms(None, None, None)
+        .map(|triple| triple.map(|triple| triple.to_string()))
+        .collect::<Result<BTreeSet<_>, _>>()?;
+    let expected = RdfParser::from_format(RdfFormat::Turtle)
+        .for_reader(File::open("resources/input.ttl")?)
+        .map(|quad| quad.map(|quad| oxrdf::Triple::from(quad).to_string()))
+        .collect::<Result<BTreeSet<_>, _>>()?;
+    assert_eq!(triples, expected);
+
+    let name = NamedNode::new("http://example.org/name")?;
+    let alice = Term::from(Literal::new_simple_literal("Alice"));
+    let triples = storage
+        .triples_matching_terms(None, Some(&name), Some(&alice))
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(triples.len(), 1);
+    assert_eq!(triples[0].subject.to_string(), "<http://example.org/a>");
+
+    let unknown = NamedNode::new("http://example.org/unknown")?;
+    let triples = storage.triples_matching_terms(None, Some(&unknown), None);
+    assert_eq!(triples.count(), 0);
+    Ok(())
+}
+
+#[test]
+fn test_decode_quads() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let store = FilesystemStore::new(dir.path().join("terms_quads.zarr"))?;
+    let storage = Storage::from_rdf_and_store("resources/input.trig", store)?;
+
+    let graph = GraphName::from(NamedNode::new("http://example.org/provenance1")?);
+    let quads = storage
+        .quads_matching_terms(None, None, None, Some(&graph))
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(quads.len(), 2);
+    assert!(quads.iter().all(|quad| quad.graph_name == graph));
+
+    let quads = storage
+        .quads_matching_terms(None, None, None, Some(&GraphName::DefaultGraph))
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(quads.len(), 1);
+    Ok(())
+}
@@ -3,11 +3,11 @@ use zarrdf::storage::Storage;
 use zarrs::filesystem::FilesystemStore;
 
 const RDF: &str = "resources/input.ttl";
-const OUTPUT: &str = "output.zarr";
 
 #[test]
 fn test_triples() -> Result<(), zarrdf::error::ZarrDfError> {
-    let store = FilesystemStore::new(OUTPUT)?;
+    let dir = tempfile::tempdir()?;
+    let store = FilesystemStore::new(dir.path().join("output.zarr"))?;
     let storage = Storage::from_rdf_and_store(RDF, store)?;
     let triples = storage.triples_matching(None, None, None)?;
     assert_eq!(triples.len(), 6);
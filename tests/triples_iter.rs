@@ -0,0 +1,24 @@
+use zarrdf::query::BasicGraphPattern;
+use zarrdf::storage::Storage;
+use zarrs::filesystem::FilesystemStore;
+
+const RDF: &str = "resources/input.ttl";
+
+#[test]
+fn test_triples_iter() -> Result<(), zarrdf::error::ZarrDfError> {
+    let dir = tempfile::tempdir()?;
+    let store = FilesystemStore::new(dir.path().join("triples_iter.zarr"))?;
+    let storage = Storage::from_rdf_and_store(RDF, store)?;
+
+    let triples = storage
+        .triples_matching_iter(None, None, None)
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(triples, storage.triples_matching(None, None, None)?);
+
+    let first = storage
+        .triples_matching_iter(None, None, None)
+        .take(2)
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(first, triples[..2]);
+    Ok(())
+}
@@ -1,16 +1,19 @@
+use std::fs;
+
+use zarrdf::config::ConfigBuilder;
 use zarrdf::query::BasicGraphPattern;
 use zarrdf::storage::Storage;
 use zarrs::filesystem::FilesystemStore;
 
 const RDF: &str = "resources/input.ttl";
-const OUTPUT: &str = "output.zarr";
 const SUBJECT: &str = "<http://example.org/a>";
 const PREDICATE: &str = "<http://example.org/birthdate>";
 const OBJECT: &str = "\"1990-05-02\"^^<http://www.w3.org/2001/XMLSchema#date>";
 
 #[test]
 fn test_by_subject() -> Result<(), zarrdf::error::ZarrDfError> {
-    let store = FilesystemStore::new(OUTPUT)?;
+    let dir = tempfile::tempdir()?;
+    let store = FilesystemStore::new(dir.path().join("output.zarr"))?;
     let storage = Storage::from_rdf_and_store(RDF, store)?;
     let subject = storage.dictionary.get_subject_idx(SUBJECT);
     let triples = storage.triples_matching(subject, None, None)?;
@@ -20,7 +23,8 @@ fn test_by_subject() -> Result<(), zarrdf::error::ZarrDfError> {
 
 #[test]
 fn test_by_predicate() -> Result<(), zarrdf::error::ZarrDfError> {
-    let store = FilesystemStore::new(OUTPUT)?;
+    let dir = tempfile::tempdir()?;
+    let store = FilesystemStore::new(dir.path().join("output.zarr"))?;
     let storage = Storage::from_rdf_and_store(RDF, store)?;
     let predicate = storage.dictionary.get_predicate_idx(PREDICATE);
     let triples = storage.triples_matching(None, predicate, None)?;
@@ -30,7 +34,8 @@ fn test_by_predicate() -> Result<(), zarrdf::error::ZarrDfError> {
 
 #[test]
 fn test_by_object() -> Result<(), zarrdf::error::ZarrDfError> {
-    let store = FilesystemStore::new(OUTPUT)?;
+    let dir = tempfile::tempdir()?;
+    let store = FilesystemStore::new(dir.path().join("output.zarr"))?;
     let storage = Storage::from_rdf_and_store(RDF, store)?;
     let object = storage.dictionary.get_object_idx(OBJECT);
     let triples = storage.triples_matching(None, None, object)?;
@@ -40,7 +45,8 @@ fn test_by_object() -> Result<(), zarrdf::error::ZarrDfError> {
 
 #[test]
 fn test_by_subject_predicate() -> Result<(), zarrdf::error::ZarrDfError> {
-    let store = FilesystemStore::new(OUTPUT)?;
+    let dir = tempfile::tempdir()?;
+    let store = FilesystemStore::new(dir.path().join("output.zarr"))?;
     let storage = Storage::from_rdf_and_store(RDF, store)?;
     let subject = storage.dictionary.get_subject_idx(SUBJECT);
     let predicate = storage.dictionary.get_predicate_idx(PREDICATE);
@@ -51,7 +57,8 @@ fn test_by_subject_predicate() -> Result<(), zarrdf::error::ZarrDfError> {
 
 #[test]
 fn test_by_subject_object() -> Result<(), zarrdf::error::ZarrDfError> {
-    let store = FilesystemStore::new(OUTPUT)?;
+    let dir = tempfile::tempdir()?;
+    let store = FilesystemStore::new(dir.path().join("output.zarr"))?;
     let storage = Storage::from_rdf_and_store(RDF, store)?;
     let subject = storage.dictionary.get_subject_idx(SUBJECT);
     let object = storage.dictionary.get_object_idx(OBJECT);
@@ -62,7 +69,8 @@ fn test_by_subject_object() -> Result<(), zarrdf::error::ZarrDfError> {
 
 #[test]
 fn test_by_predicate_object() -> Result<(), zarrdf::error::ZarrDfError> {
-    let store = FilesystemStore::new(OUTPUT)?;
+    let dir = tempfile::tempdir()?;
+    let store = FilesystemStore::new(dir.path().join("output.zarr"))?;
     let storage = Storage::from_rdf_and_store(RDF, store)?;
     let predicate = storage.dictionary.get_predicate_idx(PREDICATE);
     let object = storage.dictionary.get_object_idx(OBJECT);
@@ -73,7 +81,8 @@ fn test_by_predicate_object() -> Result<(), zarrdf::error::ZarrDfError> {
 
 #[test]
 fn test_by_subject_predicate_object() -> Result<(), zarrdf::error::ZarrDfError> {
-    let store = FilesystemStore::new(OUTPUT)?;
+    let dir = tempfile::tempdir()?;
+    let store = FilesystemStore::new(dir.path().join("output.zarr"))?;
     let storage = Storage::from_rdf_and_store(RDF, store)?;
     let subject = storage.dictionary.get_subject_idx(SUBJECT);
     let predicate = storage.dictionary.get_predicate_idx(PREDICATE);
@@ -82,3 +91,84 @@ fn test_by_subject_predicate_object() -> Result<(), zarrdf::error::ZarrDfError>
     assert_eq!(triples.len(), 1);
     Ok(())
 }
+
+/// Checks every two-bound and fully bound pattern of the subjects of the
+/// store against a scan of their chunk.
+fn assert_lookups_agree<S>(storage: &Storage<S>) -> Result<(), zarrdf::error::ZarrDfError>
+where
+    S: zarrs::storage::ReadableWritableStorageTraits + 'static,
+{
+    let scan = storage.triples_matching(None, None, None)?;
+    for &[s, p, o] in &scan {
+        let expected: Vec<_> = scan
+            .iter()
+            .filter(|triple| triple[0] == s && triple[1] == p)
+            .copied()
+            .collect();
+        assert_eq!(storage.triples_matching(Some(s), Some(p), None)?, expected);
+        // The object index returns these in another order than the scan
+        let mut expected: Vec<_> = scan
+            .iter()
+            .filter(|triple| triple[1] == p && triple[2] == o)
+            .copied()
+            .collect();
+        expected.sort();
+        let mut found = storage.triples_matching(None, Some(p), Some(o))?;
+        found.sort();
+        assert_eq!(found, expected);
+        assert_eq!(
+            storage.triples_matching(Some(s), Some(p), Some(o))?,
+            vec![[s, p, o]]
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_sorted_chunks() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let rdf = dir.path().join("input.nt");
+    let content: String = (0..200)
+        .map(|i| {
+            format!(
+                "<http://example.org/s{}> <http://example.org/p{}> \"{}\" .\n",
+                i % 3,
+                i % 7,
+                i % 11
+            )
+        })
+        .collect();
+    fs::write(&rdf, content)?;
+
+    let config = ConfigBuilder::new().delta(true).build();
+    let path = dir.path().join("output.zarr");
+    let store = FilesystemStore::new(&path)?;
+    let mut storage =
+        Storage::from_rdf_and_store_with_config(rdf.to_str().unwrap(), store, &config)?;
+    for array in [&storage.subject, &storage.predicate, &storage.object] {
+        let sorted = array.as_ref().unwrap().attributes().get("sorted");
+        assert_eq!(sorted, Some(&true.into()));
+    }
+    assert_lookups_agree(&storage)?;
+
+    // Stores without the attribute are filtered row by row
+    for array in [
+        &mut storage.subject,
+        &mut storage.predicate,
+        &mut storage.object,
+    ] {
+        let array = array.as_mut().unwrap();
+        array.attributes_mut().remove("sorted");
+        array.store_metadata()?;
+    }
+    let storage = Storage::from_zarr(FilesystemStore::new(&path)?)?;
+    assert!(storage
+        .subject
+        .as_ref()
+        .unwrap()
+        .attributes()
+        .get("sorted")
+        .is_none());
+    assert_lookups_agree(&storage)?;
+    Ok(())
+}
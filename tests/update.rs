@@ -0,0 +1,227 @@
+use zarrdf::config::Compression;
+use zarrdf::config::ConfigBuilder;
+use zarrdf::error::ZarrDfError;
+use zarrdf::sparql::QueryResults;
+use zarrdf::sparql::Update;
+use zarrdf::storage::Storage;
+use zarrs::filesystem::FilesystemStore;
+
+mod common;
+use common::open;
+use common::quads;
+
+const BASE: &str = "\
+<http://example.org/a> <http://example.org/name> \"Alice\" .
+<http://example.org/a> <http://example.org/knows> <http://example.org/b> .
+<http://example.org/b> <http://example.org/name> \"Bob\" .
+<http://example.org/b> <http://example.org/knows> <http://example.org/a> <http://example.org/g> .
+";
+
+#[test]
+fn test_update_data_and_templates() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let rdf = dir.path().join("base.nq");
+    std::fs::write(&rdf, BASE)?;
+    let zarr = dir.path().join("update.zarr");
+    let store = FilesystemStore::new(&zarr)?;
+    let mut storage = Storage::from_rdf_and_store(rdf.to_str().unwrap(), store)?;
+
+    storage.sparql_update(
+        "PREFIX : <http://example.org/>
+        INSERT DATA { :c :name \"Carol\" . GRAPH :g { :c :knows _:friend . _:friend :name \"Dan\" } } ;
+        DELETE DATA { :a :name \"Alice\" }",
+    )?;
+    let QueryResults::Solutions(_, solutions) = storage.sparql(
+        "PREFIX : <http://example.org/>
+        SELECT ?name WHERE { :c :knows ?friend . ?friend :name ?name }",
+    )?
+    else {
+        panic!("expected solutions");
+    };
+    assert_eq!(solutions.len(), 1);
+    // The blank node of the data is fresh
+    assert!(!quads(&storage).iter().any(|quad| quad.contains("_:friend")));
+    assert!(!quads(&storage).iter().any(|quad| quad.contains("Alice")));
+    assert_eq!(quads(&open(&zarr)), quads(&storage));
+
+    // Solutions are found before the templates change anything
+    storage.sparql_update(
+        "PREFIX : <http://example.org/>
+        DELETE { ?s :name ?name } INSERT { GRAPH :h { ?s :label ?name } }
+        WHERE { ?s :name ?name FILTER(?name != \"Dan\") }",
+    )?;
+    let storage = open(&zarr);
+    storage.verify()?;
+    let labels: Vec<_> = quads(&storage)
+        .into_iter()
+        .filter(|quad| quad.contains("label") || quad.contains("name"))
+        .collect();
+    assert_eq!(labels.len(), 3);
+    assert!(labels.contains(
+        &"<http://example.org/b> <http://example.org/label> \"Bob\" <http://example.org/h>"
+            .to_string()
+    ));
+
+    // Templates without GRAPH remove triples from every graph
+    let mut storage = storage;
+    storage.sparql_update("DELETE WHERE { ?s <http://example.org/knows> ?o }")?;
+    assert!(!quads(&storage).iter().any(|quad| quad.contains("knows")));
+
+    // Removed quads and unused terms are dropped on compaction
+    storage.compact()?;
+    assert_eq!(quads(&open(&zarr)), quads(&storage));
+    Ok(())
+}
+
+#[test]
+fn test_update_load_clear_and_atomicity() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let rdf = dir.path().join("base.nq");
+    std::fs::write(&rdf, BASE)?;
+    let more = dir.path().join("more.nt");
+    std::fs::write(
+        &more,
+        "<http://example.org/c> <http://example.org/name> \"Carol\" .\n",
+    )?;
+    let zarr = dir.path().join("update.zarr");
+    let store = FilesystemStore::new(&zarr)?;
+    let mut storage = Storage::from_rdf_and_store(rdf.to_str().unwrap(), store)?;
+    let before = quads(&storage);
+
+    // An operation failing after others leaves the store as it was
+    let missing = dir.path().join("missing.nt");
+    let update = format!("CLEAR ALL ; LOAD <file://{}>", missing.to_str().unwrap());
+    assert!(storage.sparql_update(&update).is_err());
+    assert_eq!(quads(&storage), before);
+    assert_eq!(quads(&open(&zarr)), before);
+
+    // Unless it is silent
+    let update = format!(
+        "LOAD SILENT <file://{}> ; LOAD <file://{}> INTO GRAPH <http://example.org/h>",
+        missing.to_str().unwrap(),
+        more.to_str().unwrap()
+    );
+    storage.sparql_update(&update)?;
+    let storage = open(&zarr);
+    storage.verify()?;
+    assert!(quads(&storage).contains(
+        &"<http://example.org/c> <http://example.org/name> \"Carol\" <http://example.org/h>"
+            .to_string()
+    ));
+
+    let mut storage = storage;
+    storage.sparql_update("CLEAR GRAPH <http://example.org/h>")?;
+    assert_eq!(quads(&storage), before);
+    storage.sparql_update("CLEAR NAMED")?;
+    assert_eq!(quads(&storage).len(), 3);
+    storage.sparql_update("CLEAR DEFAULT")?;
+    assert!(quads(&open(&zarr)).is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_update_named_graphs() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let rdf = dir.path().join("base.nq");
+    std::fs::write(&rdf, BASE)?;
+    let zarr = dir.path().join("graphs.zarr");
+    let store = FilesystemStore::new(&zarr)?;
+    let mut storage = Storage::from_rdf_and_store(rdf.to_str().unwrap(), store)?;
+
+    // The graph variable of a template is bound by GRAPH in the pattern
+    storage.sparql_update(
+        "PREFIX : <http://example.org/>
+        DELETE { GRAPH ?g { ?s :knows ?o } } WHERE { GRAPH ?g { ?s :knows ?o } }",
+    )?;
+    let knows: Vec<_> = quads(&storage)
+        .into_iter()
+        .filter(|quad| quad.contains("knows"))
+        .collect();
+    assert_eq!(
+        knows,
+        ["<http://example.org/a> <http://example.org/knows> <http://example.org/b>"]
+    );
+
+    // DELETE WHERE removes a triple from every graph it was matched in
+    storage.sparql_update(
+        "PREFIX : <http://example.org/>
+        INSERT DATA { GRAPH :g { :a :name \"Alice\" } } ;
+        DELETE WHERE { :a :name ?name }",
+    )?;
+    assert!(!quads(&storage).iter().any(|quad| quad.contains("Alice")));
+
+    // Or from the graph it names
+    storage.sparql_update(
+        "PREFIX : <http://example.org/>
+        INSERT DATA { GRAPH :g { :b :name \"Bob\" } } ;
+        DELETE WHERE { GRAPH :g { :b :name ?name } }",
+    )?;
+    let bob: Vec<_> = quads(&storage)
+        .into_iter()
+        .filter(|quad| quad.contains("Bob"))
+        .collect();
+    assert_eq!(
+        bob,
+        ["<http://example.org/b> <http://example.org/name> \"Bob\""]
+    );
+    assert_eq!(quads(&open(&zarr)), quads(&storage));
+    Ok(())
+}
+
+#[test]
+fn test_update_syntax() {
+    assert!(matches!(
+        Update::parse("DELETE DATA { ?s <http://example.org/p> 1 }"),
+        Err(ZarrDfError::SparqlSyntax(_))
+    ));
+    assert!(matches!(
+        Update::parse(
+            "DELETE { _:b <http://example.org/p> ?o } WHERE { _:b <http://example.org/p> ?o }"
+        ),
+        Err(ZarrDfError::SparqlSyntax(_))
+    ));
+    assert!(matches!(
+        Update::parse("DROP ALL"),
+        Err(ZarrDfError::UnsupportedQuery(_))
+    ));
+    let update = Update::parse(
+        "PREFIX : <http://example.org/> INSERT DATA { :a :p 1 } ; PREFIX ex: <http://example.com/> CLEAR ALL ;",
+    )
+    .unwrap();
+    assert_eq!(update.operations.len(), 2);
+}
+
+#[test]
+fn test_update_keeps_config() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let rdf = dir.path().join("base.nq");
+    std::fs::write(&rdf, BASE)?;
+    let config = ConfigBuilder::new()
+        .compression(Compression::Zstd(3))
+        .delta(true)
+        .build();
+    let zarr = dir.path().join("update_config.zarr");
+    let store = FilesystemStore::new(&zarr)?;
+    let mut storage =
+        Storage::from_rdf_and_store_with_config(rdf.to_str().unwrap(), store, &config)?;
+
+    // Deltas are encoded like the arrays of the store
+    storage.sparql_update(
+        "INSERT DATA { <http://example.org/c> <http://example.org/name> \"Carol\" }",
+    )?;
+    storage.sparql_update("DELETE WHERE { ?s <http://example.org/knows> ?o }")?;
+    for array in ["deltas/0/subject", "deltas/1/tombstones/subject"] {
+        let path = zarr.join(array).join("zarr.json");
+        let codecs = serde_json::from_str::<serde_json::Value>(&std::fs::read_to_string(path)?)?
+            ["codecs"]
+            .to_string();
+        for codec in ["zarrdf.delta", "zstd"] {
+            assert!(
+                codecs.contains(codec),
+                "{codec} missing from {array}: {codecs}"
+            );
+        }
+    }
+    assert_eq!(quads(&open(&zarr)).len(), 3);
+    Ok(())
+}
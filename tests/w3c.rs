@@ -0,0 +1,746 @@
+//! Runs test suites written in the manifest format of the W3C SPARQL 1.1
+//! test suite: the local cases under `resources/w3c` always, and the
+//! official suite from a checkout of <https://github.com/w3c/rdf-tests> when
+//! asked to, with
+//!
+//! ```sh
+//! git clone https://github.com/w3c/rdf-tests
+//! W3C_RDF_TESTS=rdf-tests cargo test --test w3c -- --ignored
+//! ```
+//!
+//! Tests of features the engine does not claim, like entailment regimes and
+//! federated queries, are skipped, and the official tests known to fail are
+//! listed in `resources/w3c/failures.txt`.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use oxrdf::vocab::rdf;
+use oxrdf::BlankNode;
+use oxrdf::Graph;
+use oxrdf::GraphName;
+use oxrdf::Literal;
+use oxrdf::NamedNode;
+use oxrdf::Quad;
+use oxrdf::Subject;
+use oxrdf::Term;
+use oxrdf::TermRef;
+use oxrdf::TripleRef;
+use oxrdfio::RdfParser;
+use quick_xml::events::BytesStart;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use zarrdf::query::TermPattern;
+use zarrdf::rdf_format::RdfFormat;
+use zarrdf::sparql::Query;
+use zarrdf::sparql::QueryResults;
+use zarrdf::sparql::ResultsFormat;
+use zarrdf::sparql::Update;
+use zarrdf::storage::Storage;
+use zarrs::filesystem::FilesystemStore;
+
+const MF: &str = "http://www.w3.org/2001/sw/DataAccess/tests/test-manifest#";
+const QT: &str = "http://www.w3.org/2001/sw/DataAccess/tests/test-query#";
+const UT: &str = "http://www.w3.org/2009/sparql/tests/test-update#";
+const DAWGT: &str = "http://www.w3.org/2001/sw/DataAccess/tests/test-dawg#";
+const RS: &str = "http://www.w3.org/2001/sw/DataAccess/tests/result-set#";
+const SD: &str = "http://www.w3.org/ns/sparql-service-description#";
+const RDFS_LABEL: &str = "http://www.w3.org/2000/01/rdf-schema#label";
+
+type Error = Box<dyn std::error::Error>;
+
+/// Row of terms compared up to the renaming of blank nodes, where `None`
+/// stands for an unbound variable or the default graph.
+type Row = Vec<Option<Term>>;
+
+fn mf(name: &str) -> NamedNode {
+    NamedNode::new_unchecked(format!("{MF}{name}"))
+}
+
+fn qt(name: &str) -> NamedNode {
+    NamedNode::new_unchecked(format!("{QT}{name}"))
+}
+
+fn ut(name: &str) -> NamedNode {
+    NamedNode::new_unchecked(format!("{UT}{name}"))
+}
+
+fn file_iri(path: &Path) -> String {
+    format!("file://{}", path.display())
+}
+
+fn iri_path(iri: &str) -> Result<PathBuf, Error> {
+    iri.strip_prefix("file://")
+        .map(PathBuf::from)
+        .ok_or_else(|| format!("{iri} is not a local file").into())
+}
+
+/// Parses an RDF file, resolving relative IRIs against its own IRI.
+fn parse_file(path: &Path) -> Result<Vec<Quad>, Error> {
+    let format = RdfFormat::from_path(path.to_str().ok_or("path is not UTF-8")?)?;
+    let quads = RdfParser::from_format(format.into())
+        .with_base_iri(file_iri(path))?
+        .for_reader(fs::File::open(path)?)
+        .collect::<Result<_, _>>()?;
+    Ok(quads)
+}
+
+/// Outcome of the tests of a suite.
+#[derive(Default)]
+struct Report {
+    passed: usize,
+    skipped: usize,
+    failed: Vec<(String, String)>,
+}
+
+/// Runs every test of a manifest and of the manifests it includes.
+fn run_manifest(manifest: &Path, report: &mut Report) -> Result<(), Error> {
+    let mut graph = Graph::new();
+    for quad in parse_file(manifest)? {
+        graph.insert(TripleRef::from(quad.as_ref()));
+    }
+    let root = Subject::from(NamedNode::new(file_iri(manifest))?);
+    for include in list(&graph, object(&graph, &root, &mf("include"))) {
+        if let Term::NamedNode(include) = include {
+            run_manifest(&iri_path(include.as_str())?, report)?;
+        }
+    }
+    for entry in list(&graph, object(&graph, &root, &mf("entries"))) {
+        let Some(test) = as_subject(&entry) else {
+            continue;
+        };
+        match run_test(&graph, &test) {
+            Ok(true) => report.passed += 1,
+            Ok(false) => report.skipped += 1,
+            Err(error) => report.failed.push((entry.to_string(), error.to_string())),
+        }
+    }
+    Ok(())
+}
+
+fn object(graph: &Graph, subject: &Subject, predicate: &NamedNode) -> Option<Term> {
+    graph
+        .object_for_subject_predicate(subject, predicate)
+        .map(TermRef::into_owned)
+}
+
+fn objects(graph: &Graph, subject: &Subject, predicate: &NamedNode) -> Vec<Term> {
+    graph
+        .objects_for_subject_predicate(subject, predicate)
+        .map(TermRef::into_owned)
+        .collect()
+}
+
+fn as_subject(term: &Term) -> Option<Subject> {
+    match term {
+        Term::NamedNode(node) => Some(node.clone().into()),
+        Term::BlankNode(node) => Some(node.clone().into()),
+        _ => None,
+    }
+}
+
+/// Items of an RDF collection.
+fn list(graph: &Graph, mut head: Option<Term>) -> Vec<Term> {
+    let mut items = Vec::new();
+    while let Some(node) = head.as_ref().and_then(as_subject) {
+        match object(graph, &node, &rdf::FIRST.into_owned()) {
+            Some(item) => items.push(item),
+            None => break,
+        }
+        head = object(graph, &node, &rdf::REST.into_owned());
+    }
+    items
+}
+
+fn file(term: Option<Term>) -> Result<PathBuf, Error> {
+    match term {
+        Some(Term::NamedNode(node)) => iri_path(node.as_str()),
+        term => Err(format!("expected a file, found {term:?}").into()),
+    }
+}
+
+/// Runs a test, returning whether it was run rather than skipped.
+fn run_test(graph: &Graph, test: &Subject) -> Result<bool, Error> {
+    let withdrawn = NamedNode::new_unchecked(format!("{DAWGT}Withdrawn"));
+    let approval = NamedNode::new_unchecked(format!("{DAWGT}approval"));
+    if object(graph, test, &approval) == Some(withdrawn.into()) {
+        return Ok(false);
+    }
+    let Some(Term::NamedNode(kind)) = object(graph, test, &rdf::TYPE.into_owned()) else {
+        return Ok(false);
+    };
+    let action = object(graph, test, &mf("action"));
+    let kind = kind.as_str().strip_prefix(MF).unwrap_or(kind.as_str());
+    match kind {
+        "PositiveSyntaxTest" | "PositiveSyntaxTest11" => {
+            Query::parse(&read_with_base(&file(action)?)?)?;
+        }
+        "NegativeSyntaxTest" | "NegativeSyntaxTest11" => {
+            if Query::parse(&read_with_base(&file(action)?)?).is_ok() {
+                return Err("invalid query parsed".into());
+            }
+        }
+        "PositiveUpdateSyntaxTest11" => {
+            Update::parse(&read_with_base(&file(action)?)?)?;
+        }
+        "NegativeUpdateSyntaxTest11" => {
+            if Update::parse(&read_with_base(&file(action)?)?).is_ok() {
+                return Err("invalid update parsed".into());
+            }
+        }
+        "QueryEvaluationTest" => {
+            let action = action.as_ref().and_then(as_subject).ok_or("no action")?;
+            // Entailment regimes and federated queries are not supported
+            let entailment = NamedNode::new_unchecked(format!("{SD}entailmentRegime"));
+            if object(graph, &action, &entailment).is_some()
+                || object(graph, &action, &qt("serviceData")).is_some()
+            {
+                return Ok(false);
+            }
+            let result = file(object(graph, test, &mf("result")))?;
+            evaluate_query(graph, &action, &result)?;
+        }
+        "UpdateEvaluationTest" => {
+            let action = action.as_ref().and_then(as_subject).ok_or("no action")?;
+            let result = object(graph, test, &mf("result"))
+                .as_ref()
+                .and_then(as_subject)
+                .ok_or("no result")?;
+            evaluate_update(graph, &action, &result)?;
+        }
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+/// Reads a query or an update, whose relative IRIs resolve against the IRI
+/// of its file.
+fn read_with_base(path: &Path) -> Result<String, Error> {
+    Ok(format!(
+        "BASE <{}>\n{}",
+        file_iri(path),
+        fs::read_to_string(path)?
+    ))
+}
+
+/// Builds a store holding the quads, written to a temporary N-Quads file.
+fn store(
+    quads: impl IntoIterator<Item = Quad>,
+    dir: &Path,
+) -> Result<Storage<FilesystemStore>, Error> {
+    let nquads = dir.join("dataset.nq");
+    let mut writer = fs::File::create(&nquads)?;
+    for quad in quads {
+        writeln!(writer, "{quad} .")?;
+    }
+    let store = FilesystemStore::new(dir.join("dataset.zarr"))?;
+    Ok(Storage::from_rdf_and_store(
+        nquads.to_str().ok_or("path is not UTF-8")?,
+        store,
+    )?)
+}
+
+/// Quads of the files, in the default graph or in the graph named `graph`.
+fn load(path: &Path, graph: Option<NamedNode>) -> Result<Vec<Quad>, Error> {
+    let graph = graph.map_or(GraphName::DefaultGraph, GraphName::from);
+    Ok(parse_file(path)?
+        .into_iter()
+        .map(|quad| Quad {
+            graph_name: graph.clone(),
+            ..quad
+        })
+        .collect())
+}
+
+fn evaluate_query(graph: &Graph, action: &Subject, result: &Path) -> Result<(), Error> {
+    let query = Query::parse(&read_with_base(&file(object(
+        graph,
+        action,
+        &qt("query"),
+    ))?)?)?;
+
+    let mut quads = Vec::new();
+    for data in objects(graph, action, &qt("data")) {
+        quads.extend(load(&file(Some(data))?, None)?);
+    }
+    for data in objects(graph, action, &qt("graphData")) {
+        let Term::NamedNode(name) = data else {
+            return Err("graph data is not a file".into());
+        };
+        quads.extend(load(&iri_path(name.as_str())?, Some(name))?);
+    }
+    // The graphs of FROM and FROM NAMED are files loaded as named graphs,
+    // which FROM merges into the default graph
+    if let Some(dataset) = &query.dataset {
+        let loaded: HashSet<_> = quads.iter().map(|quad| quad.graph_name.clone()).collect();
+        for name in dataset.default.iter().chain(&dataset.named) {
+            if !loaded.contains(&GraphName::from(name.clone())) {
+                quads.extend(load(&iri_path(name.as_str())?, Some(name.clone()))?);
+            }
+        }
+    }
+    let dir = tempfile::tempdir()?;
+    let results = store(quads, dir.path())?.evaluate(&query)?;
+
+    let extension = result.extension().and_then(|extension| extension.to_str());
+    let expected = match extension {
+        Some("srx") => parse_srx(&fs::read_to_string(result)?)?,
+        Some("srj") => parse_srj(&fs::read_to_string(result)?)?,
+        Some("tsv") => parse_tsv(&fs::read_to_string(result)?)?,
+        Some("csv") => {
+            // Values lose their types in CSV, so the serializations are
+            // compared, up to the order of the rows
+            let mut expected: Vec<_> = fs::read_to_string(result)?
+                .split("\r\n")
+                .map(str::to_string)
+                .collect();
+            let actual = String::from_utf8(results.write(Vec::new(), ResultsFormat::Csv)?)?;
+            let mut actual: Vec<_> = actual.split("\r\n").map(str::to_string).collect();
+            expected[1..].sort();
+            actual[1..].sort();
+            return match expected == actual {
+                true => Ok(()),
+                false => Err(format!("expected {expected:?}, found {actual:?}").into()),
+            };
+        }
+        _ => {
+            let quads = parse_file(result)?;
+            match results {
+                QueryResults::Graph(_) => Expected::Graph(quads),
+                _ => result_set(quads)?,
+            }
+        }
+    };
+    compare(expected, results)
+}
+
+fn evaluate_update(graph: &Graph, action: &Subject, result: &Subject) -> Result<(), Error> {
+    let dataset = |node: &Subject| -> Result<Vec<Quad>, Error> {
+        let mut quads = Vec::new();
+        for data in objects(graph, node, &ut("data")) {
+            quads.extend(load(&file(Some(data))?, None)?);
+        }
+        for data in objects(graph, node, &ut("graphData")) {
+            let data = as_subject(&data).ok_or("graph data is a literal")?;
+            let label = NamedNode::new_unchecked(RDFS_LABEL);
+            let name = match object(graph, &data, &label) {
+                Some(Term::Literal(label)) => NamedNode::new(label.value())?,
+                _ => return Err("graph data has no label".into()),
+            };
+            quads.extend(load(
+                &file(object(graph, &data, &ut("graph")))?,
+                Some(name),
+            )?);
+        }
+        Ok(quads)
+    };
+    let update = Update::parse(&read_with_base(&file(object(
+        graph,
+        action,
+        &ut("request"),
+    ))?)?)?;
+    let dir = tempfile::tempdir()?;
+    let mut storage = store(dataset(action)?, dir.path())?;
+    storage.update(&update)?;
+
+    let actual = storage
+        .quads_matching_terms(None, None, None, None)
+        .collect::<Result<Vec<_>, _>>()?;
+    let expected: Vec<_> = dataset(result)?.iter().map(quad_row).collect();
+    let actual: Vec<_> = actual.iter().map(quad_row).collect();
+    match isomorphic(&expected, &actual) {
+        true => Ok(()),
+        false => Err(format!("expected {expected:?}, found {actual:?}").into()),
+    }
+}
+
+/// Expected results of a query.
+#[derive(Debug)]
+enum Expected {
+    Solutions(Vec<String>, Vec<HashMap<String, Term>>),
+    Boolean(bool),
+    Graph(Vec<Quad>),
+}
+
+fn compare(expected: Expected, actual: QueryResults) -> Result<(), Error> {
+    let same = match (&expected, &actual) {
+        (Expected::Boolean(expected), QueryResults::Boolean(actual)) => expected == actual,
+        (Expected::Graph(expected), QueryResults::Graph(actual)) => {
+            let expected: Vec<_> = expected.iter().map(quad_row).collect();
+            let actual: Vec<_> = actual
+                .iter()
+                .map(|triple| quad_row(&triple.clone().in_graph(GraphName::DefaultGraph)))
+                .collect();
+            isomorphic(&expected, &actual)
+        }
+        (Expected::Solutions(variables, expected), QueryResults::Solutions(projection, actual)) => {
+            let mut names: Vec<_> = projection
+                .iter()
+                .map(|variable| variable.as_str().to_string())
+                .collect();
+            let mut expected_names = variables.clone();
+            names.sort();
+            expected_names.sort();
+            let expected: Vec<Row> = expected
+                .iter()
+                .map(|solution| {
+                    names
+                        .iter()
+                        .map(|name| solution.get(name).cloned())
+                        .collect()
+                })
+                .collect();
+            let actual: Vec<Row> = actual
+                .iter()
+                .map(|solution| {
+                    names
+                        .iter()
+                        .map(|name| solution.get(name).cloned())
+                        .collect()
+                })
+                .collect();
+            names == expected_names && isomorphic(&expected, &actual)
+        }
+        _ => false,
+    };
+    match same {
+        true => Ok(()),
+        false => Err(format!("expected {expected:?}, found {actual:?}").into()),
+    }
+}
+
+fn quad_row(quad: &Quad) -> Row {
+    let graph = match &quad.graph_name {
+        GraphName::NamedNode(node) => Some(node.clone().into()),
+        GraphName::BlankNode(node) => Some(node.clone().into()),
+        GraphName::DefaultGraph => None,
+    };
+    vec![
+        Some(quad.subject.clone().into()),
+        Some(quad.predicate.clone().into()),
+        Some(quad.object.clone()),
+        graph,
+    ]
+}
+
+/// Whether the rows are the same multiset up to a bijection between their
+/// blank nodes.
+fn isomorphic(expected: &[Row], actual: &[Row]) -> bool {
+    let ground = |row: &Row| !row.iter().flatten().any(Term::is_blank_node);
+    let sorted = |rows: &[Row]| {
+        let mut rows: Vec<_> = rows.iter().filter(|row| ground(row)).cloned().collect();
+        rows.sort_by_cached_key(|row| format!("{row:?}"));
+        rows
+    };
+    if expected.len() != actual.len() || sorted(expected) != sorted(actual) {
+        return false;
+    }
+    let expected: Vec<_> = expected.iter().filter(|row| !ground(row)).collect();
+    let actual: Vec<_> = actual.iter().filter(|row| !ground(row)).collect();
+    let mut used = vec![false; actual.len()];
+    matches(&expected, &actual, &mut used, &mut HashMap::new())
+}
+
+/// Matches the first expected row with an unused actual row, extending the
+/// mapping of blank nodes, and the rest recursively.
+fn matches(
+    expected: &[&Row],
+    actual: &[&Row],
+    used: &mut [bool],
+    mapping: &mut HashMap<BlankNode, BlankNode>,
+) -> bool {
+    let Some((row, rest)) = expected.split_first() else {
+        return true;
+    };
+    for (i, candidate) in actual.iter().enumerate() {
+        if used[i] {
+            continue;
+        }
+        let mut extended = mapping.clone();
+        let same = row.iter().zip(candidate.iter()).all(|pair| match pair {
+            (Some(Term::BlankNode(left)), Some(Term::BlankNode(right))) => {
+                let mapped = extended
+                    .entry(left.clone())
+                    .or_insert_with(|| right.clone());
+                mapped == right
+            }
+            (left, right) => left == right,
+        });
+        // The mapping is a bijection
+        let mut targets: Vec<_> = extended.values().collect();
+        targets.sort_unstable_by_key(|node| node.as_str());
+        targets.dedup();
+        if !same || targets.len() != extended.len() {
+            continue;
+        }
+        used[i] = true;
+        if matches(rest, actual, used, &mut extended) {
+            *mapping = extended;
+            return true;
+        }
+        used[i] = false;
+    }
+    false
+}
+
+/// Parses results in the SPARQL Query Results XML Format.
+fn parse_srx(xml: &str) -> Result<Expected, Error> {
+    let mut reader = Reader::from_str(xml);
+    let mut variables = Vec::new();
+    let mut solutions: Vec<HashMap<String, Term>> = Vec::new();
+    let mut binding = None;
+    // Kind, language and datatype of the term being read, and its value
+    let mut term: Option<(Vec<u8>, Option<String>, Option<String>)> = None;
+    let mut text = String::new();
+    let mut boolean = None;
+    let attribute = |element: &BytesStart, name: &[u8]| {
+        element
+            .attributes()
+            .flatten()
+            .find(|attribute| attribute.key.as_ref() == name)
+            .and_then(|attribute| attribute.unescape_value().ok())
+            .map(|value| value.to_string())
+    };
+    loop {
+        let (element, empty) = match reader.read_event()? {
+            Event::Start(element) => (element, false),
+            Event::Empty(element) => (element, true),
+            Event::Text(content) => {
+                text.push_str(&content.unescape()?);
+                continue;
+            }
+            Event::CData(content) => {
+                text.push_str(&String::from_utf8(content.to_vec())?);
+                continue;
+            }
+            Event::End(element) => {
+                match element.local_name().as_ref() {
+                    b"uri" | b"bnode" | b"literal" => {
+                        let (kind, language, datatype) = term.take().ok_or("unexpected end")?;
+                        let name = binding.clone().ok_or("term outside of a binding")?;
+                        let value = make_term(&kind, &text, language, datatype)?;
+                        let solution = solutions.last_mut().ok_or("binding outside of a result")?;
+                        solution.insert(name, value);
+                    }
+                    b"boolean" => boolean = Some(text.trim() == "true"),
+                    _ => {}
+                }
+                continue;
+            }
+            Event::Eof => break,
+            _ => continue,
+        };
+        text.clear();
+        match element.local_name().as_ref() {
+            b"variable" => variables.extend(attribute(&element, b"name")),
+            b"result" => solutions.push(HashMap::new()),
+            b"binding" => binding = attribute(&element, b"name"),
+            kind @ (b"uri" | b"bnode" | b"literal") => {
+                let language = attribute(&element, b"xml:lang");
+                let datatype = attribute(&element, b"datatype");
+                // An empty literal has no end tag
+                if empty {
+                    let name = binding.clone().ok_or("term outside of a binding")?;
+                    let value = make_term(kind, "", language, datatype)?;
+                    let solution = solutions.last_mut().ok_or("binding outside of a result")?;
+                    solution.insert(name, value);
+                } else {
+                    term = Some((kind.to_vec(), language, datatype));
+                }
+            }
+            _ => {}
+        }
+    }
+    match boolean {
+        Some(boolean) => Ok(Expected::Boolean(boolean)),
+        None => Ok(Expected::Solutions(variables, solutions)),
+    }
+}
+
+/// Parses results in the SPARQL 1.1 Query Results JSON Format.
+fn parse_srj(json: &str) -> Result<Expected, Error> {
+    let json: serde_json::Value = serde_json::from_str(json)?;
+    if let Some(boolean) = json["boolean"].as_bool() {
+        return Ok(Expected::Boolean(boolean));
+    }
+    let variables = json["head"]["vars"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|name| name.as_str().map(str::to_string))
+        .collect();
+    let mut solutions = Vec::new();
+    for binding in json["results"]["bindings"].as_array().into_iter().flatten() {
+        let mut solution = HashMap::new();
+        for (name, value) in binding.as_object().into_iter().flatten() {
+            let text = |key: &str| value[key].as_str().map(str::to_string);
+            let kind = match value["type"].as_str() {
+                Some("typed-literal") => "literal",
+                Some(kind) => kind,
+                None => return Err("binding without a type".into()),
+            };
+            let term = make_term(
+                kind.as_bytes(),
+                &text("value").unwrap_or_default(),
+                text("xml:lang"),
+                text("datatype"),
+            )?;
+            solution.insert(name.clone(), term);
+        }
+        solutions.push(solution);
+    }
+    Ok(Expected::Solutions(variables, solutions))
+}
+
+/// Parses results in the SPARQL 1.1 Query Results TSV Format, whose values
+/// are in N-Triples syntax.
+fn parse_tsv(tsv: &str) -> Result<Expected, Error> {
+    let mut lines = tsv.lines();
+    let variables: Vec<_> = lines
+        .next()
+        .unwrap_or_default()
+        .split('\t')
+        .map(|name| name.trim_start_matches('?').to_string())
+        .collect();
+    let mut solutions = Vec::new();
+    for line in lines.filter(|line| !line.is_empty()) {
+        let mut solution = HashMap::new();
+        for (name, value) in variables.iter().zip(line.split('\t')) {
+            if !value.is_empty() {
+                solution.insert(name.clone(), tsv_term(value)?);
+            }
+        }
+        solutions.push(solution);
+    }
+    Ok(Expected::Solutions(variables, solutions))
+}
+
+/// Parses a TSV value, which is in N-Triples syntax or a number written as
+/// in Turtle.
+fn tsv_term(value: &str) -> Result<Term, Error> {
+    let datatype = match value {
+        _ if value.parse::<i64>().is_ok() => "integer",
+        _ if value.contains(['e', 'E']) && value.parse::<f64>().is_ok() => "double",
+        _ if value.contains('.') && value.parse::<f64>().is_ok() => "decimal",
+        _ => return Ok(value.parse()?),
+    };
+    let datatype = NamedNode::new(format!("http://www.w3.org/2001/XMLSchema#{datatype}"))?;
+    Ok(Literal::new_typed_literal(value, datatype).into())
+}
+
+fn make_term(
+    kind: &[u8],
+    value: &str,
+    language: Option<String>,
+    datatype: Option<String>,
+) -> Result<Term, Error> {
+    Ok(match (kind, language, datatype) {
+        (b"uri", _, _) => NamedNode::new(value)?.into(),
+        (b"bnode", _, _) => BlankNode::new(value)?.into(),
+        (_, Some(language), _) => Literal::new_language_tagged_literal(value, language)?.into(),
+        (_, None, Some(datatype)) => {
+            Literal::new_typed_literal(value, NamedNode::new(datatype)?).into()
+        }
+        _ => Literal::new_simple_literal(value).into(),
+    })
+}
+
+/// Reads results written in RDF with the result set vocabulary.
+fn result_set(quads: Vec<Quad>) -> Result<Expected, Error> {
+    let mut graph = Graph::new();
+    for quad in &quads {
+        graph.insert(TripleRef::from(quad.as_ref()));
+    }
+    let rs = |name: &str| NamedNode::new_unchecked(format!("{RS}{name}"));
+    let set = graph
+        .subjects_for_predicate_object(&rdf::TYPE.into_owned(), &rs("ResultSet"))
+        .next()
+        .ok_or("no result set")?
+        .into_owned();
+    if let Some(Term::Literal(boolean)) = object(&graph, &set, &rs("boolean")) {
+        return Ok(Expected::Boolean(boolean.value() == "true"));
+    }
+    let variables = objects(&graph, &set, &rs("resultVariable"))
+        .into_iter()
+        .filter_map(|variable| match variable {
+            Term::Literal(name) => Some(name.value().to_string()),
+            _ => None,
+        })
+        .collect();
+    let mut solutions = Vec::new();
+    for solution in objects(&graph, &set, &rs("solution")) {
+        let solution = as_subject(&solution).ok_or("solution is a literal")?;
+        let mut bindings = HashMap::new();
+        for binding in objects(&graph, &solution, &rs("binding")) {
+            let binding = as_subject(&binding).ok_or("binding is a literal")?;
+            let name = match object(&graph, &binding, &rs("variable")) {
+                Some(Term::Literal(name)) => name.value().to_string(),
+                _ => return Err("binding without a variable".into()),
+            };
+            let value = object(&graph, &binding, &rs("value")).ok_or("binding without a value")?;
+            bindings.insert(name, value);
+        }
+        solutions.push(bindings);
+    }
+    Ok(Expected::Solutions(variables, solutions))
+}
+
+/// Runs the tests of a manifest, failing if any test not listed in
+/// `failures` fails.
+fn run_suite(manifest: &Path, failures: Option<&Path>) -> Result<(), Error> {
+    let mut report = Report::default();
+    run_manifest(manifest, &mut report)?;
+    let known: HashSet<_> = match failures {
+        Some(failures) => fs::read_to_string(failures)?
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect(),
+        None => HashSet::new(),
+    };
+    let unexpected: Vec<_> = report
+        .failed
+        .iter()
+        .filter(|(test, _)| !known.contains(test.trim_matches(['<', '>'])))
+        .collect();
+    eprintln!(
+        "{}: {} passed, {} skipped, {} failed ({} known)",
+        manifest.display(),
+        report.passed,
+        report.skipped,
+        report.failed.len(),
+        report.failed.len() - unexpected.len(),
+    );
+    for (test, error) in &unexpected {
+        eprintln!("FAILED {test}: {error}");
+    }
+    assert!(unexpected.is_empty(), "{} tests failed", unexpected.len());
+    assert!(report.passed > 0, "no test was run");
+    Ok(())
+}
+
+#[test]
+fn test_local_suite() -> Result<(), Error> {
+    let manifest = fs::canonicalize("resources/w3c/manifest.ttl")?;
+    run_suite(&manifest, None)
+}
+
+#[test]
+#[ignore = "needs a checkout of w3c/rdf-tests in W3C_RDF_TESTS"]
+fn test_w3c_sparql11_suite() -> Result<(), Error> {
+    let root = std::env::var("W3C_RDF_TESTS").map_err(|_| "W3C_RDF_TESTS is not set")?;
+    let root = fs::canonicalize(root)?.join("sparql/sparql11");
+    for manifest in [
+        "manifest-sparql11-query.ttl",
+        "manifest-sparql11-update.ttl",
+    ] {
+        let failures = Path::new("resources/w3c/failures.txt");
+        run_suite(&root.join(manifest), Some(failures))?;
+    }
+    Ok(())
+}
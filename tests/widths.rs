@@ -0,0 +1,60 @@
+use std::fs;
+
+use zarrdf::config::ConfigBuilder;
+use zarrdf::query::BasicGraphPattern;
+use zarrdf::storage::Storage;
+use zarrs::array::DataType;
+use zarrs::filesystem::FilesystemStore;
+
+#[test]
+fn test_narrow_widths() -> Result<(), zarrdf::error::ZarrDfError> {
+    let dir = tempfile::tempdir()?;
+    let store = FilesystemStore::new(dir.path().join("widths_narrow.zarr"))?;
+    let storage = Storage::from_rdf_and_store("resources/input.ttl", store)?;
+    for array in [&storage.subject, &storage.predicate, &storage.object] {
+        assert_eq!(array.as_ref().unwrap().data_type(), &DataType::UInt8);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_widen_on_read() -> Result<(), Box<dyn std::error::Error>> {
+    // 300 objects do not fit in a byte, while predicates and graphs do
+    let dir = tempfile::tempdir()?;
+    let rdf = dir.path().join("input.nt");
+    let content: String = (0..300)
+        .map(|i| {
+            format!(
+                "<http://example.org/s{}> <http://example.org/p> \"{i}\" .\n",
+                i % 3
+            )
+        })
+        .collect();
+    fs::write(&rdf, content)?;
+    let rdf = rdf.to_str().unwrap();
+
+    for delta in [false, true] {
+        let config = ConfigBuilder::new().delta(delta).build();
+        let store = FilesystemStore::new(dir.path().join(format!("{delta}.zarr")))?;
+        let storage = Storage::from_rdf_and_store_with_config(rdf, store, &config)?;
+
+        let data_types = [&storage.subject, &storage.predicate, &storage.object]
+            .map(|array| array.as_ref().unwrap().data_type().clone());
+        assert_eq!(
+            data_types,
+            [DataType::UInt16, DataType::UInt16, DataType::UInt8]
+        );
+
+        storage.verify()?;
+        let triples = storage.triples_matching(None, None, None)?;
+        assert_eq!(triples.len(), 300);
+        let object = storage.dictionary.get_object_idx("\"299\"");
+        let triples = storage.triples_matching(None, None, object)?;
+        assert_eq!(triples.len(), 1);
+        assert_eq!(
+            storage.dictionary.triple(triples[0])?.subject.to_string(),
+            "<http://example.org/s2>"
+        );
+    }
+    Ok(())
+}